@@ -0,0 +1,4 @@
+// Makes sure the embedded migrations get picked up again whenever one is added or changed.
+fn main() {
+    println!("cargo:rerun-if-changed=migrations");
+}
@@ -1,10 +1,11 @@
--- Adminer 5.3.0 PostgreSQL 17.5 dump
+-- The schema as it was originally deployed from the Adminer dump (schema.sql).
+--
+-- Everything is created only if it doesn't exist yet, so that databases set up from that dump can adopt migrations
+-- without losing any data.
 
-DROP TABLE IF EXISTS "kennelings";
-DROP SEQUENCE IF EXISTS kennelings_id_seq;
-CREATE SEQUENCE kennelings_id_seq INCREMENT 1 MINVALUE 1 MAXVALUE 2147483647 CACHE 1;
+CREATE SEQUENCE IF NOT EXISTS kennelings_id_seq INCREMENT 1 MINVALUE 1 MAXVALUE 2147483647 CACHE 1;
 
-CREATE TABLE "public"."kennelings" (
+CREATE TABLE IF NOT EXISTS "public"."kennelings" (
     "guild_id" character varying(128) NOT NULL,
     "victim_id" character varying(128) NOT NULL,
     "kennel_length" interval NOT NULL,
@@ -16,11 +17,10 @@ CREATE TABLE "public"."kennelings" (
 )
 WITH (oids = false);
 
-CREATE INDEX kennelings_released_at ON public.kennelings USING btree (released_at);
+CREATE INDEX IF NOT EXISTS kennelings_released_at ON public.kennelings USING btree (released_at);
 
 
-DROP TABLE IF EXISTS "servers";
-CREATE TABLE "public"."servers" (
+CREATE TABLE IF NOT EXISTS "public"."servers" (
     "guild_id" character varying(128) NOT NULL,
     "command_name" text DEFAULT 'kennel' NOT NULL,
     "announcement_message" text DEFAULT '$victim has been locked away in the kennel.' NOT NULL,
@@ -32,7 +32,4 @@ CREATE TABLE "public"."servers" (
 )
 WITH (oids = false);
 
-CREATE INDEX servers_guild_id_command_name ON public.servers USING btree (guild_id, command_name);
-
-
--- 2025-07-31 00:38:25 UTC
\ No newline at end of file
+CREATE INDEX IF NOT EXISTS servers_guild_id_command_name ON public.servers USING btree (guild_id, command_name);
@@ -0,0 +1,51 @@
+-- Everything that was added to schema.sql after it was first deployed: scheduled releases, early releases, pausing,
+-- reasons, role stripping, stacking policies, punishment modes, immunity, length limits, and public stats.
+
+-- `released_at` has to account for time spent paused.
+ALTER TABLE "public"."kennelings"
+    ADD COLUMN "paused_at" timestamp,
+    ADD COLUMN "paused_length" interval DEFAULT '0' NOT NULL,
+    DROP COLUMN "released_at",
+    ADD COLUMN "released_at" timestamp GENERATED ALWAYS AS (((kenneled_at + kennel_length) + paused_length)) STORED NOT NULL;
+
+CREATE INDEX kennelings_released_at ON public.kennelings USING btree (released_at);
+
+ALTER TABLE "public"."kennelings"
+    ADD COLUMN "released" boolean DEFAULT false NOT NULL,
+    ADD COLUMN "announcement_channel" character varying(128),
+    ADD COLUMN "announcement_message" character varying(128),
+    ADD COLUMN "ended_at" timestamp,
+    ADD COLUMN "ended_by" character varying(128),
+    ADD COLUMN "end_reason" text,
+    ADD COLUMN "reason" text,
+    ADD COLUMN "stripped_roles" character varying(128)[];
+
+-- Kennelings that ran out before the scheduler existed were already dealt with, so it shouldn't release them again.
+UPDATE "public"."kennelings"
+SET
+    released = true
+WHERE
+    released_at <= CURRENT_TIMESTAMP;
+
+
+ALTER TABLE "public"."servers"
+    ALTER COLUMN "role_id" DROP NOT NULL,
+    ADD COLUMN "stacking_policy" text DEFAULT 'reject' NOT NULL,
+    ADD COLUMN "punishment_mode" text DEFAULT 'role' NOT NULL,
+    ADD COLUMN "strip_roles" boolean DEFAULT false NOT NULL,
+    ADD COLUMN "immune_roles" character varying(128)[] DEFAULT '{}' NOT NULL,
+    ADD COLUMN "immune_users" character varying(128)[] DEFAULT '{}' NOT NULL,
+    ADD COLUMN "min_kennel_length" interval,
+    ADD COLUMN "max_kennel_length" interval,
+    ADD COLUMN "count_publicly" boolean DEFAULT true NOT NULL,
+    ADD CONSTRAINT "servers_stacking_policy_check" CHECK (stacking_policy IN ('reject', 'replace', 'extend', 'max')),
+    ADD CONSTRAINT "servers_punishment_mode_check" CHECK (punishment_mode IN ('role', 'timeout'));
+
+
+CREATE TABLE "public"."role_kennel_limits" (
+    "guild_id" character varying(128) NOT NULL,
+    "role_id" character varying(128) NOT NULL,
+    "max_kennel_length" interval NOT NULL,
+    CONSTRAINT "role_kennel_limits_pkey" PRIMARY KEY ("guild_id", "role_id")
+)
+WITH (oids = false);
@@ -0,0 +1,46 @@
+-- Discord IDs are 64 bit integers, so they're stored as such instead of as strings. Every ID Discord hands out is
+-- positive, which the checks make sure of.
+
+ALTER TABLE "public"."kennelings"
+    ALTER COLUMN "guild_id" TYPE bigint USING guild_id::bigint,
+    ALTER COLUMN "victim_id" TYPE bigint USING victim_id::bigint,
+    ALTER COLUMN "author_id" TYPE bigint USING author_id::bigint,
+    ALTER COLUMN "announcement_channel" TYPE bigint USING announcement_channel::bigint,
+    ALTER COLUMN "announcement_message" TYPE bigint USING announcement_message::bigint,
+    ALTER COLUMN "ended_by" TYPE bigint USING ended_by::bigint,
+    ALTER COLUMN "stripped_roles" TYPE bigint[] USING stripped_roles::bigint[],
+    ADD CONSTRAINT "kennelings_snowflakes_check" CHECK (
+        guild_id > 0
+        AND victim_id > 0
+        AND author_id > 0
+        AND announcement_channel > 0
+        AND announcement_message > 0
+        AND ended_by > 0
+        AND 0 < ALL(stripped_roles)
+    );
+
+ALTER TABLE "public"."servers"
+    ALTER COLUMN "guild_id" TYPE bigint USING guild_id::bigint,
+    ALTER COLUMN "role_id" TYPE bigint USING role_id::bigint,
+    ALTER COLUMN "kennel_channel" TYPE bigint USING kennel_channel::bigint,
+    ALTER COLUMN "immune_roles" DROP DEFAULT,
+    ALTER COLUMN "immune_roles" TYPE bigint[] USING immune_roles::bigint[],
+    ALTER COLUMN "immune_roles" SET DEFAULT '{}',
+    ALTER COLUMN "immune_users" DROP DEFAULT,
+    ALTER COLUMN "immune_users" TYPE bigint[] USING immune_users::bigint[],
+    ALTER COLUMN "immune_users" SET DEFAULT '{}',
+    ADD CONSTRAINT "servers_snowflakes_check" CHECK (
+        guild_id > 0
+        AND role_id > 0
+        AND kennel_channel > 0
+        AND 0 < ALL(immune_roles)
+        AND 0 < ALL(immune_users)
+    );
+
+ALTER TABLE "public"."role_kennel_limits"
+    ALTER COLUMN "guild_id" TYPE bigint USING guild_id::bigint,
+    ALTER COLUMN "role_id" TYPE bigint USING role_id::bigint,
+    ADD CONSTRAINT "role_kennel_limits_snowflakes_check" CHECK (
+        guild_id > 0
+        AND role_id > 0
+    );
@@ -0,0 +1,5 @@
+-- Whether taking the kennel punishment off of someone by hand ends their kenneling early, or gets it put back.
+
+ALTER TABLE "public"."servers"
+    ADD COLUMN "manual_release" text DEFAULT 'honor' NOT NULL,
+    ADD CONSTRAINT "servers_manual_release_check" CHECK (manual_release IN ('honor', 'enforce'));
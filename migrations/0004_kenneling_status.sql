@@ -0,0 +1,36 @@
+-- Kennelings keep track of how they ended, instead of having their length rewritten to the time served.
+
+ALTER TABLE "public"."kennelings"
+    ADD COLUMN "status" text DEFAULT 'active' NOT NULL,
+    ADD COLUMN "original_length" interval;
+
+-- Lengths that were already cut down to the time served can't be recovered, so those start from what's left.
+UPDATE "public"."kennelings"
+SET
+    original_length = kennel_length,
+    status = CASE
+        WHEN NOT released AND paused_at IS NOT NULL THEN 'paused'
+        WHEN NOT released THEN 'active'
+        WHEN end_reason = 'Replaced by a new kenneling' THEN 'revoked'
+        WHEN ended_at IS NOT NULL THEN 'released_early'
+        ELSE 'released_on_time'
+    END;
+
+ALTER TABLE "public"."kennelings"
+    ALTER COLUMN "original_length" SET NOT NULL,
+    DROP COLUMN "released",
+    ADD CONSTRAINT "kennelings_status_check" CHECK (status IN ('active', 'released_on_time', 'released_early', 'revoked', 'paused', 'failed'));
+
+CREATE INDEX kennelings_status ON public.kennelings USING btree (status);
+
+-- How long a kenneling counts for in the stats: its whole sentence while it's still going, and the time actually
+-- served once it's over. Time spent away doesn't count, and neither does anything past the sentence.
+CREATE FUNCTION served_length(kennelings) RETURNS interval AS $$
+    SELECT CASE
+        WHEN $1.status IN ('active', 'paused') THEN $1.kennel_length
+        ELSE LEAST(
+            $1.kennel_length,
+            GREATEST(COALESCE($1.paused_at, $1.ended_at, $1.released_at) - $1.kenneled_at - $1.paused_length, '0')
+        )
+    END
+$$ LANGUAGE SQL STABLE;
@@ -0,0 +1,6 @@
+-- Kennelings can be scheduled to start later. Until then they're pending, with `kenneled_at` set to when they start,
+-- and they can be cancelled before they do.
+
+ALTER TABLE "public"."kennelings"
+    DROP CONSTRAINT "kennelings_status_check",
+    ADD CONSTRAINT "kennelings_status_check" CHECK (status IN ('pending', 'cancelled', 'active', 'released_on_time', 'released_early', 'revoked', 'paused', 'failed'));
@@ -0,0 +1,28 @@
+-- Kennelings that happen over and over, like a bedtime lockout every weeknight. Each time one comes up, it's turned
+-- into a pending kenneling, which the scheduler starts like any other.
+
+CREATE TABLE "public"."kennel_schedules" (
+    "id" serial NOT NULL,
+    "guild_id" bigint NOT NULL,
+    "victim_id" bigint NOT NULL,
+    "author_id" bigint NOT NULL,
+    -- One bit per day of the week, starting with Monday.
+    "weekdays" smallint NOT NULL,
+    -- On the clock in the schedule's timezone, which is an IANA name like 'Europe/Berlin'.
+    "start_time" time NOT NULL,
+    "timezone" text NOT NULL,
+    "kennel_length" interval NOT NULL,
+    "reason" text,
+    "paused" boolean DEFAULT false NOT NULL,
+    "next_run" timestamp NOT NULL,
+    CONSTRAINT "kennel_schedules_pkey" PRIMARY KEY ("id"),
+    CONSTRAINT "kennel_schedules_snowflakes_check" CHECK (
+        guild_id > 0
+        AND victim_id > 0
+        AND author_id > 0
+    ),
+    CONSTRAINT "kennel_schedules_weekdays_check" CHECK (weekdays BETWEEN 1 AND 127)
+)
+WITH (oids = false);
+
+CREATE INDEX kennel_schedules_next_run ON public.kennel_schedules USING btree (next_run) WHERE NOT paused;
@@ -0,0 +1,11 @@
+-- Members can vote to kennel someone, if the server sets how many votes it takes.
+
+ALTER TABLE "public"."servers"
+    -- How many members have to approve for a vote to pass, or deny for it to fail. Voting is off while it's NULL.
+    ADD COLUMN "vote_threshold" integer,
+    ADD COLUMN "vote_window" interval DEFAULT '10 minutes' NOT NULL,
+    ADD CONSTRAINT "servers_vote_threshold_check" CHECK (vote_threshold > 0);
+
+-- Kennelings that went through because of a vote have no one person behind them.
+ALTER TABLE "public"."kennelings"
+    ADD COLUMN "community_vote" boolean DEFAULT false NOT NULL;
@@ -0,0 +1,22 @@
+-- A victim serves at most one kenneling per guild at a time. Kennelings that raced each other could both get in before,
+-- so all but the one that ends last are revoked first, the way stacking would have replaced them.
+
+UPDATE "public"."kennelings" AS kenneling
+SET
+    status = 'revoked',
+    ended_at = CURRENT_TIMESTAMP,
+    end_reason = 'Replaced by a new kenneling'
+WHERE
+    status IN ('active', 'paused')
+    AND EXISTS (
+        SELECT 1
+        FROM "public"."kennelings" AS other
+        WHERE
+            other.guild_id = kenneling.guild_id
+            AND other.victim_id = kenneling.victim_id
+            AND other.status IN ('active', 'paused')
+            AND (other.released_at, other.id) > (kenneling.released_at, kenneling.id)
+    );
+
+CREATE UNIQUE INDEX kennelings_one_running_per_victim ON public.kennelings USING btree (guild_id, victim_id)
+    WHERE status IN ('active', 'paused');
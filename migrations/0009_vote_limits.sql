@@ -0,0 +1,13 @@
+-- Whoever proposes a vote approves of it, so a threshold of 1 would let anyone kennel on their own. It takes at least
+-- one other member now.
+--
+-- Votes are updated through the interaction that started them, which Discord stops accepting after 15 minutes, so
+-- they have to close before then.
+
+UPDATE "public"."servers" SET vote_threshold = 2 WHERE vote_threshold = 1;
+UPDATE "public"."servers" SET vote_window = '14 minutes' WHERE vote_window >= '15 minutes';
+
+ALTER TABLE "public"."servers"
+    DROP CONSTRAINT "servers_vote_threshold_check",
+    ADD CONSTRAINT "servers_vote_threshold_check" CHECK (vote_threshold > 1),
+    ADD CONSTRAINT "servers_vote_window_check" CHECK (vote_window < '15 minutes');
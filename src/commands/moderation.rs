@@ -0,0 +1,272 @@
+//! Contains commands for managing kennelings that are already underway.
+
+use anyhow::{Result, anyhow};
+use serenity::all::UserId;
+use shame_bot::util::get_guild_id::GetGuildID;
+use shame_bot::util::stefan_traits::*;
+use shame_bot::{Context, get_formatted_message, types::*};
+use std::time::Duration;
+
+use crate::ShameBotData;
+use crate::commands::wildcard::too_long_message;
+
+/// Releases someone from the kennel early.
+#[poise::command(slash_command, required_permissions = "MODERATE_MEMBERS")]
+pub async fn unkennel(
+    ctx: Context<'_>,
+    #[description = "User to release"] user: UserId,
+    #[description = "Why they're being released"]
+    #[max_length = 512]
+    reason: Option<String>,
+) -> Result<()> {
+    let ShameBotData { storage, .. } = ctx.data();
+    let storage = storage.as_ref();
+    let guild_id = ctx.require_guild().await?;
+
+    let Some(kenneling) = storage.active_kenneling(guild_id, user).await? else {
+        return ctx
+            .reply_ephemeral(format!("<@{user}> isn't in the kennel!"))
+            .await;
+    };
+
+    let Some(kenneling) = kenneling
+        .release_early(ctx.http(), storage, ctx.author().id, reason.as_deref())
+        .await?
+    else {
+        return ctx
+            .reply_ephemeral(format!("<@{user}> was already released!"))
+            .await;
+    };
+
+    let time_served = humantime::format_duration(kenneling.time_served(chrono::Utc::now()));
+
+    tracing::info!(
+        "{} released {} early after {time_served}",
+        ctx.author().display_name(),
+        user,
+    );
+
+    let mut reply = format!("Released <@{user}> from the kennel after {time_served}.");
+
+    if let Some(reason) = &kenneling.end_reason {
+        reply.push_str(&format!(" Reason: {reason}"));
+    }
+
+    ctx.reply(reply).await?;
+
+    Ok(())
+}
+
+/// The message announcing an adjusted sentence. Uses the same placeholders as the configurable messages.
+const ADJUSTMENT_MESSAGE: &str = "$victim's sentence is now $time. They will return $return.";
+
+/// Adds time to, takes time off of, or sets the length of someone's kenneling.
+#[poise::command(slash_command, required_permissions = "MODERATE_MEMBERS")]
+pub async fn kennel_adjust(
+    ctx: Context<'_>,
+    #[description = "User to adjust the sentence of"] user: UserId,
+    #[description = "Like '+30m' to add time, '-10m' to take it off, or '=2h' to set it"]
+    adjustment: String,
+) -> Result<()> {
+    let ShameBotData { storage, .. } = ctx.data();
+    let storage = storage.as_ref();
+    let guild_id = ctx.require_guild().await?;
+
+    let Ok(adjustment) = adjustment.parse::<LengthAdjustment>() else {
+        return ctx
+            .reply_ephemeral("Invalid adjustment! Say something like '+30m', '-10m', or '=2h'")
+            .await;
+    };
+
+    let Some(kenneling) = storage.active_kenneling(guild_id, user).await? else {
+        return ctx
+            .reply_ephemeral(format!("<@{user}> isn't in the kennel!"))
+            .await;
+    };
+
+    let Some(kennel_length) = adjustment
+        .apply(kenneling.kennel_length)
+        .filter(|length| *length >= Duration::from_secs(1))
+    else {
+        return ctx
+            .reply_ephemeral("That would leave less than 1 second! Use `/unkennel` instead.")
+            .await;
+    };
+
+    // A sentence that's already been served would have them return in the past.
+    let time_served = kenneling.time_served(chrono::Utc::now());
+
+    if kennel_length <= time_served {
+        return ctx
+            .reply_ephemeral(format!(
+                "<@{user}> has already served {}! Use `/unkennel` to let them out.",
+                humantime::format_duration(time_served)
+            ))
+            .await;
+    }
+
+    let server = storage
+        .server(guild_id)
+        .await?
+        .ok_or_else(|| anyhow!("Server {guild_id} isn't set up"))?;
+
+    let author = ctx.http().get_member(guild_id, ctx.author().id).await?;
+    let limits = KennelLimits::for_author(storage, &server, &author).await?;
+
+    if let Err(refusal) = limits.check(kennel_length) {
+        return ctx.reply_ephemeral(refusal.to_string()).await;
+    }
+
+    if !server.can_enforce_until(kenneling.released_at - kenneling.kennel_length + kennel_length) {
+        return ctx.reply_ephemeral(too_long_message(&server)).await;
+    }
+
+    let Some(kenneling) = kenneling.set_length(storage, kennel_length).await? else {
+        return ctx
+            .reply_ephemeral(format!("<@{user}> was released in the meantime!"))
+            .await;
+    };
+
+    tracing::info!(
+        "{} adjusted {}'s sentence to {}",
+        ctx.author().display_name(),
+        user,
+        humantime::format_duration(kenneling.kennel_length)
+    );
+
+    // Timeouts carry their own end time, so they have to be moved along with the release.
+    if server.punishment_mode == PunishmentMode::Timeout && kenneling.paused_at.is_none() {
+        kenneling.apply_kennel(ctx.http(), &server, None).await?;
+    }
+
+    let adjustment_msg = get_formatted_message(
+        ADJUSTMENT_MESSAGE,
+        &kenneling.victim_id,
+        &format!("<@{}>", ctx.author().id),
+        &humantime::format_duration(kenneling.kennel_length).to_string(),
+        &kenneling.released_at.discord_relative_timestamp(),
+        kenneling.display_reason(),
+    );
+
+    ctx.reply(&adjustment_msg).await?;
+
+    if let Some(kennel_channel) = server.kennel_channel
+        && kennel_channel != ctx.channel_id()
+        && let Err(e) = kennel_channel
+            .send_message(
+                ctx.http(),
+                serenity::all::CreateMessage::new().content(adjustment_msg),
+            )
+            .await
+    {
+        tracing::error!("Couldn't send adjustment message! {e:?}");
+    }
+
+    Ok(())
+}
+
+/// Manages kennelings that are scheduled to start later.
+#[poise::command(
+    slash_command,
+    required_permissions = "MODERATE_MEMBERS",
+    subcommands("pending_list", "pending_cancel"),
+    subcommand_required
+)]
+pub async fn kennel_pending(_ctx: Context<'_>) -> Result<()> {
+    Ok(())
+}
+
+/// Lists the kennelings that haven't started yet, soonest first.
+#[poise::command(
+    slash_command,
+    rename = "list",
+    required_permissions = "MODERATE_MEMBERS"
+)]
+pub async fn pending_list(ctx: Context<'_>) -> Result<()> {
+    let ShameBotData { storage, .. } = ctx.data();
+    let guild_id = ctx.require_guild().await?;
+
+    let pending = storage.pending_kennelings(Some(guild_id)).await?;
+
+    if pending.is_empty() {
+        return ctx
+            .reply_ephemeral("Nobody is scheduled to be kenneled!")
+            .await;
+    }
+
+    let lines = pending
+        .iter()
+        .map(|kenneling| {
+            format!(
+                "`#{}` <@{}> starts {} for {}, by <@{}>: {}",
+                kenneling.id.unwrap_or_default(),
+                kenneling.victim_id,
+                kenneling.kenneled_at.discord_relative_timestamp(),
+                humantime::format_duration(kenneling.kennel_length),
+                kenneling.author_id,
+                kenneling.display_reason()
+            )
+        })
+        .collect::<Vec<_>>();
+
+    ctx.reply_ephemeral(lines.join("\n")).await
+}
+
+/// Calls off a scheduled kenneling before it starts.
+#[poise::command(
+    slash_command,
+    rename = "cancel",
+    required_permissions = "MODERATE_MEMBERS"
+)]
+pub async fn pending_cancel(
+    ctx: Context<'_>,
+    #[description = "The number of the scheduled kenneling, from `/kennel_pending list`"] id: i32,
+    #[description = "Why it's being called off"]
+    #[max_length = 512]
+    reason: Option<String>,
+) -> Result<()> {
+    let ShameBotData { storage, .. } = ctx.data();
+    let storage = storage.as_ref();
+    let guild_id = ctx.require_guild().await?;
+
+    let Some(kenneling) = storage
+        .pending_kennelings(Some(guild_id))
+        .await?
+        .into_iter()
+        .find(|kenneling| kenneling.id == Some(id))
+    else {
+        return ctx
+            .reply_ephemeral(format!("There's no scheduled kenneling #{id}!"))
+            .await;
+    };
+
+    let Some(kenneling) = kenneling
+        .cancel(
+            ctx.http(),
+            storage,
+            Some(ctx.author().id),
+            reason.as_deref(),
+        )
+        .await?
+    else {
+        return ctx
+            .reply_ephemeral(format!("Scheduled kenneling #{id} already started!"))
+            .await;
+    };
+
+    tracing::info!(
+        "{} cancelled scheduled kenneling {id} of {}",
+        ctx.author().display_name(),
+        kenneling.victim_id
+    );
+
+    let mut reply = format!("Called off <@{}>'s kenneling.", kenneling.victim_id);
+
+    if let Some(reason) = &kenneling.end_reason {
+        reply.push_str(&format!(" Reason: {reason}"));
+    }
+
+    ctx.reply(reply).await?;
+
+    Ok(())
+}
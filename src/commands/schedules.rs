@@ -0,0 +1,326 @@
+//! Contains commands for kennelings that happen over and over, like a bedtime lockout.
+
+use anyhow::Result;
+use chrono_tz::{TZ_VARIANTS, Tz};
+use serenity::all::UserId;
+use shame_bot::util::discord_errors::is_missing_member;
+use shame_bot::util::get_guild_id::GetGuildID;
+use shame_bot::util::start_time::parse_time_of_day;
+use shame_bot::util::stefan_traits::*;
+use shame_bot::{Context, types::*};
+use std::time::Duration;
+
+use crate::ShameBotData;
+use crate::commands::wildcard::too_long_message;
+
+/// Manages kennelings that happen over and over.
+#[poise::command(
+    slash_command,
+    required_permissions = "MODERATE_MEMBERS",
+    subcommands(
+        "schedule_create",
+        "schedule_list",
+        "schedule_pause",
+        "schedule_resume",
+        "schedule_delete"
+    ),
+    subcommand_required
+)]
+pub async fn kennel_schedule(_ctx: Context<'_>) -> Result<()> {
+    Ok(())
+}
+
+/// Suggests timezones that match what's been typed so far.
+async fn autocomplete_timezone(_ctx: Context<'_>, partial: &str) -> Vec<String> {
+    let partial = partial.to_lowercase();
+
+    TZ_VARIANTS
+        .iter()
+        .map(|timezone| timezone.name())
+        .filter(|name| name.to_lowercase().contains(&partial))
+        // Discord's limit on autocomplete choices.
+        .take(25)
+        .map(str::to_string)
+        .collect()
+}
+
+/// Kennels someone on the same days at the same time, over and over.
+#[poise::command(
+    slash_command,
+    rename = "create",
+    required_permissions = "MODERATE_MEMBERS"
+)]
+#[allow(clippy::too_many_arguments)]
+pub async fn schedule_create(
+    ctx: Context<'_>,
+    #[description = "User to kennel"] user: UserId,
+    #[description = "Which days, like 'weekdays', 'sun-thu' or 'mon, wed'"] days: String,
+    #[description = "When each kenneling starts, like '23:00' or '11pm'"] time: String,
+    #[description = "How long each kenneling lasts, like '8h'"] length: String,
+    #[description = "The timezone the time is in, like 'Europe/Berlin'. Defaults to UTC"]
+    #[autocomplete = "autocomplete_timezone"]
+    timezone: Option<String>,
+    #[description = "Why they're being kenneled"]
+    #[max_length = 512]
+    reason: Option<String>,
+) -> Result<()> {
+    let ShameBotData { storage, .. } = ctx.data();
+    let storage = storage.as_ref();
+    let guild_id = ctx.require_guild().await?;
+
+    let weekdays = match days.parse::<Weekdays>() {
+        Ok(weekdays) => weekdays,
+        Err(e) => {
+            return ctx
+                .reply_ephemeral(format!(
+                    "{e}! Say something like 'weekdays', 'sun-thu' or 'mon, wed'"
+                ))
+                .await;
+        }
+    };
+
+    let Some(start_time) = parse_time_of_day(&time) else {
+        return ctx
+            .reply_ephemeral("Invalid time! Say something like '23:00' or '11pm'")
+            .await;
+    };
+
+    let Ok(kennel_length) = humantime::parse_duration(&length) else {
+        return ctx
+            .reply_ephemeral("Invalid time format! Say something like '3m' or '1h'")
+            .await;
+    };
+
+    if kennel_length < Duration::from_secs(1) {
+        return ctx.reply_ephemeral("Over 1 second, please...").await;
+    }
+
+    let Ok(timezone) = timezone.as_deref().unwrap_or("UTC").parse::<Tz>() else {
+        return ctx
+            .reply_ephemeral("Unknown timezone! Pick one from the list, like 'Europe/Berlin'")
+            .await;
+    };
+
+    let Some(server) = storage.server(guild_id).await? else {
+        return ctx.reply_ephemeral("Set kennel role first!").await;
+    };
+
+    if server.punishment_mode == PunishmentMode::Role && server.role_id.is_none() {
+        return ctx.reply_ephemeral("Set kennel role first!").await;
+    }
+
+    let http = ctx.http();
+
+    let victim = match http.get_member(guild_id, user).await {
+        Ok(victim) => victim,
+        Err(e) if is_missing_member(&e) => {
+            return ctx
+                .reply_ephemeral(format!("<@{user}> isn't in this server!"))
+                .await;
+        }
+        Err(e) => return Err(e.into()),
+    };
+
+    let guild = http.get_guild(guild_id).await?;
+    let bot = http.get_member(guild_id, ctx.framework().bot_id).await?;
+    let author = http.get_member(guild_id, ctx.author().id).await?;
+
+    if let Err(refusal) = KennelRefusal::check(&server, &guild, &bot, &author, &victim) {
+        return ctx.reply_ephemeral(refusal.to_string()).await;
+    }
+
+    let limits = KennelLimits::for_author(storage, &server, &author).await?;
+
+    if let Err(refusal) = limits.check(kennel_length) {
+        return ctx.reply_ephemeral(refusal.to_string()).await;
+    }
+
+    let now = chrono::Utc::now();
+
+    if !server.can_enforce_until(now + kennel_length) {
+        return ctx.reply_ephemeral(too_long_message(&server)).await;
+    }
+
+    let mut schedule = KennelSchedule {
+        reason,
+        ..KennelSchedule::new(
+            guild_id,
+            user,
+            ctx.author().id,
+            weekdays,
+            start_time,
+            timezone,
+            kennel_length,
+            now,
+        )?
+    };
+
+    let id = storage.insert_schedule(&schedule).await?;
+    schedule.id = Some(id);
+
+    tracing::info!(
+        "{} scheduled {user} to be kenneled {}",
+        ctx.author().display_name(),
+        schedule.describe()
+    );
+
+    ctx.reply(format!(
+        "<@{user}> will be locked away in the kennel {}, starting {}. Reason: {} (schedule #{id})",
+        schedule.describe(),
+        schedule.next_run.discord_relative_timestamp(),
+        schedule.display_reason()
+    ))
+    .await?;
+
+    Ok(())
+}
+
+/// Lists the kennel schedules in this server.
+#[poise::command(
+    slash_command,
+    rename = "list",
+    required_permissions = "MODERATE_MEMBERS"
+)]
+pub async fn schedule_list(ctx: Context<'_>) -> Result<()> {
+    let ShameBotData { storage, .. } = ctx.data();
+    let guild_id = ctx.require_guild().await?;
+
+    let schedules = storage.schedules(guild_id).await?;
+
+    if schedules.is_empty() {
+        return ctx.reply_ephemeral("Nobody is on a kennel schedule!").await;
+    }
+
+    let lines = schedules
+        .iter()
+        .map(|schedule| {
+            format!(
+                "`#{}` <@{}> {}, by <@{}>. {}",
+                schedule.id.unwrap_or_default(),
+                schedule.victim_id,
+                schedule.describe(),
+                schedule.author_id,
+                if schedule.paused {
+                    "**Paused**".to_string()
+                } else {
+                    format!("Next: {}", schedule.next_run.discord_relative_timestamp())
+                }
+            )
+        })
+        .collect::<Vec<_>>();
+
+    ctx.reply_ephemeral(lines.join("\n")).await
+}
+
+/// Puts a kennel schedule on hold, so that nobody is kenneled by it until it's resumed.
+#[poise::command(
+    slash_command,
+    rename = "pause",
+    required_permissions = "MODERATE_MEMBERS"
+)]
+pub async fn schedule_pause(
+    ctx: Context<'_>,
+    #[description = "The number of the schedule, from `/kennel_schedule list`"] id: i32,
+) -> Result<()> {
+    set_paused(ctx, id, true).await
+}
+
+/// Takes a kennel schedule off hold.
+#[poise::command(
+    slash_command,
+    rename = "resume",
+    required_permissions = "MODERATE_MEMBERS"
+)]
+pub async fn schedule_resume(
+    ctx: Context<'_>,
+    #[description = "The number of the schedule, from `/kennel_schedule list`"] id: i32,
+) -> Result<()> {
+    set_paused(ctx, id, false).await
+}
+
+/// Pauses or resumes a schedule. Resuming picks up from the next time the schedule comes up, so anything it missed
+/// while paused is skipped.
+async fn set_paused(ctx: Context<'_>, id: i32, paused: bool) -> Result<()> {
+    let ShameBotData { storage, .. } = ctx.data();
+    let guild_id = ctx.require_guild().await?;
+
+    let Some(schedule) = storage
+        .schedules(guild_id)
+        .await?
+        .into_iter()
+        .find(|schedule| schedule.id == Some(id))
+    else {
+        return ctx
+            .reply_ephemeral(format!("There's no kennel schedule #{id}!"))
+            .await;
+    };
+
+    let next_run = if paused {
+        schedule.next_run
+    } else {
+        schedule.occurrence_after(chrono::Utc::now())?
+    };
+
+    if !storage
+        .set_schedule_paused(guild_id, id, paused, next_run)
+        .await?
+    {
+        return ctx
+            .reply_ephemeral(format!(
+                "Kennel schedule #{id} was deleted in the meantime!"
+            ))
+            .await;
+    }
+
+    tracing::info!(
+        "{} {} kennel schedule {id}",
+        ctx.author().display_name(),
+        if paused { "paused" } else { "resumed" }
+    );
+
+    if paused {
+        ctx.reply(format!(
+            "Paused <@{}>'s kennel schedule #{id}.",
+            schedule.victim_id
+        ))
+        .await?;
+    } else {
+        ctx.reply(format!(
+            "Resumed <@{}>'s kennel schedule #{id}. The next kenneling starts {}.",
+            schedule.victim_id,
+            next_run.discord_relative_timestamp()
+        ))
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Deletes a kennel schedule. A kenneling it already started keeps going.
+#[poise::command(
+    slash_command,
+    rename = "delete",
+    required_permissions = "MODERATE_MEMBERS"
+)]
+pub async fn schedule_delete(
+    ctx: Context<'_>,
+    #[description = "The number of the schedule, from `/kennel_schedule list`"] id: i32,
+) -> Result<()> {
+    let ShameBotData { storage, .. } = ctx.data();
+    let guild_id = ctx.require_guild().await?;
+
+    if !storage.delete_schedule(guild_id, id).await? {
+        return ctx
+            .reply_ephemeral(format!("There's no kennel schedule #{id}!"))
+            .await;
+    }
+
+    tracing::info!(
+        "{} deleted kennel schedule {id}",
+        ctx.author().display_name()
+    );
+
+    ctx.reply(format!("Deleted kennel schedule #{id}.")).await?;
+
+    Ok(())
+}
@@ -1,10 +1,15 @@
 //! Contains commands for configuring the bot's usage in a given server.
 
-use ::serenity::all::{ChannelId, RoleId};
+use ::serenity::all::{ChannelId, GuildId, RoleId, UserId};
 use anyhow::Result;
 use poise::serenity_prelude as serenity;
 use regex::Regex;
-use shame_bot::{types::kenneling::*, util::get_guild_id::GetGuildID};
+use shame_bot::{
+    storage::Storage,
+    types::*,
+    util::{get_guild_id::GetGuildID, stefan_traits::SendReplyEphemeral},
+};
+use std::time::Duration;
 
 use crate::{Context, ShameBotData};
 
@@ -14,86 +19,18 @@ pub async fn set_kennel_role(
     ctx: Context<'_>,
     #[description = "The kenneling role. Must be set for the command to work"] role: serenity::Role,
 ) -> Result<()> {
-    let ShameBotData { pool } = ctx.data();
-    let pool = pool.as_ref();
-    let role_id = role.id.get();
-    let guild_id = role.guild_id.get();
-
-    if let Ok(res) = sqlx::query!(
-        r#"
-        SELECT
-            role_id
-        FROM
-            servers
-        WHERE
-            guild_id=$1
-            ;
-        "#,
-        guild_id.to_string(),
-    )
-    .fetch_one(pool)
-    .await
-    {
-        let guild = ctx
-            .partial_guild()
-            .await
-            .expect("Why is this called outside of a guild");
-        let existing_role_id: RoleId = shame_bot::string_to_id(&res.role_id)?;
-
-        let active_kennelings: Vec<Kenneling> = sqlx::query_as!(
-            KennelingRow,
-            r#"
-            SELECT *
-            FROM kennelings
-            WHERE
-                released_at > CURRENT_TIMESTAMP AND
-                guild_id = $1
-                ;
-            "#,
-            guild_id.to_string()
-        )
-        .fetch_all(pool)
+    let ShameBotData { storage, .. } = ctx.data();
+    let storage = storage.as_ref();
+    let role_id = role.id;
+    let guild_id = role.guild_id;
+    let mut server = storage
+        .server(guild_id)
         .await?
-        .iter()
-        .map(|kr| kr.try_into().expect("malformed data inserted"))
-        .collect();
+        .unwrap_or_else(|| Server::new(guild_id));
 
-        tracing::trace!(
-            "set_kennel_role called: Updating active kennelings for guild {}",
-            guild.name
-        );
-
-        for kenneling in active_kennelings {
-            tracing::trace!("Updating kenneling: {kenneling:?}");
-
-            let member = guild
-                .member(ctx.http(), kenneling.victim_id)
-                .await
-                .expect("Member must have left!");
-
-            member.remove_role(ctx.http(), existing_role_id).await?;
-            member.add_role(ctx.http(), role_id).await?;
-        }
-    }
-
-    sqlx::query!(
-        r#"
-        INSERT INTO servers
-            (guild_id, role_id) 
-        VALUES 
-            ($1, $2)
-        ON CONFLICT 
-            (guild_id)
-        DO 
-            UPDATE SET
-                role_id=$2
-            ;
-        "#,
-        guild_id.to_string(),
-        role_id.to_string()
-    )
-    .execute(pool)
-    .await?;
+    server
+        .change_kennel_role(ctx.http(), storage, role_id)
+        .await?;
 
     ctx.reply(format!(
         "Successfully set this guild's kennel role to <@&{}>!",
@@ -114,8 +51,8 @@ pub async fn set_kennel_command(
     ctx: Context<'_>,
     #[description = "The command to kennel someone. Defaults to 'kennel'"] command: Option<String>,
 ) -> Result<()> {
-    let ShameBotData { pool } = ctx.data();
-    let pool = pool.as_ref();
+    let ShameBotData { storage, .. } = ctx.data();
+    let storage = storage.as_ref();
     let command = command.unwrap_or_else(|| "kennel".to_string());
     let guild_id = ctx.require_guild().await?;
 
@@ -132,23 +69,12 @@ pub async fn set_kennel_command(
         return Ok(());
     }
 
-    let rows_affected = sqlx::query!(
-        r#"
-        UPDATE servers
-        SET 
-            command_name = $1
-        WHERE
-            guild_id = $2
-            ;
-        "#,
-        &command,
-        guild_id.get().to_string()
-    )
-    .execute(pool)
-    .await?
-    .rows_affected();
+    let updated = update_server(storage, guild_id, |server| {
+        server.command_name = command.clone()
+    })
+    .await?;
 
-    if rows_affected == 0 {
+    if !updated {
         ctx.reply("Couldn't set command! Make sure to set the kennel role using `/set_kennel_role` first!").await?;
 
         return Ok(());
@@ -174,30 +100,19 @@ pub async fn set_kennel_command(
 #[poise::command(slash_command, required_permissions = "ADMINISTRATOR")]
 pub async fn set_announcement_message(
     ctx: Context<'_>,
-    #[description = "The message to send when kenneling someone. Use $victim, $kenneler, $time, and $return to format."]
+    #[description = "The message sent when kenneling someone. Use $victim, $kenneler, $time, $return, and $reason."]
     message: String,
 ) -> Result<()> {
-    let ShameBotData { pool } = ctx.data();
-    let pool = pool.as_ref();
+    let ShameBotData { storage, .. } = ctx.data();
+    let storage = storage.as_ref();
     let guild_id = ctx.require_guild().await?;
 
-    let rows_affected = sqlx::query!(
-        r#"
-        UPDATE servers
-        SET
-            announcement_message = $1
-        WHERE 
-            guild_id = $2
-            ;
-        "#,
-        message,
-        guild_id.get().to_string(),
-    )
-    .execute(pool)
-    .await?
-    .rows_affected();
+    let updated = update_server(storage, guild_id, |server| {
+        server.announcement_message = message.clone()
+    })
+    .await?;
 
-    if rows_affected == 0 {
+    if !updated {
         ctx.reply("Couldn't set announcement message! Make sure to set the kennel role using `/set_kennel_role` first!").await?;
     } else {
         ctx.reply(format!("Set announcement message to: {message}"))
@@ -213,27 +128,16 @@ pub async fn set_kennel_message(
     ctx: Context<'_>,
     #[description = "The message to send in the kennel when kenneling someone."] message: String,
 ) -> Result<()> {
-    let ShameBotData { pool } = ctx.data();
-    let pool = pool.as_ref();
+    let ShameBotData { storage, .. } = ctx.data();
+    let storage = storage.as_ref();
     let guild_id = ctx.require_guild().await?;
 
-    let rows_affected = sqlx::query!(
-        r#"
-        UPDATE servers
-        SET
-            kennel_message = $1
-        WHERE 
-            guild_id = $2
-            ;
-        "#,
-        message,
-        guild_id.get().to_string(),
-    )
-    .execute(pool)
-    .await?
-    .rows_affected();
+    let updated = update_server(storage, guild_id, |server| {
+        server.kennel_message = message.clone()
+    })
+    .await?;
 
-    if rows_affected == 0 {
+    if !updated {
         ctx.reply("Couldn't set kenneling message! Make sure to set the kennel role using `/set_kennel_role` first!").await?;
     } else {
         ctx.reply(format!("Set kenneling message to: {message}"))
@@ -247,30 +151,19 @@ pub async fn set_kennel_message(
 #[poise::command(slash_command, required_permissions = "ADMINISTRATOR")]
 pub async fn set_release_message(
     ctx: Context<'_>,
-    #[description = "The released from kennel message. Use $victim, $kenneler, $time, and $return to format."]
+    #[description = "The released from kennel message. Use $victim, $kenneler, $time, $return, and $reason to format."]
     message: String,
 ) -> Result<()> {
-    let ShameBotData { pool } = ctx.data();
-    let pool = pool.as_ref();
+    let ShameBotData { storage, .. } = ctx.data();
+    let storage = storage.as_ref();
     let guild_id = ctx.require_guild().await?;
 
-    let rows_affected = sqlx::query!(
-        r#"
-        UPDATE servers
-        SET
-            release_message = $1
-        WHERE
-            guild_id = $2
-            ;
-        "#,
-        message,
-        guild_id.get().to_string(),
-    )
-    .execute(pool)
-    .await?
-    .rows_affected();
+    let updated = update_server(storage, guild_id, |server| {
+        server.release_message = message.clone()
+    })
+    .await?;
 
-    if rows_affected == 0 {
+    if !updated {
         ctx.reply("Couldn't set release message! Make sure to set the kennel role using `/set_kennel_role` first!").await?;
     } else {
         ctx.reply(format!("Set release message to: {message}"))
@@ -286,27 +179,16 @@ pub async fn set_kennel_channel(
     ctx: Context<'_>,
     #[description = "The kennel channel to announce in"] message: ChannelId,
 ) -> Result<()> {
-    let ShameBotData { pool } = ctx.data();
-    let pool = pool.as_ref();
+    let ShameBotData { storage, .. } = ctx.data();
+    let storage = storage.as_ref();
     let guild_id = ctx.require_guild().await?;
 
-    let rows_affected = sqlx::query!(
-        r#"
-        UPDATE servers
-        SET
-            kennel_channel = $1
-        WHERE
-            guild_id = $2
-            ;
-        "#,
-        message.get().to_string(),
-        guild_id.get().to_string(),
-    )
-    .execute(pool)
-    .await?
-    .rows_affected();
+    let updated = update_server(storage, guild_id, |server| {
+        server.kennel_channel = Some(message)
+    })
+    .await?;
 
-    if rows_affected == 0 {
+    if !updated {
         ctx.reply("Couldn't set kennel channel! Make sure to set the kennel role using `/set_kennel_role` first!").await?;
     } else {
         ctx.reply(format!("Set kennel channel to: {message}"))
@@ -315,3 +197,496 @@ pub async fn set_kennel_channel(
 
     Ok(())
 }
+
+/// Sets what happens when someone who is already kenneled gets kenneled again.
+#[poise::command(slash_command, required_permissions = "ADMINISTRATOR")]
+pub async fn set_stacking_policy(
+    ctx: Context<'_>,
+    #[description = "What to do with a new kenneling for someone who is already kenneled"]
+    policy: StackingPolicy,
+) -> Result<()> {
+    let ShameBotData { storage, .. } = ctx.data();
+    let storage = storage.as_ref();
+    let guild_id = ctx.require_guild().await?;
+
+    let updated =
+        update_server(storage, guild_id, |server| server.stacking_policy = policy).await?;
+
+    if !updated {
+        ctx.reply("Couldn't set stacking policy! Make sure to set the kennel role using `/set_kennel_role` first!").await?;
+    } else {
+        ctx.reply(format!("Set stacking policy to: {policy}"))
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// Sets what happens when someone takes the kennel punishment off of a kenneled user by hand.
+#[poise::command(slash_command, required_permissions = "ADMINISTRATOR")]
+pub async fn set_manual_release(
+    ctx: Context<'_>,
+    #[description = "Whether to end the kenneling early, or put the punishment back"]
+    policy: ManualReleasePolicy,
+) -> Result<()> {
+    let ShameBotData { storage, .. } = ctx.data();
+    let storage = storage.as_ref();
+    let guild_id = ctx.require_guild().await?;
+
+    let updated = update_server(storage, guild_id, |server| server.manual_release = policy).await?;
+
+    if !updated {
+        ctx.reply("Couldn't set manual release policy! Make sure to set the kennel role using `/set_kennel_role` first!").await?;
+    } else if policy == ManualReleasePolicy::Enforce {
+        ctx.reply("Taking the kennel punishment off by hand will now get it put back, and reported in the kennel channel. Make sure I can view the audit log!")
+            .await?;
+    } else {
+        ctx.reply("Taking the kennel punishment off by hand will now end the kenneling early.")
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// Sets whether kenneled users get the kennel role or a Discord timeout.
+#[poise::command(slash_command, required_permissions = "ADMINISTRATOR")]
+pub async fn set_punishment_mode(
+    ctx: Context<'_>,
+    #[description = "How kenneled users are punished"] mode: PunishmentMode,
+) -> Result<()> {
+    let ShameBotData { storage, .. } = ctx.data();
+    let storage = storage.as_ref();
+    let guild_id = ctx.require_guild().await?;
+
+    // Timeouts don't need a kennel role, so this is also a way to set a server up without one.
+    let mut server = storage
+        .server(guild_id)
+        .await?
+        .unwrap_or_else(|| Server::new(guild_id));
+
+    if !server.change_punishment_mode(storage, mode).await? {
+        ctx.reply_ephemeral("Can't switch punishment modes while someone is in the kennel or scheduled to be! Wait for them to be released, or `/unkennel` them and cancel what's pending first.")
+            .await?;
+    } else if mode == PunishmentMode::Role {
+        ctx.reply(format!("Set punishment mode to: {mode}. Make sure to set the kennel role using `/set_kennel_role`!"))
+            .await?;
+    } else {
+        ctx.reply(format!("Set punishment mode to: {mode}")).await?;
+    }
+
+    Ok(())
+}
+
+/// Sets whether kenneled users have their other roles taken away until they're released.
+#[poise::command(slash_command, required_permissions = "ADMINISTRATOR")]
+pub async fn set_strip_roles(
+    ctx: Context<'_>,
+    #[description = "Whether to strip kenneled users of their other roles"] enabled: bool,
+) -> Result<()> {
+    let ShameBotData { storage, .. } = ctx.data();
+    let storage = storage.as_ref();
+    let guild_id = ctx.require_guild().await?;
+
+    let updated = update_server(storage, guild_id, |server| server.strip_roles = enabled).await?;
+
+    if !updated {
+        ctx.reply("Couldn't set role stripping! Make sure to set the kennel role using `/set_kennel_role` first!").await?;
+    } else if enabled {
+        ctx.reply("Kenneled users will now have their other roles stripped until they're released. Make sure my role is above theirs!")
+            .await?;
+    } else {
+        ctx.reply("Kenneled users will now keep their other roles.")
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// Sets whether this server's kennelings count towards the bot's status and `/time_kenneled`.
+#[poise::command(slash_command, required_permissions = "ADMINISTRATOR")]
+pub async fn set_count_publicly(
+    ctx: Context<'_>,
+    #[description = "Whether this server's kennelings count towards the bot's public stats"]
+    enabled: bool,
+) -> Result<()> {
+    let ShameBotData { storage, .. } = ctx.data();
+    let storage = storage.as_ref();
+    let guild_id = ctx.require_guild().await?;
+
+    let updated =
+        update_server(storage, guild_id, |server| server.count_publicly = enabled).await?;
+
+    if !updated {
+        ctx.reply("Couldn't set public stats! Make sure to set the kennel role using `/set_kennel_role` first!").await?;
+    } else if enabled {
+        ctx.reply("Kennelings here will now count towards the bot's public stats.")
+            .await?;
+    } else {
+        ctx.reply("Kennelings here will no longer count towards the bot's public stats.")
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// Lets members vote to kennel someone with `/vote_kennel`. Leave out `votes` to turn voting off.
+#[poise::command(slash_command, required_permissions = "ADMINISTRATOR")]
+pub async fn set_kennel_votes(
+    ctx: Context<'_>,
+    #[description = "How many members have to approve for a vote to pass, or deny for it to fail"]
+    #[min = 2]
+    votes: Option<u32>,
+    #[description = "How long votes stay open, like '10m'"] window: Option<String>,
+) -> Result<()> {
+    let ShameBotData { storage, .. } = ctx.data();
+    let storage = storage.as_ref();
+    let guild_id = ctx.require_guild().await?;
+
+    let Ok(window) = parse_limit(window.as_deref()) else {
+        return ctx
+            .reply_ephemeral("Invalid time format! Say something like '3m' or '1h'")
+            .await;
+    };
+
+    if window.is_some_and(|window| window < Duration::from_secs(10)) {
+        return ctx
+            .reply_ephemeral("Votes have to stay open for at least 10 seconds!")
+            .await;
+    }
+
+    if window.is_some_and(|window| window >= MAX_VOTE_WINDOW) {
+        return ctx
+            .reply_ephemeral(format!(
+                "Votes have to close in under {}, since Discord stops letting the bot update them after that!",
+                humantime::format_duration(MAX_VOTE_WINDOW)
+            ))
+            .await;
+    }
+
+    let mut vote_window = Duration::ZERO;
+
+    let updated = update_server(storage, guild_id, |server| {
+        server.vote_threshold = votes;
+        server.vote_window = window.unwrap_or(server.vote_window);
+        vote_window = server.vote_window;
+    })
+    .await?;
+
+    if !updated {
+        ctx.reply("Couldn't set up votes! Make sure to set the kennel role using `/set_kennel_role` first!").await?;
+    } else if let Some(votes) = votes {
+        ctx.reply(format!(
+            "Members can now vote to kennel someone with `/vote_kennel`! Votes pass with {votes} approvals, fail with as many denials, and stay open for {}.",
+            humantime::format_duration(vote_window)
+        ))
+        .await?;
+    } else {
+        ctx.reply("Members can no longer vote to kennel someone.")
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// Manages the roles and users that can't be kenneled.
+#[poise::command(
+    slash_command,
+    required_permissions = "ADMINISTRATOR",
+    subcommands("immunity_add", "immunity_remove", "immunity_list"),
+    subcommand_required
+)]
+pub async fn kennel_immunity(_ctx: Context<'_>) -> Result<()> {
+    Ok(())
+}
+
+/// Makes a role or user immune to the kennel.
+#[poise::command(slash_command, rename = "add", required_permissions = "ADMINISTRATOR")]
+pub async fn immunity_add(
+    ctx: Context<'_>,
+    #[description = "A role whose members can't be kenneled"] role: Option<RoleId>,
+    #[description = "A user who can't be kenneled"] user: Option<UserId>,
+) -> Result<()> {
+    let ShameBotData { storage, .. } = ctx.data();
+    let storage = storage.as_ref();
+    let guild_id = ctx.require_guild().await?;
+
+    if role.is_none() && user.is_none() {
+        return ctx
+            .reply_ephemeral("Give me a role or a user to make immune!")
+            .await;
+    }
+
+    let updated = update_server(storage, guild_id, |server| {
+        if let Some(role) = role
+            && !server.immune_roles.contains(&role)
+        {
+            server.immune_roles.push(role);
+        }
+
+        if let Some(user) = user
+            && !server.immune_users.contains(&user)
+        {
+            server.immune_users.push(user);
+        }
+    })
+    .await?;
+
+    if !updated {
+        ctx.reply("Couldn't add immunity! Make sure to set the kennel role using `/set_kennel_role` first!").await?;
+    } else {
+        ctx.reply(format!(
+            "Made {} immune to the kennel!",
+            mentions(role, user)
+        ))
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Takes away a role's or user's immunity to the kennel.
+#[poise::command(
+    slash_command,
+    rename = "remove",
+    required_permissions = "ADMINISTRATOR"
+)]
+pub async fn immunity_remove(
+    ctx: Context<'_>,
+    #[description = "A role to take immunity away from"] role: Option<RoleId>,
+    #[description = "A user to take immunity away from"] user: Option<UserId>,
+) -> Result<()> {
+    let ShameBotData { storage, .. } = ctx.data();
+    let storage = storage.as_ref();
+    let guild_id = ctx.require_guild().await?;
+
+    if role.is_none() && user.is_none() {
+        return ctx
+            .reply_ephemeral("Give me a role or a user to take immunity away from!")
+            .await;
+    }
+
+    let updated = update_server(storage, guild_id, |server| {
+        server
+            .immune_roles
+            .retain(|immune_role| Some(*immune_role) != role);
+        server
+            .immune_users
+            .retain(|immune_user| Some(*immune_user) != user);
+    })
+    .await?;
+
+    if !updated {
+        ctx.reply("Couldn't remove immunity! Make sure to set the kennel role using `/set_kennel_role` first!").await?;
+    } else {
+        ctx.reply(format!("{} can be kenneled again!", mentions(role, user)))
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// Lists the roles and users that can't be kenneled.
+#[poise::command(slash_command, rename = "list", required_permissions = "ADMINISTRATOR")]
+pub async fn immunity_list(ctx: Context<'_>) -> Result<()> {
+    let ShameBotData { storage, .. } = ctx.data();
+    let storage = storage.as_ref();
+    let guild_id = ctx.require_guild().await?;
+
+    let Some(res) = storage.server(guild_id).await? else {
+        return ctx
+            .reply_ephemeral("Set the kennel role using `/set_kennel_role` first!")
+            .await;
+    };
+
+    if res.immune_roles.is_empty() && res.immune_users.is_empty() {
+        return ctx.reply_ephemeral("Nobody is immune to the kennel.").await;
+    }
+
+    let immune = res
+        .immune_roles
+        .iter()
+        .map(|role_id| format!("<@&{role_id}>"))
+        .chain(
+            res.immune_users
+                .iter()
+                .map(|user_id| format!("<@{user_id}>")),
+        )
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    ctx.reply_ephemeral(format!("Immune to the kennel: {immune}"))
+        .await
+}
+
+/// Changes a server's settings and saves them.
+///
+/// Returns `false` if the server hasn't been set up yet, in which case nothing is changed.
+async fn update_server(
+    storage: &dyn Storage,
+    guild_id: GuildId,
+    change: impl FnOnce(&mut Server),
+) -> Result<bool> {
+    let Some(mut server) = storage.server(guild_id).await? else {
+        return Ok(false);
+    };
+
+    change(&mut server);
+    storage.upsert_server(&server).await?;
+
+    Ok(true)
+}
+
+/// Formats an optional role and an optional user as mentions, for replies.
+fn mentions(role: Option<RoleId>, user: Option<UserId>) -> String {
+    role.map(|role| format!("<@&{role}>"))
+        .into_iter()
+        .chain(user.map(|user| format!("<@{user}>")))
+        .collect::<Vec<_>>()
+        .join(" and ")
+}
+
+/// Manages how short or long kennelings can be.
+#[poise::command(
+    slash_command,
+    required_permissions = "ADMINISTRATOR",
+    subcommands("limits_set", "limits_role", "limits_list"),
+    subcommand_required
+)]
+pub async fn kennel_limits(_ctx: Context<'_>) -> Result<()> {
+    Ok(())
+}
+
+/// Sets the shortest and longest anyone can be kenneled for. Leave one out to remove it.
+#[poise::command(slash_command, rename = "set", required_permissions = "ADMINISTRATOR")]
+pub async fn limits_set(
+    ctx: Context<'_>,
+    #[description = "The shortest kenneling allowed, like '1m'"] min: Option<String>,
+    #[description = "The longest kenneling allowed, like '7d'"] max: Option<String>,
+) -> Result<()> {
+    let ShameBotData { storage, .. } = ctx.data();
+    let storage = storage.as_ref();
+    let guild_id = ctx.require_guild().await?;
+
+    let (Ok(min), Ok(max)) = (parse_limit(min.as_deref()), parse_limit(max.as_deref())) else {
+        return ctx
+            .reply_ephemeral("Invalid time format! Say something like '3m' or '1h'")
+            .await;
+    };
+
+    if let (Some(min), Some(max)) = (min, max)
+        && min > max
+    {
+        return ctx
+            .reply_ephemeral("The minimum can't be longer than the maximum!")
+            .await;
+    }
+
+    let updated = update_server(storage, guild_id, |server| {
+        server.min_kennel_length = min;
+        server.max_kennel_length = max;
+    })
+    .await?;
+
+    if !updated {
+        ctx.reply("Couldn't set kennel limits! Make sure to set the kennel role using `/set_kennel_role` first!").await?;
+    } else {
+        ctx.reply(format!(
+            "Kennelings can now last from {} to {}!",
+            format_limit(min, "any time"),
+            format_limit(max, "forever")
+        ))
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Caps how long members with a role can kennel for, even past the server-wide maximum.
+#[poise::command(slash_command, rename = "role", required_permissions = "ADMINISTRATOR")]
+pub async fn limits_role(
+    ctx: Context<'_>,
+    #[description = "The role to cap"] role: RoleId,
+    #[description = "The longest they can kennel for, like '1h'. Leave out to remove the cap"]
+    max: Option<String>,
+) -> Result<()> {
+    let ShameBotData { storage, .. } = ctx.data();
+    let storage = storage.as_ref();
+    let guild_id = ctx.require_guild().await?;
+
+    let Ok(max) = parse_limit(max.as_deref()) else {
+        return ctx
+            .reply_ephemeral("Invalid time format! Say something like '3m' or '1h'")
+            .await;
+    };
+
+    storage.set_role_limit(guild_id, role, max).await?;
+
+    if let Some(max) = max {
+        let server_max = storage
+            .server(guild_id)
+            .await?
+            .and_then(|server| server.max_kennel_length);
+        let past_server_max = match server_max {
+            Some(server_max) if max > server_max => format!(
+                " That's longer than the server-wide maximum of {}, which they're allowed to go past.",
+                humantime::format_duration(server_max)
+            ),
+            _ => String::new(),
+        };
+
+        ctx.reply(format!(
+            "Members with <@&{role}> can now kennel people for up to {}!{past_server_max}",
+            humantime::format_duration(max)
+        ))
+        .await?;
+    } else {
+        ctx.reply(format!("<@&{role}> now follows the server-wide maximum."))
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// Lists the server's kennel limits.
+#[poise::command(slash_command, rename = "list", required_permissions = "ADMINISTRATOR")]
+pub async fn limits_list(ctx: Context<'_>) -> Result<()> {
+    let ShameBotData { storage, .. } = ctx.data();
+    let storage = storage.as_ref();
+    let guild_id = ctx.require_guild().await?;
+
+    let Some(server) = storage.server(guild_id).await? else {
+        return ctx
+            .reply_ephemeral("Set the kennel role using `/set_kennel_role` first!")
+            .await;
+    };
+
+    let role_limits = storage.role_limits(guild_id).await?;
+
+    let mut reply = format!(
+        "Kennelings can last from {} to {}.",
+        format_limit(server.min_kennel_length, "any time"),
+        format_limit(server.max_kennel_length, "forever")
+    );
+
+    for (role_id, max) in role_limits {
+        reply.push_str(&format!(
+            "\n<@&{role_id}> can kennel for up to {}.",
+            humantime::format_duration(max)
+        ));
+    }
+
+    ctx.reply_ephemeral(reply).await
+}
+
+/// Parses an optional limit, where leaving it out means there's no limit.
+fn parse_limit(limit: Option<&str>) -> Result<Option<Duration>, humantime::DurationError> {
+    limit.map(humantime::parse_duration).transpose()
+}
+
+/// Formats an optional limit for replies, using `unlimited` when there's no limit.
+fn format_limit(limit: Option<Duration>, unlimited: &str) -> String {
+    limit.map_or_else(
+        || unlimited.to_string(),
+        |limit| humantime::format_duration(limit).to_string(),
+    )
+}
@@ -0,0 +1,121 @@
+//! Contains commands for looking at how the kennel has been used in a given server.
+
+use anyhow::Result;
+use humantime::format_duration;
+use poise::CreateReply;
+use serenity::all::CreateEmbed;
+use shame_bot::Context;
+use shame_bot::types::{Leaderboard, LeaderboardEntry};
+use shame_bot::util::get_guild_id::GetGuildID;
+use shame_bot::util::stefan_traits::*;
+
+use crate::ShameBotData;
+
+/// How many entries to show on the leaderboards.
+const LEADERBOARD_SIZE: i64 = 10;
+
+/// Shows statistics about the kennel in this server.
+#[poise::command(
+    slash_command,
+    subcommands("stats_overview", "stats_victims", "stats_kennelers"),
+    subcommand_required
+)]
+pub async fn kennel_stats(_ctx: Context<'_>) -> Result<()> {
+    Ok(())
+}
+
+/// Shows totals and recent activity for this server.
+#[poise::command(slash_command, rename = "overview")]
+pub async fn stats_overview(ctx: Context<'_>) -> Result<()> {
+    let ShameBotData { storage, .. } = ctx.data();
+    let guild_id = ctx.require_guild().await?;
+
+    let stats = storage.guild_stats(guild_id).await?;
+
+    if stats.kennelings == 0 {
+        return ctx
+            .reply_ephemeral("Nobody has been kenneled here yet!")
+            .await;
+    }
+
+    let embed = CreateEmbed::new()
+        .title("Kennel statistics")
+        .field("Kennelings", stats.kennelings.to_string(), true)
+        .field("Users kenneled", stats.victims.to_string(), true)
+        .field(
+            "Total time",
+            stats
+                .total_length
+                .map(|total| format_duration(total).to_string())
+                .unwrap_or_default(),
+            true,
+        )
+        .field(
+            "Average sentence",
+            stats
+                .average_length
+                .map(|average| format_duration(average).to_string())
+                .unwrap_or_default(),
+            true,
+        )
+        .field("Last day", stats.last_day.to_string(), true)
+        .field("Last week", stats.last_week.to_string(), true)
+        .field("Last month", stats.last_month.to_string(), true);
+
+    ctx.send(CreateReply::default().embed(embed)).await?;
+
+    Ok(())
+}
+
+/// Shows the users who have been kenneled the most in this server.
+#[poise::command(slash_command, rename = "victims")]
+pub async fn stats_victims(ctx: Context<'_>) -> Result<()> {
+    send_leaderboard(ctx, Leaderboard::Victims, "Most kenneled").await
+}
+
+/// Shows the users who have kenneled the most people in this server.
+#[poise::command(slash_command, rename = "kennelers")]
+pub async fn stats_kennelers(ctx: Context<'_>) -> Result<()> {
+    send_leaderboard(ctx, Leaderboard::Kennelers, "Most active kennelers").await
+}
+
+/// Formats one entry on a leaderboard.
+fn leaderboard_line(entry: &LeaderboardEntry) -> String {
+    format!(
+        "<@{}>: {} kenneling{} ({})",
+        entry.user_id,
+        entry.kennelings,
+        if entry.kennelings == 1 { "" } else { "s" },
+        format_duration(entry.total_length)
+    )
+}
+
+/// Sends a numbered leaderboard as an embed.
+async fn send_leaderboard(ctx: Context<'_>, leaderboard: Leaderboard, title: &str) -> Result<()> {
+    let ShameBotData { storage, .. } = ctx.data();
+    let guild_id = ctx.require_guild().await?;
+
+    let entries = storage
+        .leaderboard(guild_id, leaderboard, LEADERBOARD_SIZE)
+        .await?;
+
+    if entries.is_empty() {
+        return ctx
+            .reply_ephemeral("Nobody has been kenneled here yet!")
+            .await;
+    }
+
+    let description = entries
+        .iter()
+        .enumerate()
+        .map(|(i, entry)| format!("{}. {}", i + 1, leaderboard_line(entry)))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    ctx.send(
+        CreateReply::default().embed(CreateEmbed::new().title(title).description(description)),
+    )
+    .await?;
+
+    Ok(())
+}
@@ -1,35 +1,38 @@
 use anyhow::Result;
+use chrono::{DateTime, Utc};
 use humantime::format_duration;
+use poise::CreateReply;
+use serenity::all::{
+    ComponentInteractionCollector, CreateActionRow, CreateButton, CreateEmbed, CreateEmbedFooter,
+    CreateInteractionResponse, CreateInteractionResponseMessage, UserId,
+};
 use shame_bot::Context;
+use shame_bot::types::{Kenneling, KennelingStatus};
+use shame_bot::util::get_guild_id::GetGuildID;
+use shame_bot::util::stefan_traits::*;
+use std::time::Duration;
 
 use crate::ShameBotData;
-use shame_bot::util::pgint_dur::PgIntervalToDuration;
+
+/// How many kennelings to show on each page of `/kennel_history`.
+const HISTORY_PAGE_SIZE: usize = 5;
+
+/// How long the `/kennel_history` buttons keep working after the last press.
+const HISTORY_TIMEOUT: Duration = Duration::from_secs(10 * 60);
 
 /// Tells you the total time kenneled in case you can't read the status
 #[poise::command(slash_command)]
 pub async fn time_kenneled(ctx: Context<'_>) -> Result<()> {
-    let ShameBotData { pool } = ctx.data();
-    let pool = pool.as_ref();
-
-    match sqlx::query!(
-        r#"
-        SELECT SUM(kennel_length)
-        FROM kennelings
-        WHERE 
-            NOT guild_id = '849505364764524565'
-            ;
-        "#
-    )
-    .fetch_one(pool)
-    .await
-    {
-        Ok(res) => {
-            if let Some(sum) = res.sum {
-                ctx.reply(format!(
-                    "Kenneled users for {}",
-                    format_duration(sum.as_duration())
-                ))
-                .await?;
+    let ShameBotData {
+        storage,
+        excluded_guilds,
+    } = ctx.data();
+
+    match storage.public_stats(excluded_guilds).await {
+        Ok(stats) => {
+            if let Some(sum) = stats.total_length {
+                ctx.reply(format!("Kenneled users for {}", format_duration(sum)))
+                    .await?;
             }
         }
         Err(_) => {
@@ -39,3 +42,188 @@ pub async fn time_kenneled(ctx: Context<'_>) -> Result<()> {
 
     Ok(())
 }
+
+/// Lists everything a user has been kenneled for in this server
+#[poise::command(slash_command)]
+pub async fn kennel_history(
+    ctx: Context<'_>,
+    #[description = "User to look up"] user: UserId,
+) -> Result<()> {
+    let ShameBotData { storage, .. } = ctx.data();
+    let guild_id = ctx.require_guild().await?;
+
+    let history = storage.history(guild_id, user).await?;
+
+    if history.is_empty() {
+        return ctx
+            .reply_ephemeral(format!("<@{user}> has never been kenneled here!"))
+            .await;
+    }
+
+    let now = Utc::now();
+    let pages: Vec<&[Kenneling]> = history.chunks(HISTORY_PAGE_SIZE).collect();
+    let mut page = 0;
+
+    // Prefixing the button ids with the invocation's id keeps presses from other invocations out of the collector, and
+    // only whoever asked for the history gets to page through it.
+    let ctx_id = ctx.id();
+    let author_id = ctx.author().id;
+    let prev_id = format!("{ctx_id}prev");
+    let next_id = format!("{ctx_id}next");
+
+    let reply_handle = ctx
+        .send(
+            CreateReply::default()
+                .embed(history_embed(user, &pages, page, history.len(), now))
+                .components(history_buttons(&prev_id, &next_id, page, pages.len())),
+        )
+        .await?;
+
+    if pages.len() == 1 {
+        return Ok(());
+    }
+
+    while let Some(press) = ComponentInteractionCollector::new(ctx)
+        .filter(move |press| {
+            press.user.id == author_id && press.data.custom_id.starts_with(&ctx_id.to_string())
+        })
+        .timeout(HISTORY_TIMEOUT)
+        .await
+    {
+        if press.data.custom_id == next_id {
+            page = (page + 1).min(pages.len() - 1);
+        } else if press.data.custom_id == prev_id {
+            page = page.saturating_sub(1);
+        } else {
+            continue;
+        }
+
+        press
+            .create_response(
+                ctx.serenity_context(),
+                CreateInteractionResponse::UpdateMessage(
+                    CreateInteractionResponseMessage::new()
+                        .embed(history_embed(user, &pages, page, history.len(), now))
+                        .components(history_buttons(&prev_id, &next_id, page, pages.len())),
+                ),
+            )
+            .await?;
+    }
+
+    // Nobody's listening for presses anymore, so the buttons would only fail.
+    reply_handle
+        .edit(
+            ctx,
+            CreateReply::default()
+                .embed(history_embed(user, &pages, page, history.len(), now))
+                .components(Vec::new()),
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// Builds the embed for one page of a user's kennel history.
+fn history_embed(
+    user: UserId,
+    pages: &[&[Kenneling]],
+    page: usize,
+    total: usize,
+    now: DateTime<Utc>,
+) -> CreateEmbed {
+    let fields = pages[page].iter().map(|kenneling| {
+        let mut sentence = format_duration(kenneling.kennel_length).to_string();
+
+        if kenneling.original_length != kenneling.kennel_length {
+            sentence.push_str(&format!(
+                " (originally {})",
+                format_duration(kenneling.original_length)
+            ));
+        }
+
+        let mut value = format!(
+            "**By:** {}\n**Sentence:** {sentence}\n**Served:** {}\n**Reason:** {}\n",
+            kenneling.display_author(),
+            format_duration(kenneling.time_served(now)),
+            kenneling.display_reason(),
+        );
+
+        let ended_at = kenneling
+            .ended_at
+            .unwrap_or(kenneling.released_at)
+            .timestamp();
+
+        match kenneling.status {
+            KennelingStatus::Pending => value.push_str(&format!(
+                "**Starts:** {}",
+                kenneling.kenneled_at.discord_relative_timestamp()
+            )),
+            KennelingStatus::Cancelled => {
+                value.push_str(&format!("**Cancelled:** <t:{ended_at}:f>"))
+            }
+            KennelingStatus::Active => value.push_str(&format!(
+                "**Release:** {}",
+                kenneling.released_at.discord_relative_timestamp()
+            )),
+            KennelingStatus::Paused => value.push_str("**Paused** until they rejoin"),
+            KennelingStatus::ReleasedOnTime => {
+                value.push_str(&format!("**Released:** <t:{ended_at}:f>"))
+            }
+            KennelingStatus::ReleasedEarly => {
+                value.push_str(&format!("**Ended early:** <t:{ended_at}:f>"))
+            }
+            KennelingStatus::Revoked => value.push_str(&format!("**Revoked:** <t:{ended_at}:f>")),
+            KennelingStatus::Failed => {
+                value.push_str(&format!("**Release failed:** <t:{ended_at}:f>"))
+            }
+        }
+
+        if !kenneling.status.is_open() {
+            if let Some(ended_by) = kenneling.ended_by {
+                value.push_str(&format!(" by <@{ended_by}>"));
+            }
+
+            if let Some(end_reason) = &kenneling.end_reason {
+                value.push_str(&format!(" ({end_reason})"));
+            }
+        }
+
+        (
+            format!("<t:{}:f>", kenneling.kenneled_at.timestamp()),
+            value,
+            false,
+        )
+    });
+
+    CreateEmbed::new()
+        .title("Kennel history")
+        .description(format!(
+            "<@{user}> has been kenneled {total} time{} here.",
+            if total == 1 { "" } else { "s" }
+        ))
+        .fields(fields)
+        .footer(CreateEmbedFooter::new(format!(
+            "Page {}/{}",
+            page + 1,
+            pages.len()
+        )))
+}
+
+/// Builds the previous/next buttons for a page of kennel history, or none if there's only the one page.
+fn history_buttons(
+    prev_id: &str,
+    next_id: &str,
+    page: usize,
+    page_count: usize,
+) -> Vec<CreateActionRow> {
+    if page_count <= 1 {
+        return Vec::new();
+    }
+
+    vec![CreateActionRow::Buttons(vec![
+        CreateButton::new(prev_id).emoji('◀').disabled(page == 0),
+        CreateButton::new(next_id)
+            .emoji('▶')
+            .disabled(page + 1 == page_count),
+    ])]
+}
@@ -0,0 +1,241 @@
+//! Contains the command for members to vote someone into the kennel.
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use poise::CreateReply;
+use serenity::all::{
+    ButtonStyle, ComponentInteractionCollector, CreateActionRow, CreateButton,
+    CreateInteractionResponse, CreateInteractionResponseMessage, UserId,
+};
+use shame_bot::util::discord_errors::is_missing_member;
+use shame_bot::util::get_guild_id::GetGuildID;
+use shame_bot::util::stefan_traits::*;
+use shame_bot::{Context, types::*};
+use std::time::Duration;
+
+use crate::ShameBotData;
+use crate::commands::wildcard::too_long_message;
+
+/// Asks the server whether someone should be kenneled, and kennels them if enough members agree.
+#[poise::command(slash_command, guild_only)]
+pub async fn vote_kennel(
+    ctx: Context<'_>,
+    #[description = "User to kennel"] user: UserId,
+    #[description = "Time to kennel"] time: String,
+    #[description = "Why they should be kenneled"]
+    #[max_length = 512]
+    reason: Option<String>,
+) -> Result<()> {
+    let ShameBotData { storage, .. } = ctx.data();
+    let storage = storage.as_ref();
+    let guild_id = ctx.require_guild().await?;
+
+    let Ok(kennel_length) = humantime::parse_duration(&time) else {
+        return ctx
+            .reply_ephemeral("Invalid time format! Say something like '3m' or '1h'")
+            .await;
+    };
+
+    if kennel_length < Duration::from_secs(1) {
+        return ctx.reply_ephemeral("Over 1 second, please...").await;
+    }
+
+    let Some(server) = storage.server(guild_id).await? else {
+        return ctx.reply_ephemeral("Set kennel role first!").await;
+    };
+
+    let Some(threshold) = server.vote_threshold else {
+        return ctx
+            .reply_ephemeral(
+                "Voting isn't turned on here! An admin can turn it on with `/set_kennel_votes`.",
+            )
+            .await;
+    };
+
+    if server.punishment_mode == PunishmentMode::Role && server.role_id.is_none() {
+        return ctx.reply_ephemeral("Set kennel role first!").await;
+    }
+
+    if user == ctx.author().id {
+        return ctx
+            .reply_ephemeral(KennelRefusal::SelfKennel.to_string())
+            .await;
+    }
+
+    let http = ctx.http();
+
+    let victim = match http.get_member(guild_id, user).await {
+        Ok(victim) => victim,
+        Err(e) if is_missing_member(&e) => {
+            return ctx
+                .reply_ephemeral(format!("<@{user}> isn't in this server!"))
+                .await;
+        }
+        Err(e) => return Err(e.into()),
+    };
+
+    let guild = http.get_guild(guild_id).await?;
+    let bot = http.get_member(guild_id, ctx.framework().bot_id).await?;
+
+    // Nobody's rank matters in a vote, so it's the bot that has to be able to kennel them.
+    if let Err(refusal) = KennelRefusal::check(&server, &guild, &bot, &bot, &victim) {
+        return ctx.reply_ephemeral(refusal.to_string()).await;
+    }
+
+    if let Err(refusal) = KennelLimits::for_server(&server).check(kennel_length) {
+        return ctx.reply_ephemeral(refusal.to_string()).await;
+    }
+
+    let closes_at = Utc::now() + server.vote_window;
+
+    if !server.can_enforce_until(closes_at + kennel_length) {
+        return ctx.reply_ephemeral(too_long_message(&server)).await;
+    }
+
+    let mut vote = KennelVote::new(user, ctx.author().id, threshold);
+    let reason = reason.as_deref();
+
+    // Prefixing the button ids with the invocation's id keeps presses from other invocations out of the collector.
+    let ctx_id = ctx.id();
+    let approve_id = format!("{ctx_id}approve");
+    let deny_id = format!("{ctx_id}deny");
+
+    let vote_msg =
+        |vote: &KennelVote| vote_message(ctx.author().id, vote, kennel_length, reason, closes_at);
+
+    let reply_handle = ctx
+        .send(
+            CreateReply::default()
+                .content(vote_msg(&vote))
+                .components(vote_buttons(&approve_id, &deny_id)),
+        )
+        .await?;
+
+    tracing::info!(
+        "{} started a vote to kennel {user} for {}",
+        ctx.author().display_name(),
+        humantime::format_duration(kennel_length)
+    );
+
+    while vote.outcome() == VoteOutcome::Open
+        && let Ok(remaining) = (closes_at - Utc::now()).to_std()
+        && let Some(press) = ComponentInteractionCollector::new(ctx)
+            .filter(move |press| press.data.custom_id.starts_with(&ctx_id.to_string()))
+            .timeout(remaining)
+            .await
+    {
+        let approve = if press.data.custom_id == approve_id {
+            true
+        } else if press.data.custom_id == deny_id {
+            false
+        } else {
+            continue;
+        };
+
+        let response = match vote.cast(press.user.id, approve) {
+            Ok(_) => CreateInteractionResponse::UpdateMessage(
+                CreateInteractionResponseMessage::new().content(vote_msg(&vote)),
+            ),
+            Err(refusal) => CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new()
+                    .content(refusal.to_string())
+                    .ephemeral(true),
+            ),
+        };
+
+        press
+            .create_response(ctx.serenity_context(), response)
+            .await?;
+    }
+
+    let tally = format!(
+        "({} for, {} against)",
+        vote.approvals.len(),
+        vote.denials.len()
+    );
+
+    if vote.outcome() != VoteOutcome::Passed {
+        tracing::info!("The vote to kennel {user} didn't pass {tally}");
+
+        reply_handle
+            .edit(
+                ctx,
+                CreateReply::default()
+                    .content(format!("The vote to kennel <@{user}> didn't pass. {tally}"))
+                    .components(Vec::new()),
+            )
+            .await?;
+
+        return Ok(());
+    }
+
+    tracing::info!("The vote to kennel {user} passed {tally}");
+
+    reply_handle
+        .edit(
+            ctx,
+            CreateReply::default()
+                .content(format!("The vote to kennel <@{user}> passed! {tally}"))
+                .components(Vec::new()),
+        )
+        .await?;
+
+    // Starting it like a scheduled kenneling that's due now takes care of stacking, and turns the vote into the
+    // announcement.
+    let message = reply_handle.message().await?;
+    let mut kenneling = Kenneling {
+        status: KennelingStatus::Pending,
+        announcement: Some((message.channel_id, message.id)),
+        reason: reason.map(str::to_string),
+        community_vote: true,
+        ..Kenneling::new(guild_id, user, ctx.framework().bot_id, kennel_length)
+    };
+
+    kenneling.id = Some(storage.insert_pending_kenneling(&kenneling).await?);
+
+    match kenneling
+        .start(http, storage, ctx.framework().bot_id)
+        .await?
+    {
+        StartOutcome::Cancelled(reason) => {
+            tracing::info!("The kenneling {user} was voted into was called off: {reason}")
+        }
+        outcome => tracing::debug!("Started the kenneling {user} was voted into: {outcome:?}"),
+    }
+
+    Ok(())
+}
+
+/// The message asking members to vote, with the tally so far.
+fn vote_message(
+    proposer_id: UserId,
+    vote: &KennelVote,
+    kennel_length: Duration,
+    reason: Option<&str>,
+    closes_at: DateTime<Utc>,
+) -> String {
+    format!(
+        "<@{proposer_id}> wants to lock <@{}> away in the kennel for {}. Reason: {}\n\
+        It takes {} votes either way, and voting closes {}.\n\
+        ✅ {} ❌ {}",
+        vote.victim_id,
+        humantime::format_duration(kennel_length),
+        reason.unwrap_or(shame_bot::NO_REASON),
+        vote.threshold,
+        closes_at.discord_relative_timestamp(),
+        vote.approvals.len(),
+        vote.denials.len()
+    )
+}
+
+/// Builds the approve/deny buttons for a vote.
+fn vote_buttons(approve_id: &str, deny_id: &str) -> Vec<CreateActionRow> {
+    vec![CreateActionRow::Buttons(vec![
+        CreateButton::new(approve_id)
+            .label("Approve")
+            .style(ButtonStyle::Success),
+        CreateButton::new(deny_id)
+            .label("Deny")
+            .style(ButtonStyle::Danger),
+    ])]
+}
@@ -2,12 +2,14 @@ use anyhow::{Context as _, Result};
 use poise::{ApplicationContext, FrameworkContext};
 use serenity::all::{FullEvent, Interaction, UserId};
 use serenity::client::Context as SerenityCtx;
+use shame_bot::storage::Storage;
+use shame_bot::util::discord_errors::is_missing_member;
 use shame_bot::util::get_guild_id::GetGuildID;
+use shame_bot::util::start_time::parse_start_time;
 use shame_bot::{Context, types::*};
 use std::time::Duration;
 
 use crate::ShameBotData;
-use crate::set_activity;
 use shame_bot::util::stefan_traits::*;
 
 /// Kennels someone.
@@ -16,9 +18,14 @@ async fn kennel_user(
     ctx: Context<'_>,
     #[description = "User to kennel"] user: UserId,
     #[description = "Time to kennel"] time: String,
+    #[description = "Why they're being kenneled"]
+    #[max_length = 512]
+    reason: Option<String>,
+    #[description = "When to start, like 'in 2h' or '21:00' (UTC). Leave out to start right away"]
+    start: Option<String>,
 ) -> Result<()> {
-    let ShameBotData { pool } = ctx.data();
-    let pool = pool.as_ref();
+    let ShameBotData { storage, .. } = ctx.data();
+    let storage = storage.as_ref();
     let guild_id = ctx.require_guild().await?;
 
     let Ok(dur_time) = humantime::parse_duration(&time) else {
@@ -32,56 +39,259 @@ async fn kennel_user(
         return ctx.reply_ephemeral("Over 1 second, please...").await;
     }
 
-    let Ok(data) = sqlx::query_as!(
-        ServerRow,
-        r#"
-        SELECT * FROM servers
-        WHERE
-            guild_id = $1
-            AND command_name = $2
-            ;
-        "#,
-        guild_id.get().to_string(),
-        ctx.invoked_command_name()
-    )
-    .fetch_one(pool)
-    .await
+    let Some(server) = storage
+        .server(guild_id)
+        .await?
+        .filter(|server| server.command_name == ctx.invoked_command_name())
     else {
         ctx.reply_ephemeral("Set kennel role first!").await?;
 
         return Ok(());
     };
-    let server: Server = data.try_into()?;
+
+    if server.punishment_mode == PunishmentMode::Role && server.role_id.is_none() {
+        return ctx.reply_ephemeral("Set kennel role first!").await;
+    }
 
     let http = ctx.http();
-    let now = chrono::Utc::now();
-    let return_timestamp = now + dur_time;
 
+    let victim = match http.get_member(guild_id, user).await {
+        Ok(victim) => victim,
+        Err(e) if is_missing_member(&e) => {
+            return ctx
+                .reply_ephemeral(format!("<@{user}> isn't in this server!"))
+                .await;
+        }
+        Err(e) => return Err(e.into()),
+    };
+
+    let guild = http.get_guild(guild_id).await?;
+    let bot = http.get_member(guild_id, ctx.framework().bot_id).await?;
+    let author = http.get_member(guild_id, ctx.author().id).await?;
+
+    if let Err(refusal) = KennelRefusal::check(&server, &guild, &bot, &author, &victim) {
+        tracing::debug!(
+            "Refused to let {} kennel {}: {refusal:?}",
+            author.display_name(),
+            victim.display_name()
+        );
+
+        return ctx.reply_ephemeral(refusal.to_string()).await;
+    }
+
+    let limits = KennelLimits::for_author(storage, &server, &author).await?;
+
+    if let Err(refusal) = limits.check(dur_time) {
+        return ctx.reply_ephemeral(refusal.to_string()).await;
+    }
+
+    if let Some(start) = start {
+        return schedule_kennel(ctx, storage, &server, user, dur_time, &start, reason).await;
+    }
+
+    let kenneling = Kenneling {
+        status: KennelingStatus::Pending,
+        reason,
+        ..Kenneling::new(guild_id, user, ctx.author().id, dur_time)
+    };
+    let mut stripped_roles = server.strippable_roles(http, &victim).await?;
+
+    if !server.can_enforce_until(kenneling.released_at) {
+        return ctx.reply_ephemeral(too_long_message(&server)).await;
+    }
+
+    let policy = server.stacking_policy;
+    let active = storage.active_kenneling(guild_id, user).await?;
+    let mut replacing = None;
+
+    match policy.resolve(active, &kenneling) {
+        StackingOutcome::Insert => {}
+        StackingOutcome::Reject(active) => {
+            return ctx
+                .reply_ephemeral(format!(
+                    "<@{user}> is already in the kennel until {}! (stacking policy: {policy})",
+                    active.released_at.discord_relative_timestamp()
+                ))
+                .await;
+        }
+        StackingOutcome::Replace(active) => {
+            replacing = Some(active.replaced_by(ctx.author().id)?);
+
+            // Whatever the old kenneling stripped is already gone, so it has to be handed down to be restored later.
+            for role_id in active.stripped_roles {
+                if !stripped_roles.contains(&role_id) {
+                    stripped_roles.push(role_id);
+                }
+            }
+        }
+        StackingOutcome::Adjust(active, kennel_length) => {
+            // The stacked sentence is held to the same limits as a fresh one would be.
+            if let Err(refusal) = limits.check(kennel_length) {
+                return ctx.reply_ephemeral(refusal.to_string()).await;
+            }
+
+            if !server.can_enforce_until(active.released_at - active.kennel_length + kennel_length)
+            {
+                return ctx.reply_ephemeral(too_long_message(&server)).await;
+            }
+
+            // If it got released in the meantime, there's nothing to stack with anymore.
+            if let Some(mut adjusted) = active.set_length(storage, kennel_length).await? {
+                let announced = Kenneling {
+                    author_id: ctx.author().id,
+                    ..adjusted.clone()
+                };
+
+                if let Some(reply_handle) =
+                    announced.apply_kennel(http, &server, Some(&ctx)).await?
+                {
+                    let announcement = reply_handle.message().await?;
+                    adjusted
+                        .set_announcement(storage, (announcement.channel_id, announcement.id))
+                        .await?;
+                }
+
+                ctx.reply_ephemeral(format!(
+                    "<@{user}> was already in the kennel, so their sentence now ends {} (stacking policy: {policy})",
+                    adjusted.released_at.discord_relative_timestamp()
+                ))
+                .await?;
+
+                return Ok(());
+            }
+        }
+    }
+
+    // The kenneling is saved before anything is done to the victim, so that whatever is taken off of them can always
+    // be given back. If the bot goes down before it's applied, the scheduler starts it on its next pass instead.
+    let id = storage.insert_pending_kenneling(&kenneling).await?;
     let kenneling = Kenneling {
-        guild_id,
-        kennel_length: dur_time,
-        kenneled_at: now,
-        author_id: ctx.author().id,
-        released_at: return_timestamp,
-        victim_id: user,
-        id: None,
+        id: Some(id),
+        ..kenneling
+    };
+
+    // Replacing the old kenneling goes through along with starting this one, or not at all.
+    let replaced = replacing.is_some();
+    let server = &server;
+    let started = storage
+        .start(
+            id,
+            &stripped_roles,
+            replacing,
+            Box::new(|kenneling| {
+                Box::pin(
+                    async move { kenneling.apply_kennel(http, server, None).await.map(|_| ()) },
+                )
+            }),
+        )
+        .await;
+
+    let mut started = match started {
+        Ok(Some(started)) => started,
+        Ok(None) => {
+            return ctx
+                .reply_ephemeral(format!(
+                    "<@{user}>'s kenneling was started by the scheduler in the meantime!"
+                ))
+                .await;
+        }
+        Err(e) => {
+            // Otherwise the scheduler would keep trying to start it behind the moderator's back.
+            kenneling
+                .cancel(http, storage, None, Some(&format!("Couldn't start: {e}")))
+                .await?;
+
+            return Err(e);
+        }
     };
 
-    let reply_handle = kenneling.apply_kennel(http, &server, Some(&ctx)).await?;
-    let kenneling_row = KennelingRow::try_from(&kenneling)?;
-    kenneling_row.assume_current_and_insert(pool).await?;
+    let reply_handle = started.announce(http, server, &ctx).await?;
+    let announcement = reply_handle.message().await?;
+    started
+        .set_announcement(storage, (announcement.channel_id, announcement.id))
+        .await?;
+
+    if replaced {
+        ctx.reply_ephemeral(format!(
+            "<@{user}> was already in the kennel, so that kenneling was replaced (stacking policy: {policy})"
+        ))
+        .await?;
+    }
 
-    set_activity(ctx.serenity_context(), pool).await;
+    Ok(())
+}
 
-    tokio::time::sleep(dur_time).await;
+/// Schedules a kenneling to start later. Stacking and role stripping are worked out when it starts, since by then
+/// the victim could be kenneled already, or have different roles.
+async fn schedule_kennel(
+    ctx: Context<'_>,
+    storage: &dyn Storage,
+    server: &Server,
+    user: UserId,
+    kennel_length: Duration,
+    start: &str,
+    reason: Option<String>,
+) -> Result<()> {
+    let now = chrono::Utc::now();
 
+    let start_at = match parse_start_time(start, now) {
+        Ok(start_at) => start_at,
+        Err(e) => return ctx.reply_ephemeral(e.to_string()).await,
+    };
+
+    if start_at <= now {
+        return ctx.reply_ephemeral("That's in the past!").await;
+    }
+
+    if !server.can_enforce_until(now + kennel_length) {
+        return ctx.reply_ephemeral(too_long_message(server)).await;
+    }
+
+    let mut kenneling = Kenneling {
+        kenneled_at: start_at,
+        released_at: start_at + kennel_length,
+        status: KennelingStatus::Pending,
+        reason,
+        ..Kenneling::new(server.guild_id, user, ctx.author().id, kennel_length)
+    };
+
+    let id = storage.insert_pending_kenneling(&kenneling).await?;
+    kenneling.id = Some(id);
+
+    let reply_handle = ctx
+        .reply(format!(
+            "<@{user}> will be locked away in the kennel {} for {}. Reason: {} (scheduled kenneling #{id})",
+            start_at.discord_relative_timestamp(),
+            humantime::format_duration(kennel_length),
+            kenneling.display_reason()
+        ))
+        .await?;
+
+    // This message turns into the announcement once the kenneling starts.
+    let message = reply_handle.message().await?;
     kenneling
-        .unapply_kennel(http, pool, true, reply_handle.as_ref(), Some(&ctx))
+        .set_announcement(storage, (message.channel_id, message.id))
         .await?;
 
+    tracing::info!(
+        "{} scheduled kenneling {id} of {user}, starting at {start_at}",
+        ctx.author().display_name()
+    );
+
     Ok(())
 }
 
+/// The refusal for a kenneling that would outlast what the server's punishment mode can enforce.
+pub fn too_long_message(server: &Server) -> String {
+    match server.punishment_mode.max_remaining() {
+        Some(max_remaining) => format!(
+            "Discord timeouts can't last longer than {}!",
+            humantime::format_duration(max_remaining)
+        ),
+        None => "That's too long!".to_string(),
+    }
+}
+
 /// A more-or-less from-scratch implementation of the Poise framework's command handler.
 /// Necessary so that new commands can be created by users and registered while the bot is running.
 pub async fn wildcard_command_handler(
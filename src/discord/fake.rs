@@ -0,0 +1,339 @@
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Utc};
+use serenity::all::{ChannelId, GuildId, Member, MessageId, Role, RoleId, UserId};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use crate::discord::traits::Discord;
+
+/// Something the bot did to a guild, as recorded by [`FakeDiscord`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Call {
+    AddRole {
+        guild_id: GuildId,
+        user_id: UserId,
+        role_id: RoleId,
+    },
+    RemoveRole {
+        guild_id: GuildId,
+        user_id: UserId,
+        role_id: RoleId,
+    },
+    SetRoles {
+        guild_id: GuildId,
+        user_id: UserId,
+        role_ids: Vec<RoleId>,
+    },
+    SetTimeout {
+        guild_id: GuildId,
+        user_id: UserId,
+        until: Option<DateTime<Utc>>,
+    },
+    SendMessage {
+        channel_id: ChannelId,
+        content: String,
+    },
+    EditMessage {
+        channel_id: ChannelId,
+        message_id: MessageId,
+        content: String,
+    },
+}
+
+/// [`Discord`] that keeps a pretend set of guilds in memory and records everything done to them. Meant for tests.
+///
+/// Members and roles have to be added up front. Changing a member who isn't there fails, the way it would on Discord.
+#[derive(Debug, Default)]
+pub struct FakeDiscord {
+    state: Mutex<FakeState>,
+}
+
+#[derive(Debug, Default)]
+struct FakeState {
+    members: HashMap<(GuildId, UserId), Member>,
+    /// Who took the punishment off of a member by hand and when, for the audit log lookups.
+    removed_by: HashMap<(GuildId, UserId), (UserId, DateTime<Utc>)>,
+    roles: HashMap<GuildId, Vec<Role>>,
+    owners: HashMap<GuildId, UserId>,
+    messages: HashMap<MessageId, String>,
+    calls: Vec<Call>,
+    next_message_id: u64,
+}
+
+impl FakeState {
+    fn member(&mut self, guild_id: GuildId, user_id: UserId) -> Result<&mut Member> {
+        self.members
+            .get_mut(&(guild_id, user_id))
+            .ok_or_else(|| anyhow!("Unknown Member {user_id} in guild {guild_id}"))
+    }
+}
+
+impl FakeDiscord {
+    /// The user the bot is logged in as.
+    pub const BOT_ID: UserId = UserId::new(u64::MAX >> 1);
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Puts a member with the given roles in a guild, replacing them if they're already there.
+    pub fn add_member(&self, guild_id: GuildId, user_id: UserId, role_ids: &[RoleId]) {
+        let mut member = Member::default();
+        member.guild_id = guild_id;
+        member.user.id = user_id;
+        member.roles = role_ids.to_vec();
+
+        self.lock().members.insert((guild_id, user_id), member);
+    }
+
+    /// Takes a member out of a guild, as if they left.
+    pub fn remove_member(&self, guild_id: GuildId, user_id: UserId) {
+        self.lock().members.remove(&(guild_id, user_id));
+    }
+
+    /// Creates a role in a guild, above the ones that are already there.
+    pub fn add_guild_role(&self, guild_id: GuildId, role_id: RoleId) {
+        let mut state = self.lock();
+        let roles = state.roles.entry(guild_id).or_default();
+
+        let mut role = Role::default();
+        role.guild_id = guild_id;
+        role.id = role_id;
+        role.position = roles.len() as u16 + 1;
+
+        roles.push(role);
+    }
+
+    /// Hands a guild over to a new owner.
+    pub fn set_guild_owner(&self, guild_id: GuildId, owner_id: UserId) {
+        self.lock().owners.insert(guild_id, owner_id);
+    }
+
+    /// The roles a member currently has, or [`None`] if they aren't in the guild.
+    pub fn roles_of(&self, guild_id: GuildId, user_id: UserId) -> Option<Vec<RoleId>> {
+        self.lock()
+            .members
+            .get(&(guild_id, user_id))
+            .map(|member| member.roles.clone())
+    }
+
+    /// Takes a role off of a member without recording a call, as if `removed_by` did it by hand.
+    pub fn remove_role_manually(
+        &self,
+        guild_id: GuildId,
+        user_id: UserId,
+        role_id: RoleId,
+        removed_by: UserId,
+    ) {
+        let mut state = self.lock();
+
+        if let Some(member) = state.members.get_mut(&(guild_id, user_id)) {
+            member.roles.retain(|member_role| *member_role != role_id);
+            state
+                .removed_by
+                .insert((guild_id, user_id), (removed_by, Utc::now()));
+        }
+    }
+
+    /// Who last took the punishment off of a member by hand, if it was at `since` or later.
+    fn removed_by(
+        &self,
+        guild_id: GuildId,
+        user_id: UserId,
+        since: DateTime<Utc>,
+    ) -> Option<UserId> {
+        self.lock()
+            .removed_by
+            .get(&(guild_id, user_id))
+            .filter(|(_, removed_at)| *removed_at >= since)
+            .map(|(removed_by, _)| *removed_by)
+    }
+
+    /// The current content of a message the bot sent.
+    pub fn message(&self, message_id: MessageId) -> Option<String> {
+        self.lock().messages.get(&message_id).cloned()
+    }
+
+    /// Everything done so far, in order.
+    pub fn calls(&self) -> Vec<Call> {
+        self.lock().calls.clone()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, FakeState> {
+        self.state.lock().expect("FakeDiscord lock poisoned")
+    }
+}
+
+#[async_trait::async_trait]
+impl Discord for FakeDiscord {
+    async fn member(&self, guild_id: GuildId, user_id: UserId) -> Result<Option<Member>> {
+        Ok(self.lock().members.get(&(guild_id, user_id)).cloned())
+    }
+
+    async fn members(&self, guild_id: GuildId) -> Result<Vec<Member>> {
+        Ok(self
+            .lock()
+            .members
+            .iter()
+            .filter(|((member_guild_id, _), _)| *member_guild_id == guild_id)
+            .map(|(_, member)| member.clone())
+            .collect())
+    }
+
+    async fn guild_roles(&self, guild_id: GuildId) -> Result<Vec<Role>> {
+        Ok(self
+            .lock()
+            .roles
+            .get(&guild_id)
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    async fn guild_owner(&self, guild_id: GuildId) -> Result<UserId> {
+        self.lock()
+            .owners
+            .get(&guild_id)
+            .copied()
+            .ok_or_else(|| anyhow!("Unknown guild {guild_id}"))
+    }
+
+    async fn add_role(
+        &self,
+        guild_id: GuildId,
+        user_id: UserId,
+        role_id: RoleId,
+        _audit_log_reason: &str,
+    ) -> Result<()> {
+        let mut state = self.lock();
+        let member = state.member(guild_id, user_id)?;
+
+        if !member.roles.contains(&role_id) {
+            member.roles.push(role_id);
+        }
+
+        state.calls.push(Call::AddRole {
+            guild_id,
+            user_id,
+            role_id,
+        });
+
+        Ok(())
+    }
+
+    async fn remove_role(
+        &self,
+        guild_id: GuildId,
+        user_id: UserId,
+        role_id: RoleId,
+        _audit_log_reason: &str,
+    ) -> Result<()> {
+        let mut state = self.lock();
+        state
+            .member(guild_id, user_id)?
+            .roles
+            .retain(|member_role| *member_role != role_id);
+
+        state.calls.push(Call::RemoveRole {
+            guild_id,
+            user_id,
+            role_id,
+        });
+
+        Ok(())
+    }
+
+    async fn set_roles(
+        &self,
+        guild_id: GuildId,
+        user_id: UserId,
+        role_ids: &[RoleId],
+        _audit_log_reason: &str,
+    ) -> Result<()> {
+        let mut state = self.lock();
+        state.member(guild_id, user_id)?.roles = role_ids.to_vec();
+
+        state.calls.push(Call::SetRoles {
+            guild_id,
+            user_id,
+            role_ids: role_ids.to_vec(),
+        });
+
+        Ok(())
+    }
+
+    async fn set_timeout(
+        &self,
+        guild_id: GuildId,
+        user_id: UserId,
+        until: Option<DateTime<Utc>>,
+        _audit_log_reason: &str,
+    ) -> Result<()> {
+        let mut state = self.lock();
+        state
+            .member(guild_id, user_id)?
+            .communication_disabled_until = until.map(Into::into);
+
+        state.calls.push(Call::SetTimeout {
+            guild_id,
+            user_id,
+            until,
+        });
+
+        Ok(())
+    }
+
+    async fn role_removed_by(
+        &self,
+        guild_id: GuildId,
+        user_id: UserId,
+        _role_id: RoleId,
+        since: DateTime<Utc>,
+    ) -> Result<Option<UserId>> {
+        Ok(self.removed_by(guild_id, user_id, since))
+    }
+
+    async fn timeout_lifted_by(
+        &self,
+        guild_id: GuildId,
+        user_id: UserId,
+        since: DateTime<Utc>,
+    ) -> Result<Option<UserId>> {
+        Ok(self.removed_by(guild_id, user_id, since))
+    }
+
+    async fn send_message(&self, channel_id: ChannelId, content: &str) -> Result<MessageId> {
+        let mut state = self.lock();
+        state.next_message_id += 1;
+        let message_id = MessageId::new(state.next_message_id);
+
+        state.messages.insert(message_id, content.to_string());
+        state.calls.push(Call::SendMessage {
+            channel_id,
+            content: content.to_string(),
+        });
+
+        Ok(message_id)
+    }
+
+    async fn edit_message(
+        &self,
+        channel_id: ChannelId,
+        message_id: MessageId,
+        content: &str,
+    ) -> Result<()> {
+        let mut state = self.lock();
+        let message = state
+            .messages
+            .get_mut(&message_id)
+            .ok_or_else(|| anyhow!("Unknown Message {message_id}"))?;
+        *message = content.to_string();
+
+        state.calls.push(Call::EditMessage {
+            channel_id,
+            message_id,
+            content: content.to_string(),
+        });
+
+        Ok(())
+    }
+}
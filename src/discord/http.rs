@@ -0,0 +1,257 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serenity::all::audit_log::{Action, Change, MemberAction};
+use serenity::all::{
+    AuditLogEntry, ChannelId, CreateMessage, EditMember, EditMessage, GuildId, Http, Member,
+    MessageId, Role, RoleId, Timestamp, UserId,
+};
+
+use crate::discord::traits::Discord;
+use crate::util::discord_errors::is_missing_member;
+
+/// The most members Discord hands out per request.
+const MEMBERS_PAGE_SIZE: u64 = 1000;
+/// How many recent audit log entries to look through when working out who changed a member.
+const AUDIT_LOG_LOOKBACK: u8 = 25;
+/// The longest audit log reason Discord accepts, in characters.
+const MAX_AUDIT_LOG_REASON: usize = 512;
+
+/// Cuts an audit log reason down to what Discord accepts, since one that's too long fails the whole request.
+fn truncate_audit_log_reason(reason: &str) -> &str {
+    match reason.char_indices().nth(MAX_AUDIT_LOG_REASON) {
+        Some((end, _)) => &reason[..end],
+        None => reason,
+    }
+}
+
+/// The most recent audit log entries of one kind about a member, newest first. Entries from before `since` are left
+/// out, so that an older change can't be mistaken for a newer one.
+async fn member_audit_log(
+    http: &Http,
+    guild_id: GuildId,
+    user_id: UserId,
+    action: MemberAction,
+    since: DateTime<Utc>,
+) -> Result<Vec<AuditLogEntry>> {
+    let since = Timestamp::from(since);
+
+    let logs = http
+        .get_audit_logs(
+            guild_id,
+            Some(Action::Member(action)),
+            None,
+            None,
+            Some(AUDIT_LOG_LOOKBACK),
+        )
+        .await?;
+
+    Ok(logs
+        .entries
+        .into_iter()
+        .filter(|entry| entry.target_id.map(|target_id| target_id.get()) == Some(user_id.get()))
+        .filter(|entry| entry.id.created_at() >= since)
+        .collect())
+}
+
+#[async_trait::async_trait]
+impl Discord for Http {
+    async fn member(&self, guild_id: GuildId, user_id: UserId) -> Result<Option<Member>> {
+        match self.get_member(guild_id, user_id).await {
+            Ok(member) => Ok(Some(member)),
+            Err(e) if is_missing_member(&e) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn members(&self, guild_id: GuildId) -> Result<Vec<Member>> {
+        let mut members = Vec::new();
+        let mut after = None;
+
+        loop {
+            let page = self
+                .get_guild_members(guild_id, Some(MEMBERS_PAGE_SIZE), after)
+                .await?;
+            let done = page.len() < MEMBERS_PAGE_SIZE as usize;
+
+            after = page.last().map(|member| member.user.id.get());
+            members.extend(page);
+
+            if done {
+                return Ok(members);
+            }
+        }
+    }
+
+    async fn guild_roles(&self, guild_id: GuildId) -> Result<Vec<Role>> {
+        Ok(self.get_guild_roles(guild_id).await?)
+    }
+
+    async fn guild_owner(&self, guild_id: GuildId) -> Result<UserId> {
+        Ok(self.get_guild(guild_id).await?.owner_id)
+    }
+
+    async fn add_role(
+        &self,
+        guild_id: GuildId,
+        user_id: UserId,
+        role_id: RoleId,
+        audit_log_reason: &str,
+    ) -> Result<()> {
+        Ok(self
+            .add_member_role(
+                guild_id,
+                user_id,
+                role_id,
+                Some(truncate_audit_log_reason(audit_log_reason)),
+            )
+            .await?)
+    }
+
+    async fn remove_role(
+        &self,
+        guild_id: GuildId,
+        user_id: UserId,
+        role_id: RoleId,
+        audit_log_reason: &str,
+    ) -> Result<()> {
+        Ok(self
+            .remove_member_role(
+                guild_id,
+                user_id,
+                role_id,
+                Some(truncate_audit_log_reason(audit_log_reason)),
+            )
+            .await?)
+    }
+
+    async fn set_roles(
+        &self,
+        guild_id: GuildId,
+        user_id: UserId,
+        role_ids: &[RoleId],
+        audit_log_reason: &str,
+    ) -> Result<()> {
+        guild_id
+            .edit_member(
+                self,
+                user_id,
+                EditMember::new()
+                    .roles(role_ids.iter().copied())
+                    .audit_log_reason(truncate_audit_log_reason(audit_log_reason)),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn set_timeout(
+        &self,
+        guild_id: GuildId,
+        user_id: UserId,
+        until: Option<DateTime<Utc>>,
+        audit_log_reason: &str,
+    ) -> Result<()> {
+        let edit = match until {
+            Some(until) => EditMember::new().disable_communication_until_datetime(until.into()),
+            None => EditMember::new().enable_communication(),
+        };
+
+        guild_id
+            .edit_member(
+                self,
+                user_id,
+                edit.audit_log_reason(truncate_audit_log_reason(audit_log_reason)),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn role_removed_by(
+        &self,
+        guild_id: GuildId,
+        user_id: UserId,
+        role_id: RoleId,
+        since: DateTime<Utc>,
+    ) -> Result<Option<UserId>> {
+        let entries =
+            member_audit_log(self, guild_id, user_id, MemberAction::RoleUpdate, since).await?;
+
+        // Discord lists the removed roles as the new value of a `$remove` change.
+        Ok(entries
+            .into_iter()
+            .find(|entry| {
+                entry.changes.iter().flatten().any(|change| {
+                    matches!(change, Change::RolesRemove { old, new }
+                        if old.iter().chain(new).flatten().any(|role| role.id == role_id))
+                })
+            })
+            .map(|entry| entry.user_id))
+    }
+
+    async fn timeout_lifted_by(
+        &self,
+        guild_id: GuildId,
+        user_id: UserId,
+        since: DateTime<Utc>,
+    ) -> Result<Option<UserId>> {
+        let entries =
+            member_audit_log(self, guild_id, user_id, MemberAction::Update, since).await?;
+
+        Ok(entries
+            .into_iter()
+            .find(|entry| {
+                entry.changes.iter().flatten().any(|change| {
+                    matches!(
+                        change,
+                        Change::CommunicationDisabledUntil {
+                            old: Some(_),
+                            new: None
+                        }
+                    )
+                })
+            })
+            .map(|entry| entry.user_id))
+    }
+
+    async fn send_message(&self, channel_id: ChannelId, content: &str) -> Result<MessageId> {
+        let message = channel_id
+            .send_message(self, CreateMessage::new().content(content))
+            .await?;
+
+        Ok(message.id)
+    }
+
+    async fn edit_message(
+        &self,
+        channel_id: ChannelId,
+        message_id: MessageId,
+        content: &str,
+    ) -> Result<()> {
+        channel_id
+            .edit_message(self, message_id, EditMessage::new().content(content))
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn long_audit_log_reasons_are_cut_down() {
+        let reason = "Kenneled by 3: ".to_string() + &"ü".repeat(600);
+
+        assert_eq!(
+            truncate_audit_log_reason(&reason).chars().count(),
+            MAX_AUDIT_LOG_REASON
+        );
+        assert!(reason.starts_with(truncate_audit_log_reason(&reason)));
+        assert_eq!(
+            truncate_audit_log_reason("Kenneled by 3: No reason given"),
+            "Kenneled by 3: No reason given"
+        );
+    }
+}
@@ -0,0 +1,87 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serenity::all::{ChannelId, GuildId, Member, MessageId, Role, RoleId, UserId};
+
+/// Everything the bot does to a guild when kenneling and releasing people, so that it can be swapped out in tests.
+///
+/// Commands still reply to their interactions through poise, since only the side effects need faking.
+#[async_trait::async_trait]
+pub trait Discord: Send + Sync {
+    /// Fetches a member of a guild, or [`None`] if they aren't in it.
+    async fn member(&self, guild_id: GuildId, user_id: UserId) -> Result<Option<Member>>;
+
+    /// Fetches every member of a guild.
+    async fn members(&self, guild_id: GuildId) -> Result<Vec<Member>>;
+
+    /// Fetches every role in a guild.
+    async fn guild_roles(&self, guild_id: GuildId) -> Result<Vec<Role>>;
+
+    /// Fetches who owns a guild.
+    async fn guild_owner(&self, guild_id: GuildId) -> Result<UserId>;
+
+    /// Gives a member a role.
+    async fn add_role(
+        &self,
+        guild_id: GuildId,
+        user_id: UserId,
+        role_id: RoleId,
+        audit_log_reason: &str,
+    ) -> Result<()>;
+
+    /// Takes a role off of a member.
+    async fn remove_role(
+        &self,
+        guild_id: GuildId,
+        user_id: UserId,
+        role_id: RoleId,
+        audit_log_reason: &str,
+    ) -> Result<()>;
+
+    /// Replaces all of a member's roles.
+    async fn set_roles(
+        &self,
+        guild_id: GuildId,
+        user_id: UserId,
+        role_ids: &[RoleId],
+        audit_log_reason: &str,
+    ) -> Result<()>;
+
+    /// Times a member out until `until`, or lifts their timeout if it's [`None`].
+    async fn set_timeout(
+        &self,
+        guild_id: GuildId,
+        user_id: UserId,
+        until: Option<DateTime<Utc>>,
+        audit_log_reason: &str,
+    ) -> Result<()>;
+
+    /// Looks through the audit log for whoever most recently took a role away from a member, leaving out anything
+    /// from before `since`.
+    async fn role_removed_by(
+        &self,
+        guild_id: GuildId,
+        user_id: UserId,
+        role_id: RoleId,
+        since: DateTime<Utc>,
+    ) -> Result<Option<UserId>>;
+
+    /// Looks through the audit log for whoever most recently lifted a member's timeout, leaving out anything from
+    /// before `since`.
+    async fn timeout_lifted_by(
+        &self,
+        guild_id: GuildId,
+        user_id: UserId,
+        since: DateTime<Utc>,
+    ) -> Result<Option<UserId>>;
+
+    /// Sends a message to a channel.
+    async fn send_message(&self, channel_id: ChannelId, content: &str) -> Result<MessageId>;
+
+    /// Replaces the content of a message.
+    async fn edit_message(
+        &self,
+        channel_id: ChannelId,
+        message_id: MessageId,
+        content: &str,
+    ) -> Result<()>;
+}
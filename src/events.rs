@@ -0,0 +1,125 @@
+//! Handles the gateway events the bot cares about, other than the wildcard commands.
+
+use anyhow::Result;
+use poise::FrameworkContext;
+use serenity::all::{FullEvent, GuildId, GuildMemberUpdateEvent, Member, UserId};
+use serenity::client::Context as SerenityCtx;
+use shame_bot::discord::Discord;
+use shame_bot::storage::Storage;
+use shame_bot::types::{KennelingStatus, Validation};
+
+use crate::ShameBotData;
+use crate::commands::wildcard::wildcard_command_handler;
+
+pub async fn event_handler(
+    ctx: &SerenityCtx,
+    event: &FullEvent,
+    framework_ctx: FrameworkContext<'_, ShameBotData, anyhow::Error>,
+    data: &ShameBotData,
+) -> Result<()> {
+    let storage = data.storage.as_ref();
+
+    match event {
+        FullEvent::GuildMemberRemoval { guild_id, user, .. } => {
+            pause_kenneling(storage, *guild_id, user.id).await
+        }
+        FullEvent::GuildMemberAddition { new_member } => {
+            resume_kenneling(
+                ctx.http.as_ref(),
+                storage,
+                new_member.guild_id,
+                new_member.user.id,
+            )
+            .await
+        }
+        FullEvent::GuildMemberUpdate { event, .. } => {
+            end_if_stale(ctx.http.as_ref(), storage, framework_ctx.bot_id, event).await
+        }
+        _ => wildcard_command_handler(ctx, event, framework_ctx, data).await,
+    }
+}
+
+/// Stops the clock on someone's kenneling when they leave, so that leaving isn't a way out of the kennel.
+async fn pause_kenneling(storage: &dyn Storage, guild_id: GuildId, user_id: UserId) -> Result<()> {
+    if let Some(kenneling) = storage.active_kenneling(guild_id, user_id).await?
+        && kenneling.pause(storage).await?
+    {
+        tracing::info!("Kenneled user {user_id} left guild {guild_id}, pausing their kenneling");
+    }
+
+    Ok(())
+}
+
+/// Puts someone back in the kennel for the rest of their sentence when they rejoin.
+async fn resume_kenneling(
+    discord: &dyn Discord,
+    storage: &dyn Storage,
+    guild_id: GuildId,
+    user_id: UserId,
+) -> Result<()> {
+    if let Some(kenneling) = storage.active_kenneling(guild_id, user_id).await?
+        && let Some(kenneling) = kenneling.resume(discord, storage).await?
+    {
+        tracing::info!(
+            "Kenneled user {user_id} rejoined guild {guild_id}, resuming their kenneling until {}",
+            kenneling.released_at
+        );
+    }
+
+    Ok(())
+}
+
+/// Deals with someone's punishment being taken off by hand as soon as it happens, instead of waiting for the
+/// healthcheck to notice. Depending on the server, that either ends their kenneling with the time served, or puts the
+/// punishment back. Updates that can't be pinned on anyone yet are left to the healthcheck.
+///
+/// The bot's own releases don't count: a kenneling's end is saved before its punishment comes off, so by the time the
+/// update comes in, it isn't running anymore.
+async fn end_if_stale(
+    discord: &dyn Discord,
+    storage: &dyn Storage,
+    bot_id: UserId,
+    event: &GuildMemberUpdateEvent,
+) -> Result<()> {
+    let Some(kenneling) = storage
+        .active_kenneling(event.guild_id, event.user.id)
+        .await?
+    else {
+        return Ok(());
+    };
+
+    // Paused kennelings aren't on anyone, and overdue ones are about to be released by the scheduler anyway.
+    if kenneling.status == KennelingStatus::Paused || kenneling.released_at <= chrono::Utc::now() {
+        return Ok(());
+    }
+
+    let Some(server) = storage.server(event.guild_id).await? else {
+        return Ok(());
+    };
+
+    // The update carries everything needed to tell whether the punishment is still on, so there's no need to wait
+    // for the member to be cached.
+    let mut victim = Member::default();
+    victim.guild_id = event.guild_id;
+    victim.user = event.user.clone();
+    victim.roles = event.roles.clone();
+    victim.communication_disabled_until = event.communication_disabled_until;
+
+    match kenneling
+        .validate_update(discord, storage, &server, bot_id, &victim)
+        .await?
+    {
+        Validation::EndedEarly(time_served) => tracing::info!(
+            "Kennel punishment was taken off of {} by hand! {kenneling:?} ended early. Time served: {}",
+            event.user.id,
+            humantime::format_duration(time_served)
+        ),
+        Validation::Enforced { removed_by } => tracing::info!(
+            "Kennel punishment was taken off of {} by {removed_by:?}! Put it back on for {kenneling:?}",
+            event.user.id
+        ),
+        Validation::Intact | Validation::Paused => {}
+    }
+
+    Ok(())
+}
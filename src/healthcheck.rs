@@ -1,99 +1,86 @@
-use std::time::Duration;
+use std::collections::HashMap;
 
-use serenity::all::{Http, RoleId};
-use shame_bot::{string_to_id, types::*};
-use sqlx::{PgPool, postgres::types::PgInterval};
+use serenity::all::UserId;
+use shame_bot::discord::Discord;
+use shame_bot::storage::Storage;
+use shame_bot::types::*;
 
 pub async fn check(
-    http: &Http,
-    pool: &PgPool,
+    discord: &dyn Discord,
+    storage: &dyn Storage,
+    bot_id: UserId,
 ) -> Result<(), Box<dyn std::error::Error + std::marker::Send + std::marker::Sync>> {
-    let active_kennelings: Vec<Kenneling> = sqlx::query_as!(
-        KennelingRow,
-        r#"
-        SELECT *
-        FROM kennelings
-        WHERE
-            released_at > CURRENT_TIMESTAMP
-            ;
-        "#
-    )
-    .fetch_all(pool)
-    .await?
-    .iter()
-    .map(|kr| kr.try_into().expect("malformed data inserted"))
-    .collect();
+    let running = storage.running_kennelings_by_server().await?;
 
-    for kenneling in active_kennelings {
-        let kennel_role = sqlx::query!(
-            r#"
-        SELECT role_id
-        FROM servers
-        WHERE
-            guild_id = $1
-            ;
-        "#,
-            kenneling.guild_id.to_string(),
-        )
-        .fetch_one(pool)
-        .await?;
-
-        let kennel_role: RoleId = string_to_id(&kennel_role.role_id)?;
+    for (server, kennelings) in running {
+        // One guild the bot can't see into anymore shouldn't keep the others from being checked.
+        if let Err(e) = check_guild(discord, storage, bot_id, &server, kennelings).await {
+            tracing::error!("Healthcheck failed for guild {}: {e:?}", server.guild_id);
+        }
+    }
 
-        validate_kenneling(http, pool, kenneling, kennel_role).await?;
+    let paused_kennelings = storage.paused_kennelings().await?;
+
+    // Catches anyone who rejoined while the bot wasn't listening.
+    for kenneling in paused_kennelings {
+        match discord
+            .member(kenneling.guild_id, kenneling.victim_id)
+            .await
+        {
+            Ok(Some(_)) => {
+                tracing::info!("Paused victim is back! Resuming {kenneling:?}");
+
+                if let Err(e) = kenneling.resume(discord, storage).await {
+                    tracing::error!("Couldn't resume {kenneling:?}: {e:?}");
+                }
+            }
+            Ok(None) => {}
+            Err(e) => tracing::error!("Couldn't check on paused {kenneling:?}: {e:?}"),
+        }
     }
 
     Ok(())
 }
 
-async fn validate_kenneling(
-    http: &Http,
-    pool: &PgPool,
-    kenneling: Kenneling,
-    kennel_role: RoleId,
-) -> Result<(), Box<dyn std::error::Error + std::marker::Send + std::marker::Sync>> {
-    let Kenneling {
-        victim_id: victim,
-        guild_id,
-        ..
-    } = kenneling;
-
-    let guild = http.get_guild(guild_id).await?;
-    let victim = guild.member(http, victim).await?;
-
-    if !victim.roles.iter().any(|role| role == &kennel_role) {
-        tracing::info!("Stale kenneling detected! {kenneling:?}");
-
-        let kenneled_at = kenneling.kenneled_at;
-        let now = chrono::Utc::now();
-
-        let dur_served = now - kenneled_at;
-        let dur_served = Duration::from_secs(dur_served.num_seconds() as u64)
-            + Duration::from_micros(dur_served.subsec_micros() as u64);
-
-        let time_served = PgInterval::try_from(dur_served)?;
-
-        sqlx::query!(
-            r#"
-                UPDATE kennelings
-                SET
-                    kennel_length = $1
-                WHERE
-                    id = $2
-                    ;
-            "#,
-            time_served,
-            kenneling.id,
-        )
-        .execute(pool)
-        .await?;
-
-        tracing::info!(
-            "Kenneling ended early. Time served: {}",
-            humantime::format_duration(dur_served)
-        );
-
-        // TODO: Should set_activity, but with what context?'
+/// Validates every running kenneling in a guild, fetching the guild's members only once.
+async fn check_guild(
+    discord: &dyn Discord,
+    storage: &dyn Storage,
+    bot_id: UserId,
+    server: &Server,
+    kennelings: Vec<Kenneling>,
+) -> anyhow::Result<()> {
+    let members: HashMap<_, _> = discord
+        .members(server.guild_id)
+        .await?
+        .into_iter()
+        .map(|member| (member.user.id, member))
+        .collect();
+
+    for kenneling in kennelings {
+        let victim = members.get(&kenneling.victim_id);
+
+        match kenneling
+            .validate(discord, storage, server, bot_id, victim)
+            .await
+        {
+            Ok(Validation::Intact) => {}
+            Ok(Validation::Paused) => {
+                tracing::info!("Kenneled user left the guild! Paused {kenneling:?}");
+            }
+            Ok(Validation::EndedEarly(time_served)) => {
+                tracing::info!(
+                    "Stale kenneling detected! {kenneling:?} ended early. Time served: {}",
+                    humantime::format_duration(time_served)
+                );
+            }
+            Ok(Validation::Enforced { removed_by }) => {
+                tracing::info!(
+                    "Kennel punishment was taken off by {removed_by:?}! Put it back on for {kenneling:?}"
+                );
+            }
+            Err(e) => tracing::error!("Couldn't validate {kenneling:?}: {e:?}"),
+        }
     }
 
     Ok(())
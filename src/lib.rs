@@ -1,25 +1,69 @@
 use anyhow::Result;
-use serenity::all::{CommandOptionType, CreateCommand, CreateCommandOption, Permissions, UserId};
+use serenity::all::{
+    CommandOptionType, CreateCommand, CreateCommandOption, GuildId, Permissions, UserId,
+};
 use std::num::ParseIntError;
 
-use crate::util::pgint_dur::PgIntervalToDuration as _;
+use crate::storage::Storage;
 
+pub mod discord {
+    pub mod fake;
+    pub mod http;
+    pub mod traits;
+
+    pub use fake::FakeDiscord;
+    pub use traits::*;
+}
 pub mod util {
+    pub mod discord_errors;
     pub mod get_guild_id;
     pub mod pgint_dur;
+    pub mod snowflake;
+    pub mod start_time;
     pub mod stefan_traits;
 }
+pub mod storage {
+    pub mod memory;
+    pub mod postgres;
+    pub mod traits;
+
+    pub use memory::MemoryStorage;
+    pub use postgres::PgStorage;
+    pub use traits::*;
+}
 pub mod types {
+    pub mod adjustment;
     pub mod kenneling;
+    pub mod limits;
+    pub mod manual_release;
+    pub mod punishment;
+    pub mod refusal;
+    pub mod schedule;
     pub mod server;
+    pub mod stacking;
+    pub mod stats;
+    pub mod status;
+    pub mod vote;
 
+    pub use adjustment::*;
     pub use kenneling::*;
+    pub use limits::*;
+    pub use manual_release::*;
+    pub use punishment::*;
+    pub use refusal::*;
+    pub use schedule::*;
     pub use server::*;
+    pub use stacking::*;
+    pub use stats::*;
+    pub use status::*;
+    pub use vote::*;
 }
 
 // User data, which is stored and accessible in all command invocations
 pub struct ShameBotData {
-    pub pool: std::sync::Arc<sqlx::PgPool>,
+    pub storage: std::sync::Arc<dyn Storage>,
+    /// Guilds the bot owner has left out of the public stats, from `EXCLUDED_GUILDS`.
+    pub excluded_guilds: std::sync::Arc<Vec<GuildId>>,
 }
 
 pub type Context<'a> = poise::Context<'a, ShameBotData, anyhow::Error>;
@@ -45,41 +89,98 @@ pub fn get_kennel_command_struct(command: &str) -> CreateCommand {
             )
             .required(true),
         )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::String,
+                "reason",
+                "Why the user is being punished",
+            )
+            // Discord's limit on audit log reasons.
+            .max_length(512),
+        )
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::String,
+            "start",
+            "When to start, like 'in 2h' or '21:00' (UTC). Leave out to start right away",
+        ))
         .default_member_permissions(Permissions::MODERATE_MEMBERS)
 }
 
+/// What `$reason` turns into when a kenneling was given without one.
+pub const NO_REASON: &str = "No reason given";
+
+/// Fills in the placeholders of a configurable message. `kenneler` is what `$kenneler` turns into, which is usually a
+/// mention, like [`Kenneling::display_author`](types::Kenneling::display_author).
 pub fn get_formatted_message(
     message: &str,
     victim_id: &UserId,
-    author_id: &UserId,
+    kenneler: &str,
     time: &str,
     return_time: &str,
+    reason: &str,
 ) -> String {
     message
         .replace("$victim", format!("<@{victim_id}>").as_str())
-        .replace("$kenneler", format!("<@{author_id}>").as_str())
+        .replace("$kenneler", kenneler)
         .replace("$time", time)
         .replace("$return", return_time)
+        .replace("$reason", reason)
+}
+
+/// Parses a comma separated list of guild IDs, like the one in `EXCLUDED_GUILDS`.
+pub fn parse_guild_list(list: &str) -> Result<Vec<GuildId>, ParseIntError> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|guild_id| !guild_id.is_empty())
+        .map(string_to_id)
+        .collect()
+}
+
+/// Everything the bot's status rotates through, computed over the guilds that count towards the public stats.
+///
+/// Guilds count unless they've opted out with `/set_count_publicly`, or the bot owner excluded them.
+pub async fn activity_statuses(
+    storage: &dyn Storage,
+    excluded_guilds: &[GuildId],
+) -> Result<Vec<String>> {
+    let stats = storage.public_stats(excluded_guilds).await?;
+    let mut statuses = Vec::new();
+
+    if let Some(total) = stats.total_length {
+        statuses.push(format!(
+            "Kenneled users for {}",
+            humantime::format_duration(total)
+        ));
+    }
+
+    statuses.push(format!(
+        "{} user{} in the kennel right now",
+        stats.active,
+        if stats.active == 1 { "" } else { "s" }
+    ));
+    statuses.push(format!(
+        "Kenneling in {} server{}",
+        stats.guilds,
+        if stats.guilds == 1 { "" } else { "s" }
+    ));
+
+    Ok(statuses)
 }
 
-pub async fn set_activity(ctx: &serenity::prelude::Context, pool: &sqlx::PgPool) {
-    if let Ok(res) = sqlx::query!(
-        r#"
-        SELECT SUM(kennel_length)
-        FROM kennelings
-        WHERE 
-            NOT guild_id = '849505364764524565'
-            ;
-        "#
-    )
-    .fetch_one(pool)
-    .await
-    {
-        if let Some(sum) = res.sum {
-            ctx.set_activity(Some(serenity::all::ActivityData::custom(format!(
-                "Kenneled users for {}",
-                humantime::format_duration(sum.as_duration())
-            ))));
+/// Sets the bot's status to the `rotation`th of the [`activity_statuses`], wrapping around.
+pub async fn set_activity(
+    ctx: &serenity::prelude::Context,
+    storage: &dyn Storage,
+    excluded_guilds: &[GuildId],
+    rotation: usize,
+) {
+    match activity_statuses(storage, excluded_guilds).await {
+        Ok(statuses) if !statuses.is_empty() => {
+            ctx.set_activity(Some(serenity::all::ActivityData::custom(
+                statuses[rotation % statuses.len()].clone(),
+            )));
         }
+        Ok(_) => {}
+        Err(e) => tracing::warn!("Couldn't compute activity status: {e}"),
     }
 }
@@ -4,24 +4,40 @@ use std::time::Duration;
 use commands::setup_commands::*;
 use dotenv::dotenv;
 use poise::serenity_prelude as serenity;
-use serenity::all::{CacheHttp, GuildId};
+use serenity::all::CacheHttp;
+use shame_bot::storage::{PgStorage, Storage};
 use shame_bot::{Context, ShameBotData, set_activity};
 use sqlx::postgres::PgPoolOptions;
 use tracing_subscriber::layer::SubscriberExt;
 use tracing_subscriber::util::SubscriberInitExt;
 
-use crate::commands::utility::time_kenneled;
-use crate::commands::wildcard::wildcard_command_handler;
+use crate::commands::moderation::{kennel_adjust, kennel_pending, unkennel};
+use crate::commands::schedules::kennel_schedule;
+use crate::commands::stats::kennel_stats;
+use crate::commands::utility::{kennel_history, time_kenneled};
+use crate::commands::votes::vote_kennel;
 
+mod events;
 mod healthcheck;
+mod migrations;
+mod scheduler;
 mod commands {
+    pub mod moderation;
+    pub mod schedules;
     pub mod setup_commands;
+    pub mod stats;
     pub mod utility;
+    pub mod votes;
     pub mod wildcard;
 }
 
-/// The timeout between healthcehcks.
-const HEALTHCHECK_TIMEOUT: Duration = Duration::from_secs(30);
+/// The timeout between healthchecks. Punishments taken off by hand are picked up from member updates as they happen, so
+/// this only has to catch whatever slipped by while the bot wasn't listening.
+const HEALTHCHECK_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+/// The timeout between checks for kennelings that are due to be started or released.
+const SCHEDULER_TIMEOUT: Duration = Duration::from_secs(1);
+/// The timeout between changes of the bot's status.
+const ACTIVITY_TIMEOUT: Duration = Duration::from_secs(60);
 
 #[tokio::main]
 async fn main() {
@@ -34,17 +50,30 @@ async fn main() {
 
     let token = std::env::var("BOT_TOKEN").expect("missing BOT_TOKEN");
     let postgres_url = std::env::var("DATABASE_URL").expect("missing DATABASE_URL");
-    tracing::debug!("Connecting to database: {postgres_url}");
-    let intents = serenity::GatewayIntents::non_privileged();
-    let pool = Arc::new(
-        PgPoolOptions::new()
-            .max_connections(5)
-            .connect(&postgres_url)
-            .await
-            .expect("Couldn't connect to data   base! Aborting..."),
+    let excluded_guilds = Arc::new(
+        shame_bot::parse_guild_list(&std::env::var("EXCLUDED_GUILDS").unwrap_or_default())
+            .expect("Malformed EXCLUDED_GUILDS! Expected comma separated guild IDs"),
     );
-    // Pool reference for the healthcheck thread
-    let thread_pool = Arc::clone(&pool);
+    tracing::debug!("Connecting to database: {postgres_url}");
+    // Member events are needed to pause kennelings when people leave, resume them when they come back, and end them
+    // when someone takes the punishment off by hand.
+    let intents =
+        serenity::GatewayIntents::non_privileged() | serenity::GatewayIntents::GUILD_MEMBERS;
+    let pool = PgPoolOptions::new()
+        .max_connections(5)
+        .connect(&postgres_url)
+        .await
+        .expect("Couldn't connect to data   base! Aborting...");
+
+    if let Err(e) = migrations::run(&pool).await {
+        panic!("Couldn't migrate database! Aborting...: {e}");
+    }
+
+    let storage: Arc<dyn Storage> = Arc::new(PgStorage::new(pool));
+
+    // Storage references for the healthcheck and scheduler threads
+    let thread_storage = Arc::clone(&storage);
+    let scheduler_storage = Arc::clone(&storage);
 
     let framework = poise::Framework::builder()
         .options(poise::FrameworkOptions {
@@ -55,9 +84,24 @@ async fn main() {
                 set_kennel_message(),
                 set_release_message(),
                 set_kennel_channel(),
+                set_stacking_policy(),
+                set_manual_release(),
+                set_punishment_mode(),
+                set_strip_roles(),
+                set_count_publicly(),
+                set_kennel_votes(),
+                kennel_immunity(),
+                kennel_limits(),
                 time_kenneled(),
+                kennel_history(),
+                kennel_stats(),
+                unkennel(),
+                kennel_adjust(),
+                kennel_pending(),
+                kennel_schedule(),
+                vote_kennel(),
             ],
-            event_handler: |w, x, y, z| Box::pin(wildcard_command_handler(w, x, y, z)),
+            event_handler: |w, x, y, z| Box::pin(events::event_handler(w, x, y, z)),
             on_error: |error| {
                 async fn error_cb(error: poise::FrameworkError<'_, ShameBotData, anyhow::Error>) {
                     // Get rid of the unknown interaction errors because the kennel command triggers this.
@@ -74,37 +118,52 @@ async fn main() {
         })
         .setup(|ctx, _ready, framework| {
             Box::pin(async move {
-                let data = sqlx::query!(
-                    r#"
-                    SELECT * FROM servers
-                    ;
-                    "#
-                )
-                .fetch_all(pool.as_ref())
-                .await?;
+                let servers = storage.servers().await?;
                 tracing::info!("Setting up guild commands...");
-                for row in data {
-                    let cmd = shame_bot::get_kennel_command_struct(&row.command_name);
+                for server in servers {
+                    let cmd = shame_bot::get_kennel_command_struct(&server.command_name);
                     tracing::debug!(
                         "Initializing kennel command \"{}\" for guild {}",
-                        &row.command_name,
-                        &row.guild_id
+                        &server.command_name,
+                        &server.guild_id
                     );
 
-                    let guild_id: GuildId = shame_bot::string_to_id(&row.guild_id)?;
-
-                    ctx.http()
-                        .create_guild_commands(guild_id, &vec![cmd])
-                        .await?;
+                    // A guild the bot was removed from shouldn't keep it from starting up everywhere else.
+                    if let Err(e) = ctx
+                        .http()
+                        .create_guild_commands(server.guild_id, &vec![cmd])
+                        .await
+                    {
+                        tracing::error!(
+                            "Couldn't set up the kennel command for guild {}: {e:?}",
+                            server.guild_id
+                        );
+                    }
                 }
 
-                set_activity(ctx, pool.as_ref()).await;
+                let activity_ctx = ctx.clone();
+                let activity_storage = Arc::clone(&storage);
+                let activity_excluded_guilds = Arc::clone(&excluded_guilds);
+
+                tokio::spawn(async move {
+                    for rotation in 0.. {
+                        set_activity(
+                            &activity_ctx,
+                            activity_storage.as_ref(),
+                            &activity_excluded_guilds,
+                            rotation,
+                        )
+                        .await;
+                        tokio::time::sleep(ACTIVITY_TIMEOUT).await;
+                    }
+                });
 
                 poise::builtins::register_globally(ctx, &framework.options().commands).await?;
 
                 tracing::info!("Bot started!");
                 Ok(ShameBotData {
-                    pool: Arc::clone(&pool),
+                    storage: Arc::clone(&storage),
+                    excluded_guilds: Arc::clone(&excluded_guilds),
                 })
             })
         })
@@ -115,21 +174,49 @@ async fn main() {
         .await
         .unwrap();
 
+    let bot_id = client
+        .http
+        .get_current_user()
+        .await
+        .expect("Couldn't look up the bot's user! Aborting...")
+        .id;
     let thread_http = Arc::clone(&client.http);
+    let scheduler_http = Arc::clone(&client.http);
 
     // TODO: Should this be moved to inside the ready callback?
     tokio::spawn(async move {
         let http = thread_http.as_ref();
-        let pool = thread_pool.as_ref();
+        let storage = thread_storage.as_ref();
 
         loop {
-            if let Err(e) = healthcheck::check(http, pool).await {
+            if let Err(e) = healthcheck::check(http, storage, bot_id).await {
                 tracing::error!("Healthcheck failed!: {}", (*e).to_string());
             }
             tokio::time::sleep(HEALTHCHECK_TIMEOUT).await;
         }
     });
 
+    tokio::spawn(async move {
+        let http = scheduler_http.as_ref();
+        let storage = scheduler_storage.as_ref();
+
+        loop {
+            if let Err(e) = scheduler::run_schedules(storage).await {
+                tracing::error!(
+                    "Scheduler couldn't run kennel schedules!: {}",
+                    (*e).to_string()
+                );
+            }
+            if let Err(e) = scheduler::start_due(http, storage, bot_id).await {
+                tracing::error!("Scheduler couldn't start kennelings!: {}", (*e).to_string());
+            }
+            if let Err(e) = scheduler::release_due(http, storage).await {
+                tracing::error!("Scheduler failed!: {}", (*e).to_string());
+            }
+            tokio::time::sleep(SCHEDULER_TIMEOUT).await;
+        }
+    });
+
     tracing::info!("Bot starting...");
     client.start().await.unwrap();
     tracing::info!("Exiting...");
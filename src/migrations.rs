@@ -0,0 +1,36 @@
+use sqlx::PgPool;
+use sqlx::migrate::{Migrate as _, Migrator};
+
+/// The migrations in `migrations/`, embedded into the binary.
+static MIGRATOR: Migrator = sqlx::migrate!();
+
+/// Brings the database up to date with the migrations this binary was built with.
+///
+/// Refuses to touch a database that has migrations applied that this binary doesn't know about, since that means it
+/// was set up by a newer version of the bot, and this one would be working with a schema it wasn't built for.
+pub async fn run(
+    pool: &PgPool,
+) -> Result<(), Box<dyn std::error::Error + std::marker::Send + std::marker::Sync>> {
+    let mut conn = pool.acquire().await?;
+    conn.ensure_migrations_table().await?;
+
+    let applied = conn.list_applied_migrations().await?;
+    drop(conn);
+
+    if let Some(unknown) = applied.iter().find(|applied| {
+        !MIGRATOR
+            .iter()
+            .any(|migration| migration.version == applied.version)
+    }) {
+        return Err(format!(
+            "The database has migration {} applied, which this version of the bot doesn't know about. Is it out of date?",
+            unknown.version
+        )
+        .into());
+    }
+
+    tracing::info!("Applying database migrations...");
+    MIGRATOR.run(pool).await?;
+
+    Ok(())
+}
@@ -0,0 +1,115 @@
+use serenity::all::UserId;
+use shame_bot::discord::Discord;
+use shame_bot::storage::Storage;
+use shame_bot::types::StartOutcome;
+
+/// How long after a kenneling was due the scheduler keeps trying to start or release it, before giving up on it.
+const GRACE_PERIOD: chrono::TimeDelta = chrono::TimeDelta::hours(1);
+
+/// Releases every kenneling that is due, but hasn't been released yet.
+///
+/// Since this only relies on what's in the database, anything that was due while the bot was offline gets released
+/// on the first pass after startup.
+pub async fn release_due(
+    discord: &dyn Discord,
+    storage: &dyn Storage,
+) -> Result<(), Box<dyn std::error::Error + std::marker::Send + std::marker::Sync>> {
+    let due_kennelings = storage.due_kennelings().await?;
+
+    for kenneling in due_kennelings {
+        // If they left while the bot wasn't watching, they don't get to sit out the rest of their sentence.
+        if let Ok(None) = discord
+            .member(kenneling.guild_id, kenneling.victim_id)
+            .await
+        {
+            tracing::info!(
+                "Victim of kenneling {:?} left the guild, pausing instead of releasing",
+                kenneling.id
+            );
+            kenneling.pause(storage).await?;
+            continue;
+        }
+
+        // One bad release (missing permissions, deleted role...) shouldn't hold up everyone else's.
+        match kenneling.release(discord, storage).await {
+            Ok(true) => tracing::debug!("Released kenneling {:?}", kenneling.id),
+            Ok(false) => tracing::debug!("Kenneling {:?} was already released", kenneling.id),
+            Err(e) if chrono::Utc::now() - kenneling.released_at > GRACE_PERIOD => {
+                tracing::error!(
+                    "Couldn't release kenneling {:?}, giving up: {e:?}",
+                    kenneling.id
+                );
+                kenneling.fail(storage, &e.to_string()).await?;
+            }
+            Err(e) => tracing::error!("Couldn't release kenneling {:?}: {e:?}", kenneling.id),
+        }
+    }
+
+    Ok(())
+}
+
+/// Starts every pending kenneling whose start time has come.
+///
+/// Like releases, anything that was due to start while the bot was offline starts on the first pass after startup,
+/// and the victim still serves the whole sentence.
+pub async fn start_due(
+    discord: &dyn Discord,
+    storage: &dyn Storage,
+    bot_id: UserId,
+) -> Result<(), Box<dyn std::error::Error + std::marker::Send + std::marker::Sync>> {
+    let starting_kennelings = storage.starting_kennelings().await?;
+
+    for kenneling in starting_kennelings {
+        match kenneling.start(discord, storage, bot_id).await {
+            Ok(StartOutcome::Started) => tracing::debug!("Started kenneling {:?}", kenneling.id),
+            Ok(StartOutcome::Paused) => tracing::info!(
+                "Victim of kenneling {:?} isn't in the guild, so it starts out paused",
+                kenneling.id
+            ),
+            Ok(StartOutcome::Stacked) => tracing::info!(
+                "Victim of kenneling {:?} was already kenneled, so it was stacked onto that",
+                kenneling.id
+            ),
+            Ok(StartOutcome::Cancelled(reason)) => {
+                tracing::info!("Kenneling {:?} was cancelled: {reason}", kenneling.id)
+            }
+            Ok(StartOutcome::Skipped) => {
+                tracing::debug!("Kenneling {:?} was already started", kenneling.id)
+            }
+            Err(e) if chrono::Utc::now() - kenneling.kenneled_at > GRACE_PERIOD => {
+                tracing::error!(
+                    "Couldn't start kenneling {:?}, giving up: {e:?}",
+                    kenneling.id
+                );
+                kenneling
+                    .cancel(
+                        discord,
+                        storage,
+                        None,
+                        Some(&format!("Couldn't start: {e}")),
+                    )
+                    .await?;
+            }
+            Err(e) => tracing::error!("Couldn't start kenneling {:?}: {e:?}", kenneling.id),
+        }
+    }
+
+    Ok(())
+}
+
+/// Turns every kennel schedule that has come up into a pending kenneling, which [`start_due`] starts right after.
+pub async fn run_schedules(
+    storage: &dyn Storage,
+) -> Result<(), Box<dyn std::error::Error + std::marker::Send + std::marker::Sync>> {
+    let due_schedules = storage.due_schedules().await?;
+
+    for schedule in due_schedules {
+        match schedule.run(storage, chrono::Utc::now()).await {
+            Ok(true) => tracing::debug!("Ran kennel schedule {:?}", schedule.id),
+            Ok(false) => tracing::debug!("Kennel schedule {:?} was already run", schedule.id),
+            Err(e) => tracing::error!("Couldn't run kennel schedule {:?}: {e:?}", schedule.id),
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,960 @@
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Utc};
+use serenity::all::{ChannelId, GuildId, MessageId, RoleId, UserId};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+use crate::storage::traits::{Effect, End, Storage};
+use crate::types::kenneling::Kenneling;
+use crate::types::schedule::KennelSchedule;
+use crate::types::server::Server;
+use crate::types::stats::{GuildStats, Leaderboard, LeaderboardEntry, PublicStats};
+use crate::types::status::KennelingStatus;
+
+/// [`Storage`] that only lives as long as the process does. Meant for tests, and for trying the bot out without a
+/// database.
+#[derive(Debug, Default)]
+pub struct MemoryStorage {
+    // Held across effects, so that a start or resume is all or nothing, just like a transaction.
+    state: Mutex<MemoryState>,
+}
+
+#[derive(Debug, Default)]
+struct MemoryState {
+    servers: HashMap<GuildId, Server>,
+    role_limits: Vec<(GuildId, RoleId, Duration)>,
+    kennelings: Vec<Kenneling>,
+    next_id: i32,
+    schedules: Vec<KennelSchedule>,
+    next_schedule_id: i32,
+}
+
+impl MemoryStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl MemoryState {
+    fn kenneling(&mut self, id: i32) -> Option<&mut Kenneling> {
+        self.kennelings
+            .iter_mut()
+            .find(|kenneling| kenneling.id == Some(id))
+    }
+
+    /// Fails if the victim is already serving a kenneling in the guild, other than `except`, like the unique index
+    /// on running kennelings does in Postgres.
+    fn ensure_not_running(
+        &self,
+        guild_id: GuildId,
+        victim_id: UserId,
+        except: Option<i32>,
+    ) -> Result<()> {
+        if self.kennelings.iter().any(|kenneling| {
+            kenneling.guild_id == guild_id
+                && kenneling.victim_id == victim_id
+                && kenneling.status.is_open()
+                && kenneling.id != except
+        }) {
+            return Err(anyhow!(
+                "{victim_id} is already serving a kenneling in {guild_id}"
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn insert_pending_kenneling(&mut self, kenneling: &Kenneling) -> i32 {
+        self.next_id += 1;
+        let id = self.next_id;
+
+        let mut kenneling = Kenneling {
+            id: Some(id),
+            status: KennelingStatus::Pending,
+            original_length: kenneling.kennel_length,
+            ended_at: None,
+            ended_by: None,
+            end_reason: None,
+            paused_at: None,
+            paused_length: Duration::ZERO,
+            stripped_roles: Vec::new(),
+            ..kenneling.clone()
+        };
+        update_released_at(&mut kenneling);
+
+        self.kennelings.push(kenneling);
+
+        id
+    }
+
+    fn schedule(&mut self, guild_id: GuildId, id: i32) -> Option<&mut KennelSchedule> {
+        self.schedules
+            .iter_mut()
+            .find(|schedule| schedule.guild_id == guild_id && schedule.id == Some(id))
+    }
+
+    /// Whether a guild's kennelings count towards the public stats.
+    fn counts_publicly(&self, guild_id: GuildId, excluded_guilds: &[GuildId]) -> bool {
+        !excluded_guilds.contains(&guild_id)
+            && self
+                .servers
+                .get(&guild_id)
+                .is_none_or(|server| server.count_publicly)
+    }
+}
+
+/// Recalculates when a kenneling ends, which the database does on its own.
+fn update_released_at(kenneling: &mut Kenneling) {
+    kenneling.released_at = kenneling.kenneled_at
+        + chrono::Duration::from_std(kenneling.kennel_length + kenneling.paused_length)
+            .unwrap_or(chrono::TimeDelta::MAX);
+}
+
+/// The time from `from` until `to`, or nothing if `to` is earlier.
+fn since(from: DateTime<Utc>, to: DateTime<Utc>) -> Duration {
+    (to - from).to_std().unwrap_or_default()
+}
+
+#[async_trait::async_trait]
+impl Storage for MemoryStorage {
+    async fn server(&self, guild_id: GuildId) -> Result<Option<Server>> {
+        Ok(self.state.lock().await.servers.get(&guild_id).cloned())
+    }
+
+    async fn servers(&self) -> Result<Vec<Server>> {
+        Ok(self.state.lock().await.servers.values().cloned().collect())
+    }
+
+    async fn upsert_server(&self, server: &Server) -> Result<()> {
+        self.state
+            .lock()
+            .await
+            .servers
+            .insert(server.guild_id, server.clone());
+
+        Ok(())
+    }
+
+    async fn role_limits(&self, guild_id: GuildId) -> Result<Vec<(RoleId, Duration)>> {
+        let mut limits: Vec<_> = self
+            .state
+            .lock()
+            .await
+            .role_limits
+            .iter()
+            .filter(|(limit_guild_id, _, _)| *limit_guild_id == guild_id)
+            .map(|(_, role_id, max)| (*role_id, *max))
+            .collect();
+
+        limits.sort_by_key(|(_, max)| *max);
+
+        Ok(limits)
+    }
+
+    async fn set_role_limit(
+        &self,
+        guild_id: GuildId,
+        role_id: RoleId,
+        max: Option<Duration>,
+    ) -> Result<()> {
+        let mut state = self.state.lock().await;
+
+        state
+            .role_limits
+            .retain(|(limit_guild_id, limit_role_id, _)| {
+                (*limit_guild_id, *limit_role_id) != (guild_id, role_id)
+            });
+
+        if let Some(max) = max {
+            state.role_limits.push((guild_id, role_id, max));
+        }
+
+        Ok(())
+    }
+
+    async fn insert_pending_kenneling(&self, kenneling: &Kenneling) -> Result<i32> {
+        Ok(self.state.lock().await.insert_pending_kenneling(kenneling))
+    }
+
+    async fn pending_kennelings(&self, guild_id: Option<GuildId>) -> Result<Vec<Kenneling>> {
+        let mut pending: Vec<_> = self
+            .state
+            .lock()
+            .await
+            .kennelings
+            .iter()
+            .filter(|kenneling| {
+                kenneling.status == KennelingStatus::Pending
+                    && guild_id.is_none_or(|guild_id| kenneling.guild_id == guild_id)
+            })
+            .cloned()
+            .collect();
+
+        pending.sort_by_key(|kenneling| kenneling.kenneled_at);
+
+        Ok(pending)
+    }
+
+    async fn starting_kennelings(&self) -> Result<Vec<Kenneling>> {
+        let now = Utc::now();
+
+        Ok(self
+            .pending_kennelings(None)
+            .await?
+            .into_iter()
+            .filter(|kenneling| kenneling.kenneled_at <= now)
+            .collect())
+    }
+
+    async fn start(
+        &self,
+        id: i32,
+        stripped_roles: &[RoleId],
+        replacing: Option<(i32, End)>,
+        apply: Effect<'_>,
+    ) -> Result<Option<Kenneling>> {
+        let mut state = self.state.lock().await;
+
+        let Some(mut started) = state
+            .kenneling(id)
+            .filter(|kenneling| kenneling.status == KennelingStatus::Pending)
+            .cloned()
+        else {
+            return Ok(None);
+        };
+
+        let replaced_id = replacing.as_ref().map(|(replaced_id, _)| *replaced_id);
+        state.ensure_not_running(started.guild_id, started.victim_id, replaced_id)?;
+
+        let now = Utc::now();
+
+        // If it was ended in the meantime, there's nothing left to replace, and the new one starts all the same.
+        let replaced = replacing.and_then(|(replaced_id, end)| {
+            let mut replaced = state
+                .kenneling(replaced_id)
+                .filter(|kenneling| kenneling.status.is_open())
+                .cloned()?;
+
+            replaced.status = end.status;
+            replaced.ended_at = Some(now);
+            replaced.ended_by = end.ended_by;
+            replaced.end_reason = end.reason;
+
+            Some(replaced)
+        });
+
+        started.status = KennelingStatus::Active;
+        started.kenneled_at = now;
+        started.stripped_roles = stripped_roles.to_vec();
+        update_released_at(&mut started);
+
+        apply(started.clone()).await?;
+
+        for updated in replaced.into_iter().chain([started.clone()]) {
+            if let Some(kenneling) = state.kenneling(updated.id.unwrap_or_default()) {
+                *kenneling = updated;
+            }
+        }
+
+        Ok(Some(started))
+    }
+
+    async fn cancel(
+        &self,
+        id: i32,
+        cancelled_by: Option<UserId>,
+        reason: Option<String>,
+    ) -> Result<Option<Kenneling>> {
+        let mut state = self.state.lock().await;
+
+        let Some(kenneling) = state
+            .kenneling(id)
+            .filter(|kenneling| kenneling.status == KennelingStatus::Pending)
+        else {
+            return Ok(None);
+        };
+
+        kenneling.status = KennelingStatus::Cancelled;
+        kenneling.ended_at = Some(Utc::now());
+        kenneling.ended_by = cancelled_by;
+        kenneling.end_reason = reason;
+
+        Ok(Some(kenneling.clone()))
+    }
+
+    async fn active_kenneling(
+        &self,
+        guild_id: GuildId,
+        victim_id: UserId,
+    ) -> Result<Option<Kenneling>> {
+        Ok(self
+            .state
+            .lock()
+            .await
+            .kennelings
+            .iter()
+            .filter(|kenneling| {
+                kenneling.guild_id == guild_id
+                    && kenneling.victim_id == victim_id
+                    && kenneling.status.is_open()
+            })
+            .max_by_key(|kenneling| kenneling.released_at)
+            .cloned())
+    }
+
+    async fn running_kennelings(&self, guild_id: Option<GuildId>) -> Result<Vec<Kenneling>> {
+        let now = Utc::now();
+
+        Ok(self
+            .state
+            .lock()
+            .await
+            .kennelings
+            .iter()
+            .filter(|kenneling| {
+                kenneling.released_at > now
+                    && kenneling.status == KennelingStatus::Active
+                    && guild_id.is_none_or(|guild_id| kenneling.guild_id == guild_id)
+            })
+            .cloned()
+            .collect())
+    }
+
+    async fn running_kennelings_by_server(&self) -> Result<Vec<(Server, Vec<Kenneling>)>> {
+        let running = self.running_kennelings(None).await?;
+        let state = self.state.lock().await;
+        let mut by_server: Vec<(Server, Vec<Kenneling>)> = Vec::new();
+
+        for kenneling in running {
+            // Like the join in Postgres, kennelings in servers that were never set up are left out.
+            let Some(server) = state.servers.get(&kenneling.guild_id) else {
+                continue;
+            };
+
+            match by_server
+                .iter_mut()
+                .find(|(existing, _)| existing.guild_id == server.guild_id)
+            {
+                Some((_, kennelings)) => kennelings.push(kenneling),
+                None => by_server.push((server.clone(), vec![kenneling])),
+            }
+        }
+
+        Ok(by_server)
+    }
+
+    async fn paused_kennelings(&self) -> Result<Vec<Kenneling>> {
+        Ok(self
+            .state
+            .lock()
+            .await
+            .kennelings
+            .iter()
+            .filter(|kenneling| kenneling.status == KennelingStatus::Paused)
+            .cloned()
+            .collect())
+    }
+
+    async fn due_kennelings(&self) -> Result<Vec<Kenneling>> {
+        let now = Utc::now();
+
+        let mut due: Vec<_> = self
+            .state
+            .lock()
+            .await
+            .kennelings
+            .iter()
+            .filter(|kenneling| {
+                kenneling.released_at <= now && kenneling.status == KennelingStatus::Active
+            })
+            .cloned()
+            .collect();
+
+        due.sort_by_key(|kenneling| kenneling.released_at);
+
+        Ok(due)
+    }
+
+    async fn history(&self, guild_id: GuildId, victim_id: UserId) -> Result<Vec<Kenneling>> {
+        let mut history: Vec<_> = self
+            .state
+            .lock()
+            .await
+            .kennelings
+            .iter()
+            .filter(|kenneling| kenneling.guild_id == guild_id && kenneling.victim_id == victim_id)
+            .cloned()
+            .collect();
+
+        history.sort_by_key(|kenneling| std::cmp::Reverse(kenneling.kenneled_at));
+
+        Ok(history)
+    }
+
+    async fn end(&self, id: i32, end: End, lift: Effect<'_>) -> Result<Option<Kenneling>> {
+        // Like in Postgres, the end is saved before the kennel comes off, and undone if that fails.
+        let (running, ended) = {
+            let mut state = self.state.lock().await;
+
+            let Some(kenneling) = state
+                .kenneling(id)
+                .filter(|kenneling| kenneling.status.is_open())
+            else {
+                return Ok(None);
+            };
+
+            let running = kenneling.clone();
+
+            kenneling.status = end.status;
+            kenneling.ended_at = Some(Utc::now());
+            kenneling.ended_by = end.ended_by;
+            kenneling.end_reason = end.reason;
+
+            (running, kenneling.clone())
+        };
+
+        if let Err(e) = lift(ended.clone()).await {
+            if let Some(kenneling) = self
+                .state
+                .lock()
+                .await
+                .kenneling(id)
+                .filter(|kenneling| kenneling.status == ended.status)
+            {
+                *kenneling = running;
+            }
+
+            return Err(e);
+        }
+
+        Ok(Some(ended))
+    }
+
+    async fn set_length(&self, id: i32, kennel_length: Duration) -> Result<Option<Kenneling>> {
+        let mut state = self.state.lock().await;
+
+        let Some(kenneling) = state
+            .kenneling(id)
+            .filter(|kenneling| kenneling.status.is_open())
+        else {
+            return Ok(None);
+        };
+
+        kenneling.kennel_length = kennel_length;
+        update_released_at(kenneling);
+
+        Ok(Some(kenneling.clone()))
+    }
+
+    async fn set_announcement(&self, id: i32, announcement: (ChannelId, MessageId)) -> Result<()> {
+        if let Some(kenneling) = self.state.lock().await.kenneling(id) {
+            kenneling.announcement = Some(announcement);
+        }
+
+        Ok(())
+    }
+
+    async fn pause(&self, id: i32) -> Result<bool> {
+        let mut state = self.state.lock().await;
+
+        let Some(kenneling) = state
+            .kenneling(id)
+            .filter(|kenneling| kenneling.status == KennelingStatus::Active)
+        else {
+            return Ok(false);
+        };
+
+        kenneling.paused_at = Some(Utc::now());
+        kenneling.status = KennelingStatus::Paused;
+
+        Ok(true)
+    }
+
+    async fn resume(&self, id: i32, apply: Effect<'_>) -> Result<Option<Kenneling>> {
+        let mut state = self.state.lock().await;
+
+        let Some(mut resumed) = state
+            .kenneling(id)
+            .filter(|kenneling| kenneling.status == KennelingStatus::Paused)
+            .cloned()
+        else {
+            return Ok(None);
+        };
+
+        if let Some(paused_at) = resumed.paused_at.take() {
+            resumed.paused_length += since(paused_at, Utc::now());
+        }
+        resumed.status = KennelingStatus::Active;
+        update_released_at(&mut resumed);
+
+        apply(resumed.clone()).await?;
+
+        if let Some(kenneling) = state.kenneling(id) {
+            *kenneling = resumed.clone();
+        }
+
+        Ok(Some(resumed))
+    }
+
+    async fn insert_schedule(&self, schedule: &KennelSchedule) -> Result<i32> {
+        let mut state = self.state.lock().await;
+
+        state.next_schedule_id += 1;
+        let id = state.next_schedule_id;
+
+        state.schedules.push(KennelSchedule {
+            id: Some(id),
+            ..schedule.clone()
+        });
+
+        Ok(id)
+    }
+
+    async fn schedules(&self, guild_id: GuildId) -> Result<Vec<KennelSchedule>> {
+        let mut schedules: Vec<_> = self
+            .state
+            .lock()
+            .await
+            .schedules
+            .iter()
+            .filter(|schedule| schedule.guild_id == guild_id)
+            .cloned()
+            .collect();
+
+        schedules.sort_by_key(|schedule| schedule.next_run);
+
+        Ok(schedules)
+    }
+
+    async fn due_schedules(&self) -> Result<Vec<KennelSchedule>> {
+        let now = Utc::now();
+
+        let mut due: Vec<_> = self
+            .state
+            .lock()
+            .await
+            .schedules
+            .iter()
+            .filter(|schedule| !schedule.paused && schedule.next_run <= now)
+            .cloned()
+            .collect();
+
+        due.sort_by_key(|schedule| schedule.next_run);
+
+        Ok(due)
+    }
+
+    async fn advance_schedule(
+        &self,
+        id: i32,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        kenneling: Option<&Kenneling>,
+    ) -> Result<bool> {
+        let mut state = self.state.lock().await;
+
+        let Some(schedule) = state.schedules.iter_mut().find(|schedule| {
+            schedule.id == Some(id) && schedule.next_run == from && !schedule.paused
+        }) else {
+            return Ok(false);
+        };
+
+        schedule.next_run = to;
+
+        if let Some(kenneling) = kenneling {
+            state.insert_pending_kenneling(kenneling);
+        }
+
+        Ok(true)
+    }
+
+    async fn set_schedule_paused(
+        &self,
+        guild_id: GuildId,
+        id: i32,
+        paused: bool,
+        next_run: DateTime<Utc>,
+    ) -> Result<bool> {
+        let mut state = self.state.lock().await;
+
+        let Some(schedule) = state.schedule(guild_id, id) else {
+            return Ok(false);
+        };
+
+        schedule.paused = paused;
+        schedule.next_run = next_run;
+
+        Ok(true)
+    }
+
+    async fn delete_schedule(&self, guild_id: GuildId, id: i32) -> Result<bool> {
+        let mut state = self.state.lock().await;
+        let count = state.schedules.len();
+
+        state
+            .schedules
+            .retain(|schedule| schedule.guild_id != guild_id || schedule.id != Some(id));
+
+        Ok(state.schedules.len() != count)
+    }
+
+    async fn guild_stats(&self, guild_id: GuildId) -> Result<GuildStats> {
+        let now = Utc::now();
+        let state = self.state.lock().await;
+
+        let kennelings: Vec<_> = state
+            .kennelings
+            .iter()
+            .filter(|kenneling| kenneling.guild_id == guild_id && kenneling.status.has_started())
+            .collect();
+
+        let mut victims: Vec<_> = kennelings
+            .iter()
+            .map(|kenneling| kenneling.victim_id)
+            .collect();
+        victims.sort();
+        victims.dedup();
+
+        let total_length = (!kennelings.is_empty()).then(|| {
+            kennelings
+                .iter()
+                .map(|kenneling| kenneling.served_length(now))
+                .sum::<Duration>()
+        });
+        let average_length = (!kennelings.is_empty()).then(|| {
+            kennelings
+                .iter()
+                .map(|kenneling| kenneling.kennel_length)
+                .sum::<Duration>()
+                / kennelings.len() as u32
+        });
+
+        let since_days = |days: i64| {
+            kennelings
+                .iter()
+                .filter(|kenneling| kenneling.kenneled_at > now - chrono::Duration::days(days))
+                .count() as i64
+        };
+
+        Ok(GuildStats {
+            kennelings: kennelings.len() as i64,
+            victims: victims.len() as i64,
+            total_length,
+            average_length,
+            last_day: since_days(1),
+            last_week: since_days(7),
+            last_month: since_days(30),
+        })
+    }
+
+    async fn leaderboard(
+        &self,
+        guild_id: GuildId,
+        leaderboard: Leaderboard,
+        limit: i64,
+    ) -> Result<Vec<LeaderboardEntry>> {
+        let now = Utc::now();
+        let state = self.state.lock().await;
+
+        let mut entries: Vec<LeaderboardEntry> = Vec::new();
+
+        for kenneling in state
+            .kennelings
+            .iter()
+            .filter(|kenneling| kenneling.guild_id == guild_id && kenneling.status.has_started())
+        {
+            let user_id = match leaderboard {
+                Leaderboard::Victims => kenneling.victim_id,
+                // Nobody in particular is behind a community vote.
+                Leaderboard::Kennelers if kenneling.community_vote => continue,
+                Leaderboard::Kennelers => kenneling.author_id,
+            };
+
+            match entries.iter_mut().find(|entry| entry.user_id == user_id) {
+                Some(entry) => {
+                    entry.kennelings += 1;
+                    entry.total_length += kenneling.served_length(now);
+                }
+                None => entries.push(LeaderboardEntry {
+                    user_id,
+                    kennelings: 1,
+                    total_length: kenneling.served_length(now),
+                }),
+            }
+        }
+
+        entries.sort_by_key(|entry| Reverse((entry.kennelings, entry.total_length)));
+        entries.truncate(limit.try_into().unwrap_or_default());
+
+        Ok(entries)
+    }
+
+    async fn public_stats(&self, excluded_guilds: &[GuildId]) -> Result<PublicStats> {
+        let now = Utc::now();
+        let state = self.state.lock().await;
+
+        let counted: Vec<_> = state
+            .kennelings
+            .iter()
+            .filter(|kenneling| {
+                kenneling.status.has_started()
+                    && state.counts_publicly(kenneling.guild_id, excluded_guilds)
+            })
+            .collect();
+
+        Ok(PublicStats {
+            total_length: (!counted.is_empty()).then(|| {
+                counted
+                    .iter()
+                    .map(|kenneling| kenneling.served_length(now))
+                    .sum()
+            }),
+            active: counted
+                .iter()
+                .filter(|kenneling| kenneling.status == KennelingStatus::Active)
+                .count() as i64,
+            guilds: state
+                .servers
+                .values()
+                .filter(|server| {
+                    server.count_publicly && !excluded_guilds.contains(&server.guild_id)
+                })
+                .count() as i64,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::traits::no_effect;
+    use anyhow::anyhow;
+
+    const GUILD: GuildId = GuildId::new(1);
+    const VICTIM: UserId = UserId::new(2);
+    const AUTHOR: UserId = UserId::new(3);
+
+    fn released() -> End {
+        End {
+            status: KennelingStatus::ReleasedOnTime,
+            ended_by: None,
+            reason: None,
+        }
+    }
+
+    /// Starts a kenneling right away, the way the commands do.
+    async fn insert(storage: &MemoryStorage, kennel_length: Duration) -> i32 {
+        let id = insert_pending(storage, kennel_length).await;
+        storage
+            .start(id, &[], None, no_effect())
+            .await
+            .unwrap()
+            .unwrap();
+
+        id
+    }
+
+    #[tokio::test]
+    async fn releases_only_once() {
+        let storage = MemoryStorage::new();
+        let id = insert(&storage, Duration::from_secs(60)).await;
+
+        assert!(
+            storage
+                .end(id, released(), no_effect())
+                .await
+                .unwrap()
+                .is_some()
+        );
+        assert!(
+            storage
+                .end(id, released(), no_effect())
+                .await
+                .unwrap()
+                .is_none()
+        );
+        assert!(
+            storage
+                .active_kenneling(GUILD, VICTIM)
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn failed_lift_rolls_back_the_release() {
+        let storage = MemoryStorage::new();
+        let id = insert(&storage, Duration::from_secs(60)).await;
+
+        let failing: Effect = Box::new(|_| Box::pin(async { Err(anyhow!("Discord is down")) }));
+
+        assert!(storage.end(id, released(), failing).await.is_err());
+        assert!(
+            storage
+                .active_kenneling(GUILD, VICTIM)
+                .await
+                .unwrap()
+                .is_some()
+        );
+    }
+
+    #[tokio::test]
+    async fn kennelings_stop_running_before_they_are_lifted() {
+        let storage = MemoryStorage::new();
+        let id = insert(&storage, Duration::from_secs(60)).await;
+
+        let storage_ref = &storage;
+        let checking: Effect = Box::new(move |_| {
+            Box::pin(async move {
+                assert!(storage_ref.active_kenneling(GUILD, VICTIM).await?.is_none());
+                Ok(())
+            })
+        });
+
+        assert!(
+            storage
+                .end(id, released(), checking)
+                .await
+                .unwrap()
+                .is_some()
+        );
+    }
+
+    #[tokio::test]
+    async fn early_release_keeps_the_sentence_and_records_time_served() {
+        let storage = MemoryStorage::new();
+        let id = insert(&storage, Duration::from_secs(60 * 60)).await;
+
+        let released = storage
+            .end(
+                id,
+                End {
+                    status: KennelingStatus::ReleasedEarly,
+                    ended_by: Some(AUTHOR),
+                    reason: Some("Behaved".to_string()),
+                },
+                no_effect(),
+            )
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(released.status, KennelingStatus::ReleasedEarly);
+        assert_eq!(released.kennel_length, Duration::from_secs(60 * 60));
+        assert_eq!(released.original_length, Duration::from_secs(60 * 60));
+        assert!(
+            released.time_served(Utc::now() + chrono::Duration::hours(1)) < Duration::from_secs(60)
+        );
+        assert_eq!(released.ended_by, Some(AUTHOR));
+        assert_eq!(released.end_reason.as_deref(), Some("Behaved"));
+    }
+
+    async fn insert_pending(storage: &MemoryStorage, kennel_length: Duration) -> i32 {
+        storage
+            .insert_pending_kenneling(&Kenneling::new(GUILD, VICTIM, AUTHOR, kennel_length))
+            .await
+            .unwrap()
+    }
+
+    fn replaced(id: i32) -> Option<(i32, End)> {
+        Some((
+            id,
+            End {
+                status: KennelingStatus::Revoked,
+                ended_by: Some(AUTHOR),
+                reason: None,
+            },
+        ))
+    }
+
+    #[tokio::test]
+    async fn victims_serve_one_kenneling_at_a_time() {
+        let storage = MemoryStorage::new();
+        insert(&storage, Duration::from_secs(60)).await;
+        let pending = insert_pending(&storage, Duration::from_secs(60)).await;
+
+        assert!(
+            storage
+                .start(pending, &[], None, no_effect())
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn replacing_happens_along_with_the_start() {
+        let storage = MemoryStorage::new();
+        let old = insert(&storage, Duration::from_secs(60)).await;
+        let new = insert_pending(&storage, Duration::from_secs(60)).await;
+
+        let failing: Effect = Box::new(|_| Box::pin(async { Err(anyhow!("Discord is down")) }));
+
+        assert!(
+            storage
+                .start(new, &[], replaced(old), failing)
+                .await
+                .is_err()
+        );
+        assert_eq!(
+            storage
+                .active_kenneling(GUILD, VICTIM)
+                .await
+                .unwrap()
+                .and_then(|kenneling| kenneling.id),
+            Some(old)
+        );
+
+        storage
+            .start(new, &[], replaced(old), no_effect())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            storage
+                .active_kenneling(GUILD, VICTIM)
+                .await
+                .unwrap()
+                .and_then(|kenneling| kenneling.id),
+            Some(new)
+        );
+    }
+
+    #[tokio::test]
+    async fn paused_kennelings_are_neither_running_nor_due() {
+        let storage = MemoryStorage::new();
+        let id = insert(&storage, Duration::from_secs(60)).await;
+
+        assert!(storage.pause(id).await.unwrap());
+        assert!(!storage.pause(id).await.unwrap());
+        assert!(storage.running_kennelings(None).await.unwrap().is_empty());
+        assert!(storage.due_kennelings().await.unwrap().is_empty());
+        assert_eq!(storage.paused_kennelings().await.unwrap().len(), 1);
+
+        assert!(storage.resume(id, no_effect()).await.unwrap().is_some());
+        assert_eq!(
+            storage.running_kennelings(Some(GUILD)).await.unwrap().len(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn opted_out_guilds_dont_count_publicly() {
+        let storage = MemoryStorage::new();
+        insert(&storage, Duration::from_secs(60)).await;
+
+        let stats = storage.public_stats(&[]).await.unwrap();
+        assert_eq!(stats.total_length, Some(Duration::from_secs(60)));
+        assert_eq!(stats.active, 1);
+
+        let mut server = Server::new(GUILD);
+        server.count_publicly = false;
+        storage.upsert_server(&server).await.unwrap();
+
+        let stats = storage.public_stats(&[]).await.unwrap();
+        assert_eq!(stats.total_length, None);
+        assert_eq!(stats.guilds, 0);
+    }
+}
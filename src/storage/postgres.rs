@@ -0,0 +1,1127 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serenity::all::{ChannelId, GuildId, MessageId, RoleId, UserId};
+use sqlx::PgPool;
+use std::time::Duration;
+
+use crate::storage::traits::{Effect, End, Storage};
+use crate::types::kenneling::{Kenneling, KennelingRow};
+use crate::types::schedule::{KennelSchedule, KennelScheduleRow};
+use crate::types::server::{Server, ServerRow};
+use crate::types::stats::{GuildStats, Leaderboard, LeaderboardEntry, PublicStats};
+use crate::util::pgint_dur::{PgIntervalToDuration as _, to_interval};
+use crate::util::snowflake::from_snowflake;
+
+/// [`Storage`] backed by the Postgres database.
+#[derive(Debug, Clone)]
+pub struct PgStorage {
+    pool: PgPool,
+}
+
+impl PgStorage {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+/// Turns Discord IDs into the `bigint`s they're stored as, for binding.
+fn snowflakes<Id: Copy + Into<i64>>(ids: &[Id]) -> Vec<i64> {
+    ids.iter().copied().map(Into::into).collect()
+}
+
+/// Turns the rows of a kenneling query into Kennelings.
+fn kennelings(rows: Vec<KennelingRow>) -> Result<Vec<Kenneling>> {
+    rows.iter().map(Kenneling::try_from).collect()
+}
+
+/// Saves a kenneling that starts later, either on its own or as part of a bigger change.
+async fn insert_pending_kenneling(
+    executor: impl sqlx::PgExecutor<'_>,
+    kenneling: &Kenneling,
+) -> Result<i32> {
+    let row = KennelingRow::try_from(kenneling)?;
+
+    let id = sqlx::query!(
+        r#"
+        INSERT INTO kennelings
+            (guild_id, victim_id, author_id, kennel_length, original_length, kenneled_at, status, announcement_channel, announcement_message, reason, community_vote)
+        VALUES
+            ($1, $2, $3, $4, $4, $5, 'pending', $6, $7, $8, $9)
+        RETURNING
+            id
+            ;
+        "#,
+        row.guild_id,
+        row.victim_id,
+        row.author_id,
+        row.kennel_length,
+        row.kenneled_at,
+        row.announcement_channel,
+        row.announcement_message,
+        row.reason,
+        row.community_vote,
+    )
+    .fetch_one(executor)
+    .await?
+    .id;
+
+    tracing::trace!("New pending Kenneling inserted! Id: {id}");
+
+    Ok(id)
+}
+
+/// Turns the rows of a schedule query into KennelSchedules.
+fn schedules(rows: Vec<KennelScheduleRow>) -> Result<Vec<KennelSchedule>> {
+    rows.iter().map(KennelSchedule::try_from).collect()
+}
+
+#[async_trait::async_trait]
+impl Storage for PgStorage {
+    async fn server(&self, guild_id: GuildId) -> Result<Option<Server>> {
+        let row = sqlx::query_as!(
+            ServerRow,
+            r#"
+            SELECT * FROM servers
+            WHERE
+                guild_id = $1
+                ;
+            "#,
+            i64::from(guild_id),
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        row.map(Server::try_from).transpose()
+    }
+
+    async fn servers(&self) -> Result<Vec<Server>> {
+        sqlx::query_as!(
+            ServerRow,
+            r#"
+            SELECT * FROM servers
+            ;
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(Server::try_from)
+        .collect()
+    }
+
+    async fn upsert_server(&self, server: &Server) -> Result<()> {
+        sqlx::query!(
+            r#"
+            INSERT INTO servers
+                (
+                    guild_id,
+                    command_name,
+                    announcement_message,
+                    release_message,
+                    role_id,
+                    kennel_channel,
+                    kennel_message,
+                    stacking_policy,
+                    punishment_mode,
+                    strip_roles,
+                    immune_roles,
+                    immune_users,
+                    min_kennel_length,
+                    max_kennel_length,
+                    count_publicly,
+                    manual_release,
+                    vote_threshold,
+                    vote_window
+                )
+            VALUES
+                ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18)
+            ON CONFLICT
+                (guild_id)
+            DO
+                UPDATE SET
+                    command_name = EXCLUDED.command_name,
+                    announcement_message = EXCLUDED.announcement_message,
+                    release_message = EXCLUDED.release_message,
+                    role_id = EXCLUDED.role_id,
+                    kennel_channel = EXCLUDED.kennel_channel,
+                    kennel_message = EXCLUDED.kennel_message,
+                    stacking_policy = EXCLUDED.stacking_policy,
+                    punishment_mode = EXCLUDED.punishment_mode,
+                    strip_roles = EXCLUDED.strip_roles,
+                    immune_roles = EXCLUDED.immune_roles,
+                    immune_users = EXCLUDED.immune_users,
+                    min_kennel_length = EXCLUDED.min_kennel_length,
+                    max_kennel_length = EXCLUDED.max_kennel_length,
+                    count_publicly = EXCLUDED.count_publicly,
+                    manual_release = EXCLUDED.manual_release,
+                    vote_threshold = EXCLUDED.vote_threshold,
+                    vote_window = EXCLUDED.vote_window
+                ;
+            "#,
+            i64::from(server.guild_id),
+            server.command_name,
+            server.announcement_message,
+            server.release_message,
+            server.role_id.map(i64::from),
+            server.kennel_channel.map(i64::from),
+            server.kennel_message,
+            server.stacking_policy.as_str(),
+            server.punishment_mode.as_str(),
+            server.strip_roles,
+            &server
+                .immune_roles
+                .iter()
+                .copied()
+                .map(i64::from)
+                .collect::<Vec<_>>(),
+            &server
+                .immune_users
+                .iter()
+                .copied()
+                .map(i64::from)
+                .collect::<Vec<_>>(),
+            server.min_kennel_length.map(to_interval).transpose()?,
+            server.max_kennel_length.map(to_interval).transpose()?,
+            server.count_publicly,
+            server.manual_release.as_str(),
+            server.vote_threshold.map(i32::try_from).transpose()?,
+            to_interval(server.vote_window)?,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn role_limits(&self, guild_id: GuildId) -> Result<Vec<(RoleId, Duration)>> {
+        sqlx::query!(
+            r#"
+            SELECT
+                role_id,
+                max_kennel_length
+            FROM
+                role_kennel_limits
+            WHERE
+                guild_id = $1
+            ORDER BY
+                max_kennel_length
+                ;
+            "#,
+            i64::from(guild_id),
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|limit| {
+            Ok((
+                from_snowflake(limit.role_id)?,
+                limit.max_kennel_length.to_duration()?,
+            ))
+        })
+        .collect()
+    }
+
+    async fn set_role_limit(
+        &self,
+        guild_id: GuildId,
+        role_id: RoleId,
+        max: Option<Duration>,
+    ) -> Result<()> {
+        match max {
+            Some(max) => {
+                sqlx::query!(
+                    r#"
+                    INSERT INTO role_kennel_limits (guild_id, role_id, max_kennel_length)
+                    VALUES ($1, $2, $3)
+                    ON CONFLICT (guild_id, role_id) DO UPDATE
+                    SET
+                        max_kennel_length = EXCLUDED.max_kennel_length
+                        ;
+                    "#,
+                    i64::from(guild_id),
+                    i64::from(role_id),
+                    to_interval(max)?,
+                )
+                .execute(&self.pool)
+                .await?;
+            }
+            None => {
+                sqlx::query!(
+                    r#"
+                    DELETE FROM role_kennel_limits
+                    WHERE
+                        guild_id = $1
+                        AND role_id = $2
+                        ;
+                    "#,
+                    i64::from(guild_id),
+                    i64::from(role_id),
+                )
+                .execute(&self.pool)
+                .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn insert_pending_kenneling(&self, kenneling: &Kenneling) -> Result<i32> {
+        insert_pending_kenneling(&self.pool, kenneling).await
+    }
+
+    async fn pending_kennelings(&self, guild_id: Option<GuildId>) -> Result<Vec<Kenneling>> {
+        let rows = sqlx::query_as!(
+            KennelingRow,
+            r#"
+            SELECT *
+            FROM kennelings
+            WHERE
+                status = 'pending'
+                AND ($1::bigint IS NULL OR guild_id = $1)
+            ORDER BY
+                kenneled_at
+                ;
+            "#,
+            guild_id.map(i64::from),
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        kennelings(rows)
+    }
+
+    async fn starting_kennelings(&self) -> Result<Vec<Kenneling>> {
+        let rows = sqlx::query_as!(
+            KennelingRow,
+            r#"
+            SELECT *
+            FROM kennelings
+            WHERE
+                status = 'pending'
+                AND kenneled_at <= CURRENT_TIMESTAMP
+            ORDER BY
+                kenneled_at
+                ;
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        kennelings(rows)
+    }
+
+    async fn start(
+        &self,
+        id: i32,
+        stripped_roles: &[RoleId],
+        replacing: Option<(i32, End)>,
+        apply: Effect<'_>,
+    ) -> Result<Option<Kenneling>> {
+        let mut transaction = self.pool.begin().await?;
+
+        // If it was ended in the meantime, there's nothing left to replace, and the new one starts all the same.
+        if let Some((replaced_id, end)) = replacing {
+            sqlx::query!(
+                r#"
+                UPDATE kennelings
+                SET
+                    status = $2,
+                    ended_at = CURRENT_TIMESTAMP,
+                    ended_by = $3,
+                    end_reason = $4
+                WHERE
+                    id = $1
+                    AND status IN ('active', 'paused')
+                    ;
+                "#,
+                replaced_id,
+                end.status.as_str(),
+                end.ended_by.map(i64::from),
+                end.reason,
+            )
+            .execute(&mut *transaction)
+            .await?;
+        }
+
+        // Another kenneling that's still running makes this fail, thanks to `kennelings_one_running_per_victim`.
+
+        // However late the scheduler gets to it, the victim serves the whole sentence from now on.
+        let Some(row) = sqlx::query_as!(
+            KennelingRow,
+            r#"
+            UPDATE kennelings
+            SET
+                status = 'active',
+                kenneled_at = CURRENT_TIMESTAMP,
+                stripped_roles = $2
+            WHERE
+                id = $1
+                AND status = 'pending'
+            RETURNING
+                *
+                ;
+            "#,
+            id,
+            &snowflakes(stripped_roles),
+        )
+        .fetch_optional(&mut *transaction)
+        .await?
+        else {
+            return Ok(None);
+        };
+
+        let kenneling = Kenneling::try_from(&row)?;
+
+        apply(kenneling.clone()).await?;
+
+        transaction.commit().await?;
+
+        Ok(Some(kenneling))
+    }
+
+    async fn cancel(
+        &self,
+        id: i32,
+        cancelled_by: Option<UserId>,
+        reason: Option<String>,
+    ) -> Result<Option<Kenneling>> {
+        let row = sqlx::query_as!(
+            KennelingRow,
+            r#"
+            UPDATE kennelings
+            SET
+                status = 'cancelled',
+                ended_at = CURRENT_TIMESTAMP,
+                ended_by = $2,
+                end_reason = $3
+            WHERE
+                id = $1
+                AND status = 'pending'
+            RETURNING
+                *
+                ;
+            "#,
+            id,
+            cancelled_by.map(i64::from),
+            reason,
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.as_ref().map(Kenneling::try_from).transpose()?)
+    }
+
+    async fn active_kenneling(
+        &self,
+        guild_id: GuildId,
+        victim_id: UserId,
+    ) -> Result<Option<Kenneling>> {
+        let row = sqlx::query_as!(
+            KennelingRow,
+            r#"
+            SELECT *
+            FROM kennelings
+            WHERE
+                guild_id = $1
+                AND victim_id = $2
+                AND status IN ('active', 'paused')
+            ORDER BY
+                released_at DESC
+            LIMIT 1
+                ;
+            "#,
+            i64::from(guild_id),
+            i64::from(victim_id),
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.as_ref().map(Kenneling::try_from).transpose()?)
+    }
+
+    async fn running_kennelings(&self, guild_id: Option<GuildId>) -> Result<Vec<Kenneling>> {
+        let rows = sqlx::query_as!(
+            KennelingRow,
+            r#"
+            SELECT *
+            FROM kennelings
+            WHERE
+                released_at > CURRENT_TIMESTAMP
+                AND status = 'active'
+                AND ($1::bigint IS NULL OR guild_id = $1)
+                ;
+            "#,
+            guild_id.map(i64::from),
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        kennelings(rows)
+    }
+
+    async fn running_kennelings_by_server(&self) -> Result<Vec<(Server, Vec<Kenneling>)>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT
+                k.guild_id,
+                k.kennel_length,
+                k.kenneled_at,
+                k.author_id,
+                k.released_at,
+                k.victim_id,
+                k.id,
+                k.status,
+                k.original_length,
+                k.announcement_channel,
+                k.announcement_message,
+                k.ended_at,
+                k.ended_by,
+                k.end_reason,
+                k.paused_at,
+                k.paused_length,
+                k.reason,
+                k.stripped_roles,
+                k.community_vote,
+                s.command_name,
+                s.announcement_message AS server_announcement_message,
+                s.release_message,
+                s.role_id,
+                s.kennel_channel,
+                s.kennel_message,
+                s.stacking_policy,
+                s.punishment_mode,
+                s.strip_roles,
+                s.immune_roles,
+                s.immune_users,
+                s.min_kennel_length,
+                s.max_kennel_length,
+                s.count_publicly,
+                s.manual_release,
+                s.vote_threshold,
+                s.vote_window
+            FROM
+                kennelings k
+                JOIN servers s ON s.guild_id = k.guild_id
+            WHERE
+                k.released_at > CURRENT_TIMESTAMP
+                AND k.status = 'active'
+            ORDER BY
+                k.guild_id
+                ;
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut by_server: Vec<(Server, Vec<Kenneling>)> = Vec::new();
+
+        for row in rows {
+            let kenneling = Kenneling::try_from(&KennelingRow {
+                guild_id: row.guild_id,
+                kennel_length: row.kennel_length,
+                kenneled_at: row.kenneled_at,
+                author_id: row.author_id,
+                released_at: row.released_at,
+                victim_id: row.victim_id,
+                id: Some(row.id),
+                status: row.status,
+                original_length: row.original_length,
+                announcement_channel: row.announcement_channel,
+                announcement_message: row.announcement_message,
+                ended_at: row.ended_at,
+                ended_by: row.ended_by,
+                end_reason: row.end_reason,
+                paused_at: row.paused_at,
+                paused_length: row.paused_length,
+                reason: row.reason,
+                stripped_roles: row.stripped_roles,
+                community_vote: row.community_vote,
+            })?;
+
+            // The rows are ordered by guild, so each server only has to be parsed once.
+            match by_server.last_mut() {
+                Some((server, kennelings)) if server.guild_id == kenneling.guild_id => {
+                    kennelings.push(kenneling);
+                }
+                _ => {
+                    let server = Server::try_from(ServerRow {
+                        guild_id: row.guild_id,
+                        command_name: row.command_name,
+                        announcement_message: row.server_announcement_message,
+                        release_message: row.release_message,
+                        role_id: row.role_id,
+                        kennel_channel: row.kennel_channel,
+                        kennel_message: row.kennel_message,
+                        stacking_policy: row.stacking_policy,
+                        punishment_mode: row.punishment_mode,
+                        strip_roles: row.strip_roles,
+                        immune_roles: row.immune_roles,
+                        immune_users: row.immune_users,
+                        min_kennel_length: row.min_kennel_length,
+                        max_kennel_length: row.max_kennel_length,
+                        count_publicly: row.count_publicly,
+                        manual_release: row.manual_release,
+                        vote_threshold: row.vote_threshold,
+                        vote_window: row.vote_window,
+                    })?;
+
+                    by_server.push((server, vec![kenneling]));
+                }
+            }
+        }
+
+        Ok(by_server)
+    }
+
+    async fn paused_kennelings(&self) -> Result<Vec<Kenneling>> {
+        let rows = sqlx::query_as!(
+            KennelingRow,
+            r#"
+            SELECT *
+            FROM kennelings
+            WHERE
+                status = 'paused'
+                ;
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        kennelings(rows)
+    }
+
+    async fn due_kennelings(&self) -> Result<Vec<Kenneling>> {
+        let rows = sqlx::query_as!(
+            KennelingRow,
+            r#"
+            SELECT *
+            FROM kennelings
+            WHERE
+                released_at <= CURRENT_TIMESTAMP
+                AND status = 'active'
+            ORDER BY
+                released_at
+                ;
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        kennelings(rows)
+    }
+
+    async fn history(&self, guild_id: GuildId, victim_id: UserId) -> Result<Vec<Kenneling>> {
+        let rows = sqlx::query_as!(
+            KennelingRow,
+            r#"
+            SELECT *
+            FROM kennelings
+            WHERE
+                guild_id = $1
+                AND victim_id = $2
+            ORDER BY
+                kenneled_at DESC
+                ;
+            "#,
+            i64::from(guild_id),
+            i64::from(victim_id),
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        kennelings(rows)
+    }
+
+    async fn end(&self, id: i32, end: End, lift: Effect<'_>) -> Result<Option<Kenneling>> {
+        // The end is claimed and saved before the kennel comes off, so that the release happens exactly once no matter
+        // how many tasks race for it, and nothing sees the kenneling as running while the bot takes its punishment
+        // off. Holding a transaction open across Discord calls instead would keep it looking active until then.
+        let Some(row) = sqlx::query_as!(
+            KennelingRow,
+            r#"
+            UPDATE kennelings
+            SET
+                status = $2,
+                ended_at = CURRENT_TIMESTAMP,
+                ended_by = $3,
+                end_reason = $4
+            WHERE
+                id = $1
+                AND status IN ('active', 'paused')
+            RETURNING
+                *
+                ;
+            "#,
+            id,
+            end.status.as_str(),
+            end.ended_by.map(i64::from),
+            end.reason,
+        )
+        .fetch_optional(&self.pool)
+        .await?
+        else {
+            return Ok(None);
+        };
+
+        let kenneling = Kenneling::try_from(&row)?;
+
+        if let Err(e) = lift(kenneling.clone()).await {
+            // The kennel is still on, so the kenneling goes back to running, to be released again later.
+            sqlx::query!(
+                r#"
+                UPDATE kennelings
+                SET
+                    status = CASE WHEN paused_at IS NULL THEN 'active' ELSE 'paused' END,
+                    ended_at = NULL,
+                    ended_by = NULL,
+                    end_reason = NULL
+                WHERE
+                    id = $1
+                    AND status = $2
+                    ;
+                "#,
+                id,
+                end.status.as_str(),
+            )
+            .execute(&self.pool)
+            .await?;
+
+            return Err(e);
+        }
+
+        Ok(Some(kenneling))
+    }
+
+    async fn set_length(&self, id: i32, kennel_length: Duration) -> Result<Option<Kenneling>> {
+        let row = sqlx::query_as!(
+            KennelingRow,
+            r#"
+            UPDATE kennelings
+            SET
+                kennel_length = $2
+            WHERE
+                id = $1
+                AND status IN ('active', 'paused')
+            RETURNING
+                *
+                ;
+            "#,
+            id,
+            to_interval(kennel_length)?,
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.as_ref().map(Kenneling::try_from).transpose()?)
+    }
+
+    async fn set_announcement(&self, id: i32, announcement: (ChannelId, MessageId)) -> Result<()> {
+        sqlx::query!(
+            r#"
+            UPDATE kennelings
+            SET
+                announcement_channel = $2,
+                announcement_message = $3
+            WHERE
+                id = $1
+                ;
+            "#,
+            id,
+            i64::from(announcement.0),
+            i64::from(announcement.1),
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn pause(&self, id: i32) -> Result<bool> {
+        let rows_affected = sqlx::query!(
+            r#"
+            UPDATE kennelings
+            SET
+                paused_at = CURRENT_TIMESTAMP,
+                status = 'paused'
+            WHERE
+                id = $1
+                AND status = 'active'
+                ;
+            "#,
+            id,
+        )
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+
+        Ok(rows_affected != 0)
+    }
+
+    async fn resume(&self, id: i32, apply: Effect<'_>) -> Result<Option<Kenneling>> {
+        let mut transaction = self.pool.begin().await?;
+
+        let Some(row) = sqlx::query_as!(
+            KennelingRow,
+            r#"
+            UPDATE kennelings
+            SET
+                paused_length = paused_length + (CURRENT_TIMESTAMP - paused_at),
+                paused_at = NULL,
+                status = 'active'
+            WHERE
+                id = $1
+                AND status = 'paused'
+            RETURNING
+                *
+                ;
+            "#,
+            id,
+        )
+        .fetch_optional(&mut *transaction)
+        .await?
+        else {
+            return Ok(None);
+        };
+
+        let kenneling = Kenneling::try_from(&row)?;
+
+        apply(kenneling.clone()).await?;
+
+        transaction.commit().await?;
+
+        Ok(Some(kenneling))
+    }
+
+    async fn insert_schedule(&self, schedule: &KennelSchedule) -> Result<i32> {
+        let row = KennelScheduleRow::try_from(schedule)?;
+
+        let id = sqlx::query!(
+            r#"
+            INSERT INTO kennel_schedules
+                (guild_id, victim_id, author_id, weekdays, start_time, timezone, kennel_length, reason, paused, next_run)
+            VALUES
+                ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+            RETURNING
+                id
+                ;
+            "#,
+            row.guild_id,
+            row.victim_id,
+            row.author_id,
+            row.weekdays,
+            row.start_time,
+            row.timezone,
+            row.kennel_length,
+            row.reason,
+            row.paused,
+            row.next_run,
+        )
+        .fetch_one(&self.pool)
+        .await?
+        .id;
+
+        tracing::trace!("New KennelSchedule inserted! Id: {id}");
+
+        Ok(id)
+    }
+
+    async fn schedules(&self, guild_id: GuildId) -> Result<Vec<KennelSchedule>> {
+        let rows = sqlx::query_as!(
+            KennelScheduleRow,
+            r#"
+            SELECT *
+            FROM kennel_schedules
+            WHERE
+                guild_id = $1
+            ORDER BY
+                next_run
+                ;
+            "#,
+            i64::from(guild_id),
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        schedules(rows)
+    }
+
+    async fn due_schedules(&self) -> Result<Vec<KennelSchedule>> {
+        let rows = sqlx::query_as!(
+            KennelScheduleRow,
+            r#"
+            SELECT *
+            FROM kennel_schedules
+            WHERE
+                NOT paused
+                AND next_run <= CURRENT_TIMESTAMP
+            ORDER BY
+                next_run
+                ;
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        schedules(rows)
+    }
+
+    async fn advance_schedule(
+        &self,
+        id: i32,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        kenneling: Option<&Kenneling>,
+    ) -> Result<bool> {
+        let mut transaction = self.pool.begin().await?;
+
+        let rows_affected = sqlx::query!(
+            r#"
+            UPDATE kennel_schedules
+            SET
+                next_run = $3
+            WHERE
+                id = $1
+                AND next_run = $2
+                AND NOT paused
+                ;
+            "#,
+            id,
+            from.naive_utc(),
+            to.naive_utc(),
+        )
+        .execute(&mut *transaction)
+        .await?
+        .rows_affected();
+
+        if rows_affected == 0 {
+            return Ok(false);
+        }
+
+        if let Some(kenneling) = kenneling {
+            insert_pending_kenneling(&mut *transaction, kenneling).await?;
+        }
+
+        transaction.commit().await?;
+
+        Ok(true)
+    }
+
+    async fn set_schedule_paused(
+        &self,
+        guild_id: GuildId,
+        id: i32,
+        paused: bool,
+        next_run: DateTime<Utc>,
+    ) -> Result<bool> {
+        let rows_affected = sqlx::query!(
+            r#"
+            UPDATE kennel_schedules
+            SET
+                paused = $3,
+                next_run = $4
+            WHERE
+                guild_id = $1
+                AND id = $2
+                ;
+            "#,
+            i64::from(guild_id),
+            id,
+            paused,
+            next_run.naive_utc(),
+        )
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+
+        Ok(rows_affected != 0)
+    }
+
+    async fn delete_schedule(&self, guild_id: GuildId, id: i32) -> Result<bool> {
+        let rows_affected = sqlx::query!(
+            r#"
+            DELETE FROM kennel_schedules
+            WHERE
+                guild_id = $1
+                AND id = $2
+                ;
+            "#,
+            i64::from(guild_id),
+            id,
+        )
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+
+        Ok(rows_affected != 0)
+    }
+
+    async fn guild_stats(&self, guild_id: GuildId) -> Result<GuildStats> {
+        let stats = sqlx::query!(
+            r#"
+            SELECT
+                COUNT(*) AS "kennelings!",
+                COUNT(DISTINCT victim_id) AS "victims!",
+                SUM(served_length(kennelings)) AS total_length,
+                AVG(kennel_length) AS average_length,
+                COUNT(*) FILTER (WHERE kenneled_at > CURRENT_TIMESTAMP - INTERVAL '1 day') AS "last_day!",
+                COUNT(*) FILTER (WHERE kenneled_at > CURRENT_TIMESTAMP - INTERVAL '1 week') AS "last_week!",
+                COUNT(*) FILTER (WHERE kenneled_at > CURRENT_TIMESTAMP - INTERVAL '1 month') AS "last_month!"
+            FROM
+                kennelings
+            WHERE
+                guild_id = $1
+                AND status NOT IN ('pending', 'cancelled')
+                ;
+            "#,
+            i64::from(guild_id),
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(GuildStats {
+            kennelings: stats.kennelings,
+            victims: stats.victims,
+            total_length: stats
+                .total_length
+                .map(|total| total.to_duration())
+                .transpose()?,
+            average_length: stats
+                .average_length
+                .map(|average| average.to_duration())
+                .transpose()?,
+            last_day: stats.last_day,
+            last_week: stats.last_week,
+            last_month: stats.last_month,
+        })
+    }
+
+    async fn leaderboard(
+        &self,
+        guild_id: GuildId,
+        leaderboard: Leaderboard,
+        limit: i64,
+    ) -> Result<Vec<LeaderboardEntry>> {
+        let leaders = match leaderboard {
+            Leaderboard::Victims => sqlx::query!(
+                r#"
+                    SELECT
+                        victim_id AS user_id,
+                        COUNT(*) AS "kennelings!",
+                        SUM(served_length(kennelings)) AS "total_length!"
+                    FROM
+                        kennelings
+                    WHERE
+                        guild_id = $1
+                        AND status NOT IN ('pending', 'cancelled')
+                    GROUP BY
+                        victim_id
+                    ORDER BY
+                        2 DESC,
+                        3 DESC
+                    LIMIT $2
+                        ;
+                    "#,
+                i64::from(guild_id),
+                limit,
+            )
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .map(|leader| (leader.user_id, leader.kennelings, leader.total_length))
+            .collect::<Vec<_>>(),
+            Leaderboard::Kennelers => sqlx::query!(
+                r#"
+                    SELECT
+                        author_id AS user_id,
+                        COUNT(*) AS "kennelings!",
+                        SUM(served_length(kennelings)) AS "total_length!"
+                    FROM
+                        kennelings
+                    WHERE
+                        guild_id = $1
+                        AND status NOT IN ('pending', 'cancelled')
+                        AND NOT community_vote
+                    GROUP BY
+                        author_id
+                    ORDER BY
+                        2 DESC,
+                        3 DESC
+                    LIMIT $2
+                        ;
+                    "#,
+                i64::from(guild_id),
+                limit,
+            )
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .map(|leader| (leader.user_id, leader.kennelings, leader.total_length))
+            .collect::<Vec<_>>(),
+        };
+
+        leaders
+            .into_iter()
+            .map(|(user_id, kennelings, total_length)| {
+                Ok(LeaderboardEntry {
+                    user_id: from_snowflake(user_id)?,
+                    kennelings,
+                    total_length: total_length.to_duration()?,
+                })
+            })
+            .collect()
+    }
+
+    async fn public_stats(&self, excluded_guilds: &[GuildId]) -> Result<PublicStats> {
+        let stats = sqlx::query!(
+            r#"
+            SELECT
+                (
+                    SELECT SUM(served_length(kennelings))
+                    FROM kennelings
+                    WHERE
+                        status NOT IN ('pending', 'cancelled')
+                        AND NOT guild_id = ANY($1)
+                        AND NOT EXISTS (
+                            SELECT FROM servers
+                            WHERE
+                                servers.guild_id = kennelings.guild_id
+                                AND NOT servers.count_publicly
+                        )
+                ) AS total_length,
+                (
+                    SELECT COUNT(*)
+                    FROM kennelings
+                    WHERE
+                        status = 'active'
+                        AND NOT guild_id = ANY($1)
+                        AND NOT EXISTS (
+                            SELECT FROM servers
+                            WHERE
+                                servers.guild_id = kennelings.guild_id
+                                AND NOT servers.count_publicly
+                        )
+                ) AS "active!",
+                (
+                    SELECT COUNT(*)
+                    FROM servers
+                    WHERE
+                        count_publicly
+                        AND NOT guild_id = ANY($1)
+                ) AS "guilds!"
+                ;
+            "#,
+            &snowflakes(excluded_guilds),
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(PublicStats {
+            total_length: stats
+                .total_length
+                .map(|total| total.to_duration())
+                .transpose()?,
+            active: stats.active,
+            guilds: stats.guilds,
+        })
+    }
+}
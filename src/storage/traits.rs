@@ -0,0 +1,195 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serenity::all::{ChannelId, GuildId, MessageId, RoleId, UserId};
+use std::future::Future;
+use std::pin::Pin;
+use std::time::Duration;
+
+use crate::types::kenneling::Kenneling;
+use crate::types::schedule::KennelSchedule;
+use crate::types::server::Server;
+use crate::types::stats::{GuildStats, Leaderboard, LeaderboardEntry, PublicStats};
+use crate::types::status::KennelingStatus;
+
+/// Work that has to succeed for a change to the storage to stick, like taking the kennel role off of someone when
+/// they're released. It gets the updated Kenneling, and if it fails, the change is rolled back.
+pub type Effect<'a> =
+    Box<dyn FnOnce(Kenneling) -> Pin<Box<dyn Future<Output = Result<()>> + Send + 'a>> + Send + 'a>;
+
+/// An [`Effect`] that doesn't do anything, for changes that don't need to touch Discord.
+pub fn no_effect<'a>() -> Effect<'a> {
+    Box::new(|_| Box::pin(async { Ok(()) }))
+}
+
+/// How a kenneling ended, who ended it, and why.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct End {
+    /// One of the statuses a kenneling can end with, as opposed to [`KennelingStatus::Active`] or
+    /// [`KennelingStatus::Paused`].
+    pub status: KennelingStatus,
+    pub ended_by: Option<UserId>,
+    pub reason: Option<String>,
+}
+
+/// Everything the bot keeps track of, so that commands don't have to care where it's kept.
+///
+/// Kennelings are looked up by their id, and every change that could race with another task (releasing, pausing...)
+/// only goes through if the kenneling is still in the state it's expected to be in.
+#[async_trait::async_trait]
+pub trait Storage: Send + Sync {
+    /// Fetches a server's settings.
+    async fn server(&self, guild_id: GuildId) -> Result<Option<Server>>;
+
+    /// Fetches every server's settings.
+    async fn servers(&self) -> Result<Vec<Server>>;
+
+    /// Saves a server's settings, creating them if they don't exist yet.
+    async fn upsert_server(&self, server: &Server) -> Result<()>;
+
+    /// Fetches the longest members with each role can kennel people for.
+    async fn role_limits(&self, guild_id: GuildId) -> Result<Vec<(RoleId, Duration)>>;
+
+    /// Sets the longest members with a role can kennel people for, or takes the cap away with [`None`].
+    async fn set_role_limit(
+        &self,
+        guild_id: GuildId,
+        role_id: RoleId,
+        max: Option<Duration>,
+    ) -> Result<()>;
+
+    /// Saves a kenneling that starts later, at its `kenneled_at`, and returns its id.
+    async fn insert_pending_kenneling(&self, kenneling: &Kenneling) -> Result<i32>;
+
+    /// Fetches every kenneling that's scheduled to start later, optionally only in one guild, soonest first.
+    async fn pending_kennelings(&self, guild_id: Option<GuildId>) -> Result<Vec<Kenneling>>;
+
+    /// Fetches every pending kenneling whose start time has come, soonest first.
+    async fn starting_kennelings(&self) -> Result<Vec<Kenneling>>;
+
+    /// Starts a pending kenneling now, with `stripped_roles` taken off of the victim, and runs `apply` to put the
+    /// kennel on. If it's `replacing` a running kenneling, that one is ended as described in the same go, so that if
+    /// either doesn't go through, neither does. The replaced one isn't lifted, since the new one takes over from it.
+    ///
+    /// Returns the started Kenneling, or [`None`] if it had already started or been cancelled. Fails if the victim is
+    /// serving another kenneling that isn't being replaced, since nobody serves two at once.
+    async fn start(
+        &self,
+        id: i32,
+        stripped_roles: &[RoleId],
+        replacing: Option<(i32, End)>,
+        apply: Effect<'_>,
+    ) -> Result<Option<Kenneling>>;
+
+    /// Calls off a pending kenneling before it starts.
+    ///
+    /// Returns the cancelled Kenneling, or [`None`] if it had already started or been cancelled.
+    async fn cancel(
+        &self,
+        id: i32,
+        cancelled_by: Option<UserId>,
+        reason: Option<String>,
+    ) -> Result<Option<Kenneling>>;
+
+    /// Fetches the kenneling a user is serving in a guild, if there is one. Includes paused ones.
+    async fn active_kenneling(
+        &self,
+        guild_id: GuildId,
+        victim_id: UserId,
+    ) -> Result<Option<Kenneling>>;
+
+    /// Fetches every kenneling that's running right now, optionally only in one guild.
+    async fn running_kennelings(&self, guild_id: Option<GuildId>) -> Result<Vec<Kenneling>>;
+
+    /// Fetches every kenneling that's running right now, grouped by the server it's in, along with that server's
+    /// settings.
+    async fn running_kennelings_by_server(&self) -> Result<Vec<(Server, Vec<Kenneling>)>>;
+
+    /// Fetches every kenneling that's paused because the victim left.
+    async fn paused_kennelings(&self) -> Result<Vec<Kenneling>>;
+
+    /// Fetches every kenneling whose time is up, but that hasn't been released yet, soonest first.
+    async fn due_kennelings(&self) -> Result<Vec<Kenneling>>;
+
+    /// Fetches every kenneling a user has had in a guild, most recent first.
+    async fn history(&self, guild_id: GuildId, victim_id: UserId) -> Result<Vec<Kenneling>>;
+
+    /// Ends a kenneling that's active or paused, recording how it ended, and runs `lift` to take the kennel off. Its
+    /// length is left alone, so the sentence stays on record next to the time served.
+    ///
+    /// The end is saved before `lift` runs, so that nothing sees the kenneling as running while its punishment comes
+    /// off. If `lift` fails, the kenneling goes back to how it was.
+    ///
+    /// Returns the ended Kenneling, or [`None`] if it had already ended.
+    async fn end(&self, id: i32, end: End, lift: Effect<'_>) -> Result<Option<Kenneling>>;
+
+    /// Changes the length of a kenneling that hasn't been released yet.
+    ///
+    /// Returns the updated Kenneling, or [`None`] if it was released in the meantime.
+    async fn set_length(&self, id: i32, kennel_length: Duration) -> Result<Option<Kenneling>>;
+
+    /// Points a kenneling at a new announcement message.
+    async fn set_announcement(&self, id: i32, announcement: (ChannelId, MessageId)) -> Result<()>;
+
+    /// Stops the clock on a kenneling.
+    ///
+    /// Returns `false` if it was already paused or released.
+    async fn pause(&self, id: i32) -> Result<bool>;
+
+    /// Starts the clock on a paused kenneling again, adding the time spent away to its release time, and runs
+    /// `apply` to put the kennel back on.
+    ///
+    /// Returns the resumed Kenneling, or [`None`] if it wasn't paused.
+    async fn resume(&self, id: i32, apply: Effect<'_>) -> Result<Option<Kenneling>>;
+
+    /// Saves a new kennel schedule, and returns its id.
+    async fn insert_schedule(&self, schedule: &KennelSchedule) -> Result<i32>;
+
+    /// Fetches every kennel schedule in a guild, paused or not, soonest first.
+    async fn schedules(&self, guild_id: GuildId) -> Result<Vec<KennelSchedule>>;
+
+    /// Fetches every kennel schedule that isn't paused, and whose next occurrence has come.
+    async fn due_schedules(&self) -> Result<Vec<KennelSchedule>>;
+
+    /// Moves a schedule on from the occurrence at `from` to the one at `to`, and saves `kenneling` as a pending
+    /// kenneling for the occurrence, all at once.
+    ///
+    /// Returns whether the schedule moved on, which it doesn't if it's paused, gone, or already past `from`.
+    async fn advance_schedule(
+        &self,
+        id: i32,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        kenneling: Option<&Kenneling>,
+    ) -> Result<bool>;
+
+    /// Puts a schedule on hold, or takes it off hold with its next occurrence at `next_run`.
+    ///
+    /// Returns whether the schedule was found in the guild.
+    async fn set_schedule_paused(
+        &self,
+        guild_id: GuildId,
+        id: i32,
+        paused: bool,
+        next_run: DateTime<Utc>,
+    ) -> Result<bool>;
+
+    /// Deletes a schedule. Kennelings it already started keep going.
+    ///
+    /// Returns whether the schedule was found in the guild.
+    async fn delete_schedule(&self, guild_id: GuildId, id: i32) -> Result<bool>;
+
+    /// Adds up the kennelings in a guild.
+    async fn guild_stats(&self, guild_id: GuildId) -> Result<GuildStats>;
+
+    /// Ranks the users in a guild by how many kennelings they've been on one side of.
+    async fn leaderboard(
+        &self,
+        guild_id: GuildId,
+        leaderboard: Leaderboard,
+        limit: i64,
+    ) -> Result<Vec<LeaderboardEntry>>;
+
+    /// Adds up the kennelings in every guild that counts towards the public stats: the ones that haven't opted out,
+    /// other than `excluded_guilds`.
+    async fn public_stats(&self, excluded_guilds: &[GuildId]) -> Result<PublicStats>;
+}
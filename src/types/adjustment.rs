@@ -0,0 +1,100 @@
+use anyhow::anyhow;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// A change to the length of an active kenneling, as written by a moderator: `+30m`, `-10m`, or `=2h`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LengthAdjustment {
+    Extend(Duration),
+    Shorten(Duration),
+    Set(Duration),
+}
+
+impl LengthAdjustment {
+    /// Applies the adjustment to a kennel length. Returns [`None`] if the result would be negative.
+    pub fn apply(self, length: Duration) -> Option<Duration> {
+        match self {
+            LengthAdjustment::Extend(by) => length.checked_add(by),
+            LengthAdjustment::Shorten(by) => length.checked_sub(by),
+            LengthAdjustment::Set(to) => Some(to),
+        }
+    }
+}
+
+impl FromStr for LengthAdjustment {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+
+        let (constructor, duration): (fn(Duration) -> Self, &str) =
+            if let Some(duration) = s.strip_prefix('+') {
+                (LengthAdjustment::Extend, duration)
+            } else if let Some(duration) = s.strip_prefix('-') {
+                (LengthAdjustment::Shorten, duration)
+            } else if let Some(duration) = s.strip_prefix('=') {
+                (LengthAdjustment::Set, duration)
+            } else {
+                return Err(anyhow!("Adjustment must start with +, -, or ="));
+            };
+
+        Ok(constructor(humantime::parse_duration(duration.trim())?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn minutes(minutes: u64) -> Duration {
+        Duration::from_secs(minutes * 60)
+    }
+
+    #[test]
+    fn adjustments_are_parsed_by_their_sign() {
+        assert_eq!(
+            "+30m".parse::<LengthAdjustment>().unwrap(),
+            LengthAdjustment::Extend(minutes(30))
+        );
+        assert_eq!(
+            "-10m".parse::<LengthAdjustment>().unwrap(),
+            LengthAdjustment::Shorten(minutes(10))
+        );
+        assert_eq!(
+            " = 2h ".parse::<LengthAdjustment>().unwrap(),
+            LengthAdjustment::Set(minutes(120))
+        );
+    }
+
+    #[test]
+    fn nonsense_is_rejected() {
+        assert!("30m".parse::<LengthAdjustment>().is_err());
+        assert!("+".parse::<LengthAdjustment>().is_err());
+        assert!("*2".parse::<LengthAdjustment>().is_err());
+        assert!("+forever".parse::<LengthAdjustment>().is_err());
+    }
+
+    #[test]
+    fn adjustments_change_the_length() {
+        assert_eq!(
+            LengthAdjustment::Extend(minutes(30)).apply(minutes(60)),
+            Some(minutes(90))
+        );
+        assert_eq!(
+            LengthAdjustment::Shorten(minutes(10)).apply(minutes(60)),
+            Some(minutes(50))
+        );
+        assert_eq!(
+            LengthAdjustment::Set(minutes(120)).apply(minutes(60)),
+            Some(minutes(120))
+        );
+    }
+
+    #[test]
+    fn shortening_past_zero_is_refused() {
+        assert_eq!(
+            LengthAdjustment::Shorten(minutes(90)).apply(minutes(60)),
+            None
+        );
+    }
+}
@@ -1,32 +1,59 @@
 use anyhow::{Result, anyhow};
 use chrono::DateTime;
 use chrono::Utc;
+use serenity::all::ChannelId;
 use serenity::all::GuildId;
+use serenity::all::Member;
+use serenity::all::MessageId;
+use serenity::all::RoleId;
 use serenity::all::UserId;
 use std::time::Duration;
 
 use crate::Context;
+use crate::NO_REASON;
+use crate::discord::Discord;
 use crate::get_formatted_message;
-use crate::string_to_id;
+use crate::storage::{End, Storage, no_effect};
+use crate::types::limits::KennelLimits;
+use crate::types::manual_release::ManualReleasePolicy;
+use crate::types::refusal::KennelRefusal;
 use crate::types::server::Server;
-use crate::types::server::ServerRow;
-use crate::util::pgint_dur::PgIntervalToDuration as _;
+use crate::types::stacking::StackingOutcome;
+use crate::types::status::KennelingStatus;
+use crate::util::pgint_dur::{PgIntervalToDuration as _, to_interval};
+use crate::util::snowflake::{from_snowflake, from_snowflakes};
 use crate::util::stefan_traits::GetRelativeTimestamp as _;
 
+/// How long after a kenneling starts member updates about its victim are ignored, since they may be about changes from
+/// before the punishment went on.
+const UPDATE_GRACE_PERIOD: chrono::TimeDelta = chrono::TimeDelta::seconds(5);
+
 /// Represents the fields available from a query to the `kennelings` table.
 #[derive(Debug)]
 pub struct KennelingRow {
-    pub guild_id: String,
+    pub guild_id: i64,
     pub kennel_length: sqlx::postgres::types::PgInterval,
     pub kenneled_at: sqlx::types::chrono::NaiveDateTime,
-    pub author_id: String,
+    pub author_id: i64,
     pub released_at: sqlx::types::chrono::NaiveDateTime,
-    pub victim_id: String,
+    pub victim_id: i64,
     pub id: Option<i32>,
+    pub status: String,
+    pub original_length: sqlx::postgres::types::PgInterval,
+    pub announcement_channel: Option<i64>,
+    pub announcement_message: Option<i64>,
+    pub ended_at: Option<sqlx::types::chrono::NaiveDateTime>,
+    pub ended_by: Option<i64>,
+    pub end_reason: Option<String>,
+    pub paused_at: Option<sqlx::types::chrono::NaiveDateTime>,
+    pub paused_length: sqlx::postgres::types::PgInterval,
+    pub reason: Option<String>,
+    pub stripped_roles: Option<Vec<i64>>,
+    pub community_vote: bool,
 }
 
 /// Information about a given Kenneling from the database.
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Kenneling {
     pub guild_id: GuildId,
     pub kennel_length: Duration,
@@ -35,20 +62,87 @@ pub struct Kenneling {
     pub released_at: DateTime<Utc>,
     pub victim_id: UserId,
     pub id: Option<i32>,
+    /// Whether the kenneling is still going, and if not, how it ended. Set once it ends, by whoever ends it.
+    pub status: KennelingStatus,
+    /// The length the victim was first kenneled for, before any adjustments or stacking.
+    pub original_length: Duration,
+    /// The message announcing this kenneling, so it can be edited on release.
+    pub announcement: Option<(ChannelId, MessageId)>,
+    /// When the kenneling ended, if it has.
+    pub ended_at: Option<DateTime<Utc>>,
+    /// Who ended the kenneling, if anyone did by hand.
+    pub ended_by: Option<UserId>,
+    pub end_reason: Option<String>,
+    /// When the victim left the guild, if they're currently gone. The sentence doesn't run while they're away.
+    pub paused_at: Option<DateTime<Utc>>,
+    /// How long the victim has spent outside of the guild over the course of the kenneling, which pushes back the
+    /// release time.
+    pub paused_length: Duration,
+    /// Why the victim was kenneled, as given by the kenneler.
+    pub reason: Option<String>,
+    /// The roles taken off of the victim for the duration of the kenneling, which are given back on release.
+    pub stripped_roles: Vec<RoleId>,
+    /// Whether the kenneling went through because of a `/vote_kennel`, instead of being the author's call. The author
+    /// is the bot, which carried it out.
+    pub community_vote: bool,
+}
+
+/// What [`Kenneling::validate`] found out about a kenneling.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Validation {
+    /// The victim is still kenneled.
+    Intact,
+    /// The victim isn't in the guild anymore, so the kenneling was paused.
+    Paused,
+    /// The punishment was taken off of the victim by hand, so the kenneling was ended early, after the time served.
+    EndedEarly(Duration),
+    /// The punishment was taken off of the victim by hand, and put back because the server enforces sentences.
+    Enforced { removed_by: Option<UserId> },
+}
+
+/// What happened when a pending kenneling's start time came, from [`Kenneling::start`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StartOutcome {
+    /// The kennel was put on the victim.
+    Started,
+    /// The victim isn't in the guild, so the kenneling started out paused until they come back.
+    Paused,
+    /// The victim was already kenneled, and the server's stacking policy added this one onto that.
+    Stacked,
+    /// The kenneling couldn't go ahead, so it was cancelled, for this reason.
+    Cancelled(String),
+    /// Somebody else started or cancelled it first.
+    Skipped,
 }
 
 impl TryFrom<&KennelingRow> for Kenneling {
-    type Error = std::num::ParseIntError;
+    type Error = anyhow::Error;
 
     fn try_from(row: &KennelingRow) -> Result<Self, Self::Error> {
         Ok(Self {
-            guild_id: string_to_id(&row.guild_id)?,
-            kennel_length: row.kennel_length.as_duration(),
+            guild_id: from_snowflake(row.guild_id)?,
+            kennel_length: row.kennel_length.to_duration()?,
             kenneled_at: row.kenneled_at.and_utc(),
-            author_id: string_to_id(&row.author_id)?,
-            released_at: row.kenneled_at.and_utc(),
-            victim_id: string_to_id(&row.victim_id)?,
+            author_id: from_snowflake(row.author_id)?,
+            released_at: row.released_at.and_utc(),
+            victim_id: from_snowflake(row.victim_id)?,
             id: row.id,
+            status: row.status.parse()?,
+            original_length: row.original_length.to_duration()?,
+            announcement: match (row.announcement_channel, row.announcement_message) {
+                (Some(channel), Some(message)) => {
+                    Some((from_snowflake(channel)?, from_snowflake(message)?))
+                }
+                _ => None,
+            },
+            ended_at: row.ended_at.map(|ended_at| ended_at.and_utc()),
+            ended_by: row.ended_by.map(from_snowflake).transpose()?,
+            end_reason: row.end_reason.clone(),
+            paused_at: row.paused_at.map(|paused_at| paused_at.and_utc()),
+            paused_length: row.paused_length.to_duration()?,
+            reason: row.reason.clone(),
+            stripped_roles: from_snowflakes(row.stripped_roles.as_deref().unwrap_or_default())?,
+            community_vote: row.community_vote,
         })
     }
 }
@@ -58,21 +152,100 @@ impl TryFrom<&Kenneling> for KennelingRow {
 
     fn try_from(row: &Kenneling) -> Result<Self, Self::Error> {
         Ok(KennelingRow {
-            guild_id: row.guild_id.to_string(),
-            kennel_length: row
-                .kennel_length
-                .try_into()
-                .map_err(|_| anyhow!("Couldn't convert length into PgInterval"))?,
+            guild_id: row.guild_id.into(),
+            kennel_length: to_interval(row.kennel_length)?,
             kenneled_at: row.kenneled_at.naive_utc(),
-            author_id: row.author_id.to_string(),
+            author_id: row.author_id.into(),
             released_at: row.released_at.naive_utc(),
-            victim_id: row.victim_id.to_string(),
-            id: None,
+            victim_id: row.victim_id.into(),
+            id: row.id,
+            status: row.status.to_string(),
+            original_length: to_interval(row.original_length)?,
+            announcement_channel: row.announcement.map(|(channel, _)| channel.into()),
+            announcement_message: row.announcement.map(|(_, message)| message.into()),
+            ended_at: row.ended_at.map(|ended_at| ended_at.naive_utc()),
+            ended_by: row.ended_by.map(i64::from),
+            end_reason: row.end_reason.clone(),
+            paused_at: row.paused_at.map(|paused_at| paused_at.naive_utc()),
+            paused_length: to_interval(row.paused_length)?,
+            reason: row.reason.clone(),
+            stripped_roles: (!row.stripped_roles.is_empty()).then(|| {
+                row.stripped_roles
+                    .iter()
+                    .map(|role_id| i64::from(*role_id))
+                    .collect()
+            }),
+            community_vote: row.community_vote,
         })
     }
 }
 
 impl Kenneling {
+    /// A new Kenneling starting now, with no reason and nothing stripped. It isn't stored until it's inserted.
+    pub fn new(
+        guild_id: GuildId,
+        victim_id: UserId,
+        author_id: UserId,
+        kennel_length: Duration,
+    ) -> Self {
+        let kenneled_at = Utc::now();
+
+        Self {
+            guild_id,
+            kennel_length,
+            kenneled_at,
+            author_id,
+            released_at: kenneled_at + kennel_length,
+            victim_id,
+            id: None,
+            status: KennelingStatus::Active,
+            original_length: kennel_length,
+            announcement: None,
+            ended_at: None,
+            ended_by: None,
+            end_reason: None,
+            paused_at: None,
+            paused_length: Duration::ZERO,
+            reason: None,
+            stripped_roles: Vec::new(),
+            community_vote: false,
+        }
+    }
+
+    /// The reason for this Kenneling, or a placeholder if none was given.
+    pub fn display_reason(&self) -> &str {
+        self.reason.as_deref().unwrap_or(NO_REASON)
+    }
+
+    /// Who's behind this Kenneling, as a mention, or "community vote" if it went through because of a vote.
+    pub fn display_author(&self) -> String {
+        if self.community_vote {
+            "community vote".to_string()
+        } else {
+            format!("<@{}>", self.author_id)
+        }
+    }
+
+    /// How long the victim has actually spent in the kennel so far, not counting time paused while they were away.
+    /// The clock stops when they leave or the kenneling ends, and never runs past the sentence.
+    pub fn time_served(&self, now: DateTime<Utc>) -> Duration {
+        (self.paused_at.or(self.ended_at).unwrap_or(now) - self.kenneled_at)
+            .to_std()
+            .unwrap_or_default()
+            .saturating_sub(self.paused_length)
+            .min(self.kennel_length)
+    }
+
+    /// How long this Kenneling counts for in the stats: the whole sentence while it's still going, and the time
+    /// actually served once it's over.
+    pub fn served_length(&self, now: DateTime<Utc>) -> Duration {
+        if self.status.is_open() {
+            self.kennel_length
+        } else {
+            self.time_served(now)
+        }
+    }
+
     /// Applies the roles for a given Kenneling, sends a message, and returns a handle to the announcement message.
     ///
     /// If `ctx` is [`None`], applies the roles, but does not send a message.
@@ -81,7 +254,7 @@ impl Kenneling {
     /// duplicate that query (assuming it's current).
     pub async fn apply_kennel<'a>(
         &self,
-        http: &serenity::all::Http,
+        discord: &dyn Discord,
         server: &Server,
         ctx: Option<&Context<'a>>,
     ) -> Result<Option<poise::ReplyHandle<'a>>> {
@@ -92,141 +265,164 @@ impl Kenneling {
             author_id: kenneler_id,
             released_at,
             victim_id,
-            id: _id,
+            ..
         } = self;
 
-        let kenneler = http.get_member(*guild_id, *kenneler_id).await?;
-        let victim = http.get_member(*guild_id, *victim_id).await?;
+        let victim = discord
+            .member(*guild_id, *victim_id)
+            .await?
+            .ok_or_else(|| anyhow!("{victim_id} isn't in guild {guild_id}"))?;
+
+        let kenneled_by = if self.community_vote {
+            "community vote".to_string()
+        } else {
+            kenneler_id.to_string()
+        };
+        let audit_log_reason = format!("Kenneled by {kenneled_by}: {}", self.display_reason());
+
+        if !self.stripped_roles.is_empty() {
+            let kept_roles: Vec<RoleId> = victim
+                .roles
+                .iter()
+                .filter(|role_id| !self.stripped_roles.contains(role_id))
+                .copied()
+                .collect();
 
-        http.add_member_role(*guild_id, (&victim).into(), server.role_id, None)
+            discord
+                .set_roles(*guild_id, *victim_id, &kept_roles, &audit_log_reason)
+                .await?;
+        }
+
+        server
+            .add_punishment(discord, &victim, *released_at, &audit_log_reason)
             .await?;
 
         tracing::info!(
-            "{} kenneled user {} for {}!",
-            kenneler.display_name(),
+            "{} kenneled user {} for {}! Reason: {}",
+            kenneled_by,
             victim.display_name(),
-            humantime::format_duration(*kennel_length)
+            humantime::format_duration(*kennel_length),
+            self.display_reason()
+        );
+
+        match ctx {
+            Some(ctx) => Ok(Some(self.announce(discord, server, ctx).await?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Replies to a command with the announcement for this Kenneling, tells the kennel channel, and returns a handle
+    /// to the announcement message.
+    pub async fn announce<'a>(
+        &self,
+        discord: &dyn Discord,
+        server: &Server,
+        ctx: &Context<'a>,
+    ) -> Result<poise::ReplyHandle<'a>> {
+        let time = humantime::format_duration(self.kennel_length).to_string();
+        let return_time = self.released_at.discord_relative_timestamp();
+
+        let announcement_msg = get_formatted_message(
+            &server.announcement_message,
+            &self.victim_id,
+            &self.display_author(),
+            &time,
+            &return_time,
+            self.display_reason(),
         );
 
-        let mut reply_handle: Option<poise::ReplyHandle<'a>> = None;
+        let reply_handle = ctx.reply(&announcement_msg).await?;
 
-        if let Some(ctx) = ctx {
-            let announcement_msg = get_formatted_message(
-                &server.announcement_message,
-                victim_id,
-                kenneler_id,
-                &humantime::format_duration(*kennel_length).to_string(),
-                &released_at.discord_relative_timestamp(),
+        // This is kinda dumb. // TODO: put this in a better spot
+        if let Some(kennel_channel) = server.kennel_channel
+            && kennel_channel != ctx.channel_id()
+        {
+            let kennel_announcement_msg = get_formatted_message(
+                &server.kennel_message,
+                &self.victim_id,
+                &self.display_author(),
+                &time,
+                &return_time,
+                self.display_reason(),
             );
 
-            reply_handle = Some(ctx.reply(&announcement_msg).await?);
-
-            // This is kinda dumb. // TODO: put this in a better spot
-            if let Some(kennel_channel) = server.kennel_channel {
-                if kennel_channel != ctx.channel_id() {
-                    let kennel_announcement_msg = get_formatted_message(
-                        &server.kennel_message,
-                        victim_id,
-                        kenneler_id,
-                        &humantime::format_duration(*kennel_length).to_string(),
-                        &released_at.discord_relative_timestamp(),
-                    );
-
-                    match kennel_channel
-                        .send_message(
-                            http,
-                            serenity::all::CreateMessage::new().content(kennel_announcement_msg),
-                        )
-                        .await
-                    {
-                        Ok(_) => {}
-                        Err(e) => tracing::error!("Couldn't send kennel message! {e:?}"),
-                    }
-                }
+            match discord
+                .send_message(kennel_channel, &kennel_announcement_msg)
+                .await
+            {
+                Ok(_) => {}
+                Err(e) => tracing::error!("Couldn't send kennel message! {e:?}"),
             }
         }
 
         Ok(reply_handle)
     }
 
-    /// Applies the roles for a kennel, fetching the server info from the database.
-    pub async fn apply_kennel_from_db(
+    /// Removes the roles for a Kenneling, and edits the announcement message if one was recorded.
+    pub async fn unapply_kennel(
         &self,
-        http: &serenity::all::Http,
-        pool: &sqlx::PgPool,
-    ) -> Result<()> {
-        let server: Server = sqlx::query_as!(
-            ServerRow,
-            r#"
-            SELECT * FROM servers
-            WHERE
-                guild_id = $1
-                ;
-            "#,
-            &self.guild_id.to_string(),
-        )
-        .fetch_one(pool)
-        .await?
-        .try_into()?;
-
-        self.apply_kennel(http, &server, None).await.map(|_| ())
-    }
-
-    /// Removes the roles for a Kenneling, and edits the reply if the handle is present.
-    pub async fn unapply_kennel<'a>(
-        &self,
-        http: &serenity::all::Http,
-        pool: &sqlx::PgPool,
+        discord: &dyn Discord,
+        server: &Server,
         send_in_channel: bool,
-        reply_handle: Option<&poise::ReplyHandle<'a>>,
-        ctx: Option<&Context<'a>>,
     ) -> Result<()> {
         let Kenneling {
             guild_id,
-            kennel_length,
-            kenneled_at: _kenneled_at,
-            author_id: kenneler_id,
             released_at,
             victim_id,
-            id: _id,
+            announcement,
+            ..
         } = self;
 
-        let server: Server = sqlx::query_as!(
-            ServerRow,
-            r#"
-            SELECT * FROM servers
-            WHERE
-                guild_id = $1
-                ;
-            "#,
-            &self.guild_id.to_string(),
-        )
-        .fetch_one(pool)
-        .await?
-        .try_into()?;
+        // Early releases say how long the victim was actually in for, and when they actually got out.
+        let time_served = humantime::format_duration(self.time_served(Utc::now())).to_string();
+        let returned_at = self
+            .ended_at
+            .unwrap_or(*released_at)
+            .discord_relative_timestamp();
 
-        let victim = http.get_member(*guild_id, *victim_id).await?;
+        // Leaving the guild takes the role away anyway, so there's nothing to remove. Timeouts do stick around, but
+        // they run out on their own.
+        match discord.member(*guild_id, *victim_id).await? {
+            Some(victim) => {
+                let audit_log_reason = match &self.end_reason {
+                    Some(end_reason) => format!("Released from the kennel: {end_reason}"),
+                    None => "Released from the kennel".to_string(),
+                };
 
-        victim.remove_role(http, server.role_id).await?;
+                server
+                    .remove_punishment(discord, &victim, &audit_log_reason)
+                    .await?;
 
-        tracing::info!("Unkenneled {}", victim.display_name());
+                if !self.stripped_roles.is_empty() {
+                    self.restore_roles(discord, server.role_id, &victim, &audit_log_reason)
+                        .await?;
+                }
 
-        if let Some(reply_handle) = reply_handle
-            && let Some(ctx) = ctx
-        {
+                tracing::info!("Unkenneled {}", victim.display_name());
+            }
+            None => {
+                tracing::info!("Unkenneled {victim_id}, who isn't in the guild anymore");
+            }
+        }
+
+        if let Some((channel_id, message_id)) = announcement {
             let edit_msg = get_formatted_message(
                 &server.release_message,
                 victim_id,
-                kenneler_id,
-                &humantime::format_duration(*kennel_length).to_string(),
-                &released_at.discord_relative_timestamp(),
+                &self.display_author(),
+                &time_served,
+                &returned_at,
+                self.display_reason(),
             );
 
-            reply_handle
-                .edit(*ctx, poise::CreateReply::default().content(edit_msg))
-                .await?;
-
-            tracing::trace!("Reply edited!");
+            // The announcement might have been deleted in the meantime, which shouldn't stop the release.
+            match discord
+                .edit_message(*channel_id, *message_id, &edit_msg)
+                .await
+            {
+                Ok(_) => tracing::trace!("Announcement edited!"),
+                Err(e) => tracing::warn!("Couldn't edit kennel announcement! {e:?}"),
+            }
         }
 
         if let Some(kennel_channel) = server.kennel_channel
@@ -235,52 +431,786 @@ impl Kenneling {
             let release_message = get_formatted_message(
                 &server.release_message,
                 victim_id,
-                kenneler_id,
-                &humantime::format_duration(*kennel_length).to_string(),
-                &released_at.discord_relative_timestamp(),
+                &self.display_author(),
+                &time_served,
+                &returned_at,
+                self.display_reason(),
             );
 
-            kennel_channel
-                .send_message(
-                    http,
-                    serenity::all::CreateMessage::new().content(release_message),
-                )
+            // The victim is already out by now, so a message that can't be sent shouldn't put them back on record as
+            // kenneled.
+            match discord.send_message(kennel_channel, &release_message).await {
+                Ok(_) => tracing::trace!("Sent release message in kennel channel!"),
+                Err(e) => tracing::warn!("Couldn't send release message! {e:?}"),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Gives the victim back the roles that were stripped from them, other than any that were deleted since.
+    async fn restore_roles(
+        &self,
+        discord: &dyn Discord,
+        kennel_role: Option<RoleId>,
+        victim: &Member,
+        audit_log_reason: &str,
+    ) -> Result<()> {
+        let guild_roles = discord.guild_roles(self.guild_id).await?;
+
+        let mut roles: Vec<RoleId> = victim
+            .roles
+            .iter()
+            .filter(|role_id| Some(**role_id) != kennel_role)
+            .copied()
+            .collect();
+
+        for role_id in &self.stripped_roles {
+            if !roles.contains(role_id) && guild_roles.iter().any(|role| role.id == *role_id) {
+                roles.push(*role_id);
+            }
+        }
+
+        discord
+            .set_roles(self.guild_id, self.victim_id, &roles, audit_log_reason)
+            .await?;
+
+        tracing::info!(
+            "Restored {} stripped roles to {}",
+            self.stripped_roles.len(),
+            victim.display_name()
+        );
+
+        Ok(())
+    }
+
+    /// Marks this Kenneling as released on time and lifts it, so that the release happens exactly once no matter how
+    /// many tasks race for it.
+    ///
+    /// Returns `false` if somebody else already ended it.
+    pub async fn release(&self, discord: &dyn Discord, storage: &dyn Storage) -> Result<bool> {
+        let server = self.server(storage).await?;
+
+        let released = self
+            .end_and_lift(
+                discord,
+                storage,
+                &server,
+                End {
+                    status: KennelingStatus::ReleasedOnTime,
+                    ended_by: None,
+                    reason: None,
+                },
+            )
+            .await?;
+
+        Ok(released.is_some())
+    }
+
+    /// Like [`Kenneling::release`], but before the sentence is up. Records who ended it and why; the sentence itself
+    /// is left as it was.
+    ///
+    /// Returns the ended Kenneling, or [`None`] if somebody else already ended it.
+    pub async fn release_early(
+        &self,
+        discord: &dyn Discord,
+        storage: &dyn Storage,
+        released_by: UserId,
+        reason: Option<&str>,
+    ) -> Result<Option<Kenneling>> {
+        let server = self.server(storage).await?;
+
+        self.end_and_lift(
+            discord,
+            storage,
+            &server,
+            End {
+                status: KennelingStatus::ReleasedEarly,
+                ended_by: Some(released_by),
+                reason: reason.map(str::to_string),
+            },
+        )
+        .await
+    }
+
+    /// How this Kenneling ends when a new one takes its place, for [`Storage::start`]. It isn't lifted, since the new
+    /// one carries on from it.
+    pub fn replaced_by(&self, replaced_by: UserId) -> Result<(i32, End)> {
+        let id = self
+            .id
+            .ok_or_else(|| anyhow!("Can't replace a kenneling that was never inserted"))?;
+
+        Ok((
+            id,
+            End {
+                status: KennelingStatus::Revoked,
+                ended_by: Some(replaced_by),
+                reason: Some("Replaced by a new kenneling".to_string()),
+            },
+        ))
+    }
+
+    /// Gives up on releasing this Kenneling, so that it stops being retried. The punishment stays wherever it is.
+    ///
+    /// Returns `false` if it was ended in the meantime.
+    pub async fn fail(&self, storage: &dyn Storage, reason: &str) -> Result<bool> {
+        let id = self
+            .id
+            .ok_or_else(|| anyhow!("Can't fail a kenneling that was never inserted"))?;
+
+        let failed = storage
+            .end(
+                id,
+                End {
+                    status: KennelingStatus::Failed,
+                    ended_by: None,
+                    reason: Some(reason.to_string()),
+                },
+                no_effect(),
+            )
+            .await?;
+
+        Ok(failed.is_some())
+    }
+
+    /// Ends this Kenneling as described by `end`, and takes the kennel off in the same go.
+    async fn end_and_lift(
+        &self,
+        discord: &dyn Discord,
+        storage: &dyn Storage,
+        server: &Server,
+        end: End,
+    ) -> Result<Option<Kenneling>> {
+        let id = self
+            .id
+            .ok_or_else(|| anyhow!("Can't release a kenneling that was never inserted"))?;
+
+        storage
+            .end(
+                id,
+                end,
+                Box::new(move |kenneling| {
+                    Box::pin(async move { kenneling.unapply_kennel(discord, server, true).await })
+                }),
+            )
+            .await
+    }
+
+    /// Starts this pending Kenneling, now that its start time has come. If the victim is already kenneled, the
+    /// server's stacking policy decides what happens, just like it does for kennelings that start right away. If they
+    /// aren't in the guild, it starts out paused, and they serve it once they come back.
+    ///
+    /// It's cancelled instead if it wouldn't be allowed anymore, e.g. because the victim became immune or the author
+    /// was demoted or left. `bot_id` is the bot's own user, which has to be able to kennel the victim.
+    pub async fn start(
+        &self,
+        discord: &dyn Discord,
+        storage: &dyn Storage,
+        bot_id: UserId,
+    ) -> Result<StartOutcome> {
+        let id = self
+            .id
+            .ok_or_else(|| anyhow!("Can't start a kenneling that was never inserted"))?;
+        let server = &self.server(storage).await?;
+        let victim = discord.member(self.guild_id, self.victim_id).await?;
+
+        if let Some(reason) = self
+            .refusal(discord, storage, server, bot_id, victim.as_ref())
+            .await?
+        {
+            return self.cancel_because(discord, storage, reason).await;
+        }
+
+        // Stacking goes by the sentence as it'll actually run, starting now.
+        let now = Utc::now();
+        let starting = Kenneling {
+            kenneled_at: now,
+            released_at: now + self.kennel_length,
+            ..self.clone()
+        };
+
+        let mut stripped_roles = Vec::new();
+        let mut replacing = None;
+        let policy = server.stacking_policy;
+
+        match policy.resolve(
+            storage
+                .active_kenneling(self.guild_id, self.victim_id)
+                .await?,
+            &starting,
+        ) {
+            StackingOutcome::Insert => {}
+            StackingOutcome::Reject(_) => {
+                return self
+                    .cancel_because(
+                        discord,
+                        storage,
+                        format!("They were already in the kennel (stacking policy: {policy})"),
+                    )
+                    .await;
+            }
+            StackingOutcome::Replace(active) => {
+                // Whatever the old kenneling stripped is already gone, so it has to be handed down to be restored later.
+                replacing = Some(active.replaced_by(self.author_id)?);
+                stripped_roles = active.stripped_roles;
+            }
+            StackingOutcome::Adjust(active, kennel_length) => {
+                if !server
+                    .can_enforce_until(active.released_at - active.kennel_length + kennel_length)
+                {
+                    return self
+                        .cancel_because(
+                            discord,
+                            storage,
+                            "Adding it on would make the kenneling too long".to_string(),
+                        )
+                        .await;
+                }
+
+                // If it got released in the meantime, there's nothing to stack with anymore.
+                if let Some(adjusted) = active.set_length(storage, kennel_length).await? {
+                    if victim.is_some() {
+                        adjusted.apply_kennel(discord, server, None).await?;
+                    }
+
+                    return Ok(
+                        match self
+                            .cancel(
+                                discord,
+                                storage,
+                                None,
+                                Some("Added onto the kenneling they were already serving"),
+                            )
+                            .await?
+                        {
+                            Some(_) => StartOutcome::Stacked,
+                            None => StartOutcome::Skipped,
+                        },
+                    );
+                }
+            }
+        }
+
+        let Some(victim) = victim else {
+            let Some(started) = storage
+                .start(id, &stripped_roles, replacing, no_effect())
+                .await?
+            else {
+                return Ok(StartOutcome::Skipped);
+            };
+
+            started.pause(storage).await?;
+
+            return Ok(StartOutcome::Paused);
+        };
+
+        for role_id in server.strippable_roles(discord, &victim).await? {
+            if !stripped_roles.contains(&role_id) {
+                stripped_roles.push(role_id);
+            }
+        }
+
+        let Some(started) = storage
+            .start(
+                id,
+                &stripped_roles,
+                replacing,
+                Box::new(move |kenneling| {
+                    Box::pin(async move {
+                        kenneling
+                            .apply_kennel(discord, server, None)
+                            .await
+                            .map(|_| ())
+                    })
+                }),
+            )
+            .await?
+        else {
+            return Ok(StartOutcome::Skipped);
+        };
+
+        // The kennel is already on, so a missing announcement shouldn't undo that.
+        if let Err(e) = started.announce_start(discord, server).await {
+            tracing::warn!("Couldn't announce the start of {started:?}: {e:?}");
+        }
+
+        Ok(StartOutcome::Started)
+    }
+
+    /// Runs the checks a kenneling has to pass before it starts again, since roles, immunities and limits may have
+    /// changed since this one was scheduled. Community votes are checked the way `/vote_kennel` checks them, with the
+    /// bot standing in for the author. The victim's rank can only be checked if they're in the guild.
+    ///
+    /// Returns why it can't start, if it can't.
+    async fn refusal(
+        &self,
+        discord: &dyn Discord,
+        storage: &dyn Storage,
+        server: &Server,
+        bot_id: UserId,
+        victim: Option<&Member>,
+    ) -> Result<Option<String>> {
+        let author = if self.community_vote {
+            None
+        } else {
+            let Some(author) = discord.member(self.guild_id, self.author_id).await? else {
+                return Ok(Some(format!(
+                    "<@{}> isn't in the server anymore",
+                    self.author_id
+                )));
+            };
+
+            Some(author)
+        };
+
+        let limits = match &author {
+            Some(author) => KennelLimits::for_author(storage, server, author).await?,
+            None => KennelLimits::for_server(server),
+        };
+
+        if let Err(refusal) = limits.check(self.kennel_length) {
+            return Ok(Some(refusal.to_string()));
+        }
+
+        let Some(victim) = victim else {
+            return Ok(None);
+        };
+
+        let bot = discord
+            .member(self.guild_id, bot_id)
+            .await?
+            .ok_or_else(|| anyhow!("The bot isn't in guild {}", self.guild_id))?;
+        let owner_id = discord.guild_owner(self.guild_id).await?;
+        let roles = discord
+            .guild_roles(self.guild_id)
+            .await?
+            .into_iter()
+            .map(|role| (role.id, role))
+            .collect();
+
+        Ok(KennelRefusal::check_roles(
+            server,
+            owner_id,
+            &roles,
+            &bot,
+            author.as_ref().unwrap_or(&bot),
+            victim,
+        )
+        .err()
+        .map(|refusal| refusal.to_string()))
+    }
+
+    /// Announces a kenneling that started on its own, instead of from a command: the message that scheduled it turns
+    /// into the announcement, and the kennel channel is told like usual.
+    async fn announce_start(&self, discord: &dyn Discord, server: &Server) -> Result<()> {
+        let time = humantime::format_duration(self.kennel_length).to_string();
+        let return_time = self.released_at.discord_relative_timestamp();
+
+        if let Some((channel_id, message_id)) = self.announcement {
+            let announcement_msg = get_formatted_message(
+                &server.announcement_message,
+                &self.victim_id,
+                &self.display_author(),
+                &time,
+                &return_time,
+                self.display_reason(),
+            );
+
+            discord
+                .edit_message(channel_id, message_id, &announcement_msg)
+                .await?;
+        }
+
+        if let Some(kennel_channel) = server.kennel_channel {
+            let kennel_announcement_msg = get_formatted_message(
+                &server.kennel_message,
+                &self.victim_id,
+                &self.display_author(),
+                &time,
+                &return_time,
+                self.display_reason(),
+            );
+
+            discord
+                .send_message(kennel_channel, &kennel_announcement_msg)
                 .await?;
-            tracing::trace!("Sent release message in kennel channel!");
         }
 
         Ok(())
     }
-}
 
-impl KennelingRow {
-    /// Inserts this Row into the database, assuming that it is current (ignores the timestamp value of the Row).
+    /// Calls off this pending Kenneling before it starts, and says so on the message that scheduled it.
     ///
-    /// Returns the ID of the inserted kenneling.
-    pub async fn assume_current_and_insert(&self, pool: &sqlx::PgPool) -> Result<i32> {
-        tracing::trace!("Inserting a new Kenneling...");
-
-        let id = sqlx::query!(
-            r#"
-            INSERT INTO kennelings
-                (guild_id, victim_id, author_id, kennel_length)
-            VALUES
-                ($1, $2, $3, $4)
-            RETURNING
-                id
-                ;
-            "#,
-            self.guild_id,
-            self.victim_id,
-            self.author_id,
-            self.kennel_length,
+    /// Returns the cancelled Kenneling, or [`None`] if it already started or was cancelled.
+    pub async fn cancel(
+        &self,
+        discord: &dyn Discord,
+        storage: &dyn Storage,
+        cancelled_by: Option<UserId>,
+        reason: Option<&str>,
+    ) -> Result<Option<Kenneling>> {
+        let id = self
+            .id
+            .ok_or_else(|| anyhow!("Can't cancel a kenneling that was never inserted"))?;
+
+        let Some(cancelled) = storage
+            .cancel(id, cancelled_by, reason.map(str::to_string))
+            .await?
+        else {
+            return Ok(None);
+        };
+
+        if let Some((channel_id, message_id)) = cancelled.announcement {
+            let mut cancelled_msg = format!("<@{}>'s kenneling was called off.", self.victim_id);
+
+            if let Some(reason) = reason {
+                cancelled_msg.push_str(&format!(" Reason: {reason}"));
+            }
+
+            // The message might have been deleted in the meantime, which shouldn't stop the cancellation.
+            if let Err(e) = discord
+                .edit_message(channel_id, message_id, &cancelled_msg)
+                .await
+            {
+                tracing::warn!("Couldn't edit the scheduled kenneling message! {e:?}");
+            }
+        }
+
+        Ok(Some(cancelled))
+    }
+
+    /// Cancels this pending Kenneling because it couldn't go ahead.
+    async fn cancel_because(
+        &self,
+        discord: &dyn Discord,
+        storage: &dyn Storage,
+        reason: String,
+    ) -> Result<StartOutcome> {
+        Ok(
+            match self.cancel(discord, storage, None, Some(&reason)).await? {
+                Some(_) => StartOutcome::Cancelled(reason),
+                None => StartOutcome::Skipped,
+            },
         )
-        .fetch_one(pool)
-        .await?
-        .id;
+    }
+
+    /// Changes the length of this (active) Kenneling. Since the release time is derived from the length, the scheduler
+    /// picks up the new release time on its own.
+    ///
+    /// Returns the updated Kenneling, or [`None`] if it was released in the meantime.
+    pub async fn set_length(
+        &self,
+        storage: &dyn Storage,
+        kennel_length: Duration,
+    ) -> Result<Option<Kenneling>> {
+        let id = self
+            .id
+            .ok_or_else(|| anyhow!("Can't adjust a kenneling that was never inserted"))?;
+
+        storage.set_length(id, kennel_length).await
+    }
+
+    /// Points this Kenneling at a new announcement message, which is the one edited on release.
+    pub async fn set_announcement(
+        &mut self,
+        storage: &dyn Storage,
+        announcement: (ChannelId, MessageId),
+    ) -> Result<()> {
+        let id = self
+            .id
+            .ok_or_else(|| anyhow!("Can't update a kenneling that was never inserted"))?;
+
+        storage.set_announcement(id, announcement).await?;
+
+        self.announcement = Some(announcement);
+
+        Ok(())
+    }
+
+    /// Stops the clock on this Kenneling, because the victim left the guild.
+    ///
+    /// Returns `false` if it was already paused or released.
+    pub async fn pause(&self, storage: &dyn Storage) -> Result<bool> {
+        let id = self
+            .id
+            .ok_or_else(|| anyhow!("Can't pause a kenneling that was never inserted"))?;
+
+        storage.pause(id).await
+    }
+
+    /// Starts the clock on this Kenneling again, because the victim came back, and puts the kennel back on them. The
+    /// time spent away is added to the release time.
+    ///
+    /// Returns the updated Kenneling, or [`None`] if it wasn't paused.
+    pub async fn resume(
+        &self,
+        discord: &dyn Discord,
+        storage: &dyn Storage,
+    ) -> Result<Option<Kenneling>> {
+        let id = self
+            .id
+            .ok_or_else(|| anyhow!("Can't resume a kenneling that was never inserted"))?;
+        let server = &self.server(storage).await?;
+
+        storage
+            .resume(
+                id,
+                Box::new(move |kenneling| {
+                    Box::pin(async move {
+                        kenneling
+                            .apply_kennel(discord, server, None)
+                            .await
+                            .map(|_| ())
+                    })
+                }),
+            )
+            .await
+    }
+
+    /// Checks that this (running) Kenneling is still in effect on `victim`, who is [`None`] if they're not in the
+    /// guild anymore. If the victim left, the kenneling is paused. If somebody took the punishment off of them by
+    /// hand, what happens depends on the server's [`ManualReleasePolicy`]: either it's recorded as an early release
+    /// by whoever did it, or the punishment is put back. `bot_id` is the bot's own user, whose removals don't count.
+    pub async fn validate(
+        &self,
+        discord: &dyn Discord,
+        storage: &dyn Storage,
+        server: &Server,
+        bot_id: UserId,
+        victim: Option<&Member>,
+    ) -> Result<Validation> {
+        let Some(victim) = victim else {
+            self.pause(storage).await?;
+
+            return Ok(Validation::Paused);
+        };
+
+        if server.has_punishment(victim) {
+            return Ok(Validation::Intact);
+        }
+
+        // Without the audit log permission there's nobody to blame, but the policy still applies.
+        let removed_by = self.punishment_removed_by(discord, server).await;
+
+        self.handle_removal(discord, storage, server, bot_id, victim, removed_by)
+            .await
+    }
+
+    /// Like [`Kenneling::validate`], but for a member update about `victim` that just came in. Updates can arrive
+    /// after the kenneling started even if they're from before the punishment went on (like the one for stripping
+    /// their roles), so the ones from right after the start are ignored. It's also only acted on once the audit log
+    /// says who took the punishment off, since it may not have caught up yet. Anything else is left to the
+    /// healthcheck.
+    pub async fn validate_update(
+        &self,
+        discord: &dyn Discord,
+        storage: &dyn Storage,
+        server: &Server,
+        bot_id: UserId,
+        victim: &Member,
+    ) -> Result<Validation> {
+        if Utc::now() - self.kenneled_at < UPDATE_GRACE_PERIOD || server.has_punishment(victim) {
+            return Ok(Validation::Intact);
+        }
+
+        let Some(removed_by) = self.punishment_removed_by(discord, server).await else {
+            return Ok(Validation::Intact);
+        };
+
+        self.handle_removal(discord, storage, server, bot_id, victim, Some(removed_by))
+            .await
+    }
+
+    /// Looks up who took the punishment off of the victim, if the audit log says. Anything from before this kenneling
+    /// was about some other punishment.
+    async fn punishment_removed_by(
+        &self,
+        discord: &dyn Discord,
+        server: &Server,
+    ) -> Option<UserId> {
+        server
+            .punishment_removed_by(discord, self.victim_id, self.kenneled_at)
+            .await
+            .unwrap_or_else(|e| {
+                tracing::warn!("Couldn't look up who released {}: {e:?}", self.victim_id);
+                None
+            })
+    }
+
+    /// Applies the server's [`ManualReleasePolicy`] to a victim whose punishment was taken off by `removed_by`.
+    async fn handle_removal(
+        &self,
+        discord: &dyn Discord,
+        storage: &dyn Storage,
+        server: &Server,
+        bot_id: UserId,
+        victim: &Member,
+        removed_by: Option<UserId>,
+    ) -> Result<Validation> {
+        // The bot only takes the punishment off when ending a kenneling, which this one is in the middle of.
+        if removed_by == Some(bot_id) {
+            return Ok(Validation::Intact);
+        }
+
+        match server.manual_release {
+            ManualReleasePolicy::Honor => {
+                let ended = self
+                    .end_and_lift(
+                        discord,
+                        storage,
+                        server,
+                        End {
+                            status: KennelingStatus::ReleasedEarly,
+                            ended_by: removed_by,
+                            reason: Some("Punishment removed by hand".to_string()),
+                        },
+                    )
+                    .await?;
+
+                Ok(match ended {
+                    Some(ended) => Validation::EndedEarly(ended.time_served(Utc::now())),
+                    None => Validation::Intact,
+                })
+            }
+            ManualReleasePolicy::Enforce => self.enforce(discord, server, victim, removed_by).await,
+        }
+    }
+
+    /// Puts the punishment back on a victim who had it taken off by hand, and reports whoever did it to the kennel
+    /// channel.
+    async fn enforce(
+        &self,
+        discord: &dyn Discord,
+        server: &Server,
+        victim: &Member,
+        removed_by: Option<UserId>,
+    ) -> Result<Validation> {
+        server
+            .add_punishment(
+                discord,
+                victim,
+                self.released_at,
+                "Kennel sentence enforced",
+            )
+            .await?;
+
+        if let Some(kennel_channel) = server.kennel_channel {
+            let remover = removed_by.map_or_else(
+                || "Someone".to_string(),
+                |removed_by| format!("<@{removed_by}>"),
+            );
+
+            discord
+                .send_message(
+                    kennel_channel,
+                    &format!(
+                        "{remover} let <@{}> out of the kennel early, so they've been put back! They will return {}.",
+                        self.victim_id,
+                        self.released_at.discord_relative_timestamp()
+                    ),
+                )
+                .await?;
+        }
+
+        Ok(Validation::Enforced { removed_by })
+    }
+
+    /// Fetches the settings of the server this Kenneling is in.
+    async fn server(&self, storage: &dyn Storage) -> Result<Server> {
+        storage
+            .server(self.guild_id)
+            .await?
+            .ok_or_else(|| anyhow!("Server {} isn't set up", self.guild_id))
+    }
+}
 
-        tracing::trace!("New Kenneling inserted! Id: {id}");
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
 
-        Ok(id)
+    /// Snowflakes are stored as `bigint`s, which every ID Discord hands out fits in.
+    fn snowflake() -> impl Strategy<Value = u64> {
+        1..=i64::MAX as u64
+    }
+
+    /// Lengths that can be stored, down to the microsecond.
+    fn length() -> impl Strategy<Value = Duration> {
+        (0..=i64::MAX as u64).prop_map(Duration::from_micros)
+    }
+
+    /// Any time from 1970 to 2100, down to the microsecond like `timestamp` columns.
+    fn timestamp() -> impl Strategy<Value = DateTime<Utc>> {
+        (0..4_102_444_800i64, 0..1_000_000u32)
+            .prop_map(|(secs, micros)| DateTime::from_timestamp(secs, micros * 1000).unwrap())
+    }
+
+    fn status() -> impl Strategy<Value = KennelingStatus> {
+        prop_oneof![
+            Just(KennelingStatus::Pending),
+            Just(KennelingStatus::Cancelled),
+            Just(KennelingStatus::Active),
+            Just(KennelingStatus::Paused),
+            Just(KennelingStatus::ReleasedOnTime),
+            Just(KennelingStatus::ReleasedEarly),
+            Just(KennelingStatus::Revoked),
+            Just(KennelingStatus::Failed),
+        ]
+    }
+
+    prop_compose! {
+        fn kenneling()(
+            (guild_id, victim_id, author_id) in (snowflake(), snowflake(), snowflake()),
+            (kennel_length, original_length, paused_length) in (length(), length(), length()),
+            (kenneled_at, released_at) in (timestamp(), timestamp()),
+            (ended_at, paused_at) in (proptest::option::of(timestamp()), proptest::option::of(timestamp())),
+            id in proptest::option::of(any::<i32>()),
+            status in status(),
+            announcement in proptest::option::of((snowflake(), snowflake())),
+            ended_by in proptest::option::of(snowflake()),
+            end_reason in proptest::option::of(".*"),
+            reason in proptest::option::of(".*"),
+            stripped_roles in proptest::collection::vec(snowflake(), 0..5),
+            community_vote in any::<bool>(),
+        ) -> Kenneling {
+            Kenneling {
+                guild_id: GuildId::new(guild_id),
+                kennel_length,
+                kenneled_at,
+                author_id: UserId::new(author_id),
+                released_at,
+                victim_id: UserId::new(victim_id),
+                id,
+                status,
+                original_length,
+                announcement: announcement
+                    .map(|(channel, message)| (ChannelId::new(channel), MessageId::new(message))),
+                ended_at,
+                ended_by: ended_by.map(UserId::new),
+                end_reason,
+                paused_at,
+                paused_length,
+                reason,
+                stripped_roles: stripped_roles.into_iter().map(RoleId::new).collect(),
+                community_vote,
+            }
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn kennelings_round_trip_through_rows(kenneling in kenneling()) {
+            let row = KennelingRow::try_from(&kenneling).unwrap();
+
+            prop_assert_eq!(Kenneling::try_from(&row).unwrap(), kenneling);
+        }
+
+        #[test]
+        fn release_time_comes_from_the_row(kenneling in kenneling(), released_at in timestamp()) {
+            let mut row = KennelingRow::try_from(&kenneling).unwrap();
+            row.released_at = released_at.naive_utc();
+
+            prop_assert_eq!(Kenneling::try_from(&row).unwrap().released_at, released_at);
+        }
     }
 }
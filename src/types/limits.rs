@@ -0,0 +1,176 @@
+use anyhow::Result;
+use serenity::all::Member;
+use std::time::Duration;
+
+use crate::storage::Storage;
+use crate::types::refusal::KennelRefusal;
+use crate::types::server::Server;
+
+/// The shortest and longest a given moderator is allowed to kennel someone for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct KennelLimits {
+    pub min: Option<Duration>,
+    pub max: Option<Duration>,
+}
+
+impl KennelLimits {
+    /// Works out the limits for `author` in `server`.
+    ///
+    /// If any of the author's roles has its own maximum, the most generous of those replaces the server-wide
+    /// maximum, so that e.g. trial mods and senior mods can have different caps. A role's maximum can be longer than
+    /// the server-wide one on purpose, to let senior mods go past it.
+    pub async fn for_author(
+        storage: &dyn Storage,
+        server: &Server,
+        author: &Member,
+    ) -> Result<Self> {
+        let role_max = storage
+            .role_limits(server.guild_id)
+            .await?
+            .into_iter()
+            .filter(|(role_id, _)| author.roles.contains(role_id))
+            .map(|(_, max)| max)
+            .max();
+
+        Ok(KennelLimits {
+            min: server.min_kennel_length,
+            max: role_max.or(server.max_kennel_length),
+        })
+    }
+
+    /// The limits for kennelings with nobody in particular behind them, like community votes: just the server-wide
+    /// ones.
+    pub fn for_server(server: &Server) -> Self {
+        KennelLimits {
+            min: server.min_kennel_length,
+            max: server.max_kennel_length,
+        }
+    }
+
+    /// Checks a kennel length against these limits.
+    pub fn check(&self, length: Duration) -> Result<(), KennelRefusal> {
+        if let Some(min) = self.min
+            && length < min
+        {
+            return Err(KennelRefusal::TooShort(min));
+        }
+
+        if let Some(max) = self.max
+            && length > max
+        {
+            return Err(KennelRefusal::TooLong(max));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serenity::all::{GuildId, RoleId};
+
+    use crate::storage::memory::MemoryStorage;
+
+    const GUILD: GuildId = GuildId::new(1);
+    const TRIAL_MOD: RoleId = RoleId::new(2);
+    const SENIOR_MOD: RoleId = RoleId::new(3);
+
+    const MINUTE: Duration = Duration::from_secs(60);
+    const HOUR: Duration = Duration::from_secs(60 * 60);
+    const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+    fn server() -> Server {
+        Server {
+            min_kennel_length: Some(MINUTE),
+            max_kennel_length: Some(DAY),
+            ..Server::new(GUILD)
+        }
+    }
+
+    fn author(role_ids: &[RoleId]) -> Member {
+        let mut member = Member::default();
+        member.roles = role_ids.to_vec();
+
+        member
+    }
+
+    async fn storage() -> MemoryStorage {
+        let storage = MemoryStorage::new();
+        storage
+            .set_role_limit(GUILD, TRIAL_MOD, Some(HOUR))
+            .await
+            .unwrap();
+        storage
+            .set_role_limit(GUILD, SENIOR_MOD, Some(7 * DAY))
+            .await
+            .unwrap();
+
+        storage
+    }
+
+    #[test]
+    fn lengths_are_checked_against_both_ends() {
+        let limits = KennelLimits::for_server(&server());
+
+        assert_eq!(
+            limits.check(Duration::from_secs(59)),
+            Err(KennelRefusal::TooShort(MINUTE))
+        );
+        assert_eq!(limits.check(MINUTE), Ok(()));
+        assert_eq!(limits.check(DAY), Ok(()));
+        assert_eq!(limits.check(DAY + MINUTE), Err(KennelRefusal::TooLong(DAY)));
+    }
+
+    #[test]
+    fn no_limits_allow_anything() {
+        let limits = KennelLimits::default();
+
+        assert_eq!(limits.check(Duration::ZERO), Ok(()));
+        assert_eq!(limits.check(365 * DAY), Ok(()));
+    }
+
+    #[tokio::test]
+    async fn authors_without_capped_roles_get_the_server_limits() {
+        let limits = KennelLimits::for_author(&storage().await, &server(), &author(&[]))
+            .await
+            .unwrap();
+
+        assert_eq!(limits, KennelLimits::for_server(&server()));
+    }
+
+    #[tokio::test]
+    async fn role_caps_replace_the_server_maximum() {
+        let storage = storage().await;
+
+        let trial = KennelLimits::for_author(&storage, &server(), &author(&[TRIAL_MOD]))
+            .await
+            .unwrap();
+        assert_eq!(
+            trial,
+            KennelLimits {
+                min: Some(MINUTE),
+                max: Some(HOUR)
+            }
+        );
+
+        // Going past the server-wide maximum is allowed, for roles that are trusted with more.
+        let senior = KennelLimits::for_author(&storage, &server(), &author(&[SENIOR_MOD]))
+            .await
+            .unwrap();
+        assert_eq!(senior.max, Some(7 * DAY));
+    }
+
+    #[tokio::test]
+    async fn the_most_generous_role_cap_wins() {
+        let limits = KennelLimits::for_author(
+            &storage().await,
+            &server(),
+            &author(&[TRIAL_MOD, SENIOR_MOD]),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(limits.max, Some(7 * DAY));
+    }
+}
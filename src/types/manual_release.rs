@@ -0,0 +1,41 @@
+use anyhow::anyhow;
+use std::str::FromStr;
+
+/// What a server does when someone takes the kennel punishment off of a kenneled member by hand.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, poise::ChoiceParameter)]
+pub enum ManualReleasePolicy {
+    /// Treat it as an early release by whoever took the punishment off.
+    #[name = "Honor manual release"]
+    Honor,
+    /// Put the punishment back, and report whoever took it off to the kennel channel.
+    #[name = "Enforce the sentence"]
+    Enforce,
+}
+
+impl ManualReleasePolicy {
+    /// The representation stored in the `servers` table.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ManualReleasePolicy::Honor => "honor",
+            ManualReleasePolicy::Enforce => "enforce",
+        }
+    }
+}
+
+impl FromStr for ManualReleasePolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "honor" => Ok(ManualReleasePolicy::Honor),
+            "enforce" => Ok(ManualReleasePolicy::Enforce),
+            _ => Err(anyhow!("Unknown manual release policy: {s}")),
+        }
+    }
+}
+
+impl std::fmt::Display for ManualReleasePolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
@@ -0,0 +1,51 @@
+use anyhow::anyhow;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// How a server keeps its kenneled users in line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, poise::ChoiceParameter)]
+pub enum PunishmentMode {
+    /// Give the victim the kennel role.
+    #[name = "Kennel role"]
+    Role,
+    /// Use Discord's own member timeout.
+    #[name = "Discord timeout"]
+    Timeout,
+}
+
+impl PunishmentMode {
+    /// The representation stored in the `servers` table.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PunishmentMode::Role => "role",
+            PunishmentMode::Timeout => "timeout",
+        }
+    }
+
+    /// The longest a kenneling can have left to run, if this mode has a limit.
+    pub fn max_remaining(&self) -> Option<Duration> {
+        match self {
+            PunishmentMode::Role => None,
+            // Discord refuses to time anyone out for longer than 28 days.
+            PunishmentMode::Timeout => Some(Duration::from_secs(28 * 24 * 60 * 60)),
+        }
+    }
+}
+
+impl FromStr for PunishmentMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "role" => Ok(PunishmentMode::Role),
+            "timeout" => Ok(PunishmentMode::Timeout),
+            _ => Err(anyhow!("Unknown punishment mode: {s}")),
+        }
+    }
+}
+
+impl std::fmt::Display for PunishmentMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
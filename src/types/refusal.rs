@@ -0,0 +1,353 @@
+use serenity::all::{Member, PartialGuild, Role, RoleId, UserId};
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::types::punishment::PunishmentMode;
+use crate::types::server::Server;
+
+/// A reason a kenneling isn't allowed to go ahead. Checked before anything is applied, so that moderators get a
+/// useful explanation instead of an error from deep inside the Discord API.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KennelRefusal {
+    /// The author tried to kennel themselves.
+    SelfKennel,
+    /// The victim is a bot.
+    Bot,
+    /// The victim owns the server.
+    Owner,
+    /// The victim is an administrator.
+    Administrator,
+    /// The victim is on the server's list of immune users.
+    ImmuneUser,
+    /// The victim has one of the server's immune roles.
+    ImmuneRole(RoleId),
+    /// The victim's top role isn't below the author's.
+    AuthorNotAbove,
+    /// The kennel role isn't below the bot's top role, so the bot can't hand it out.
+    BotNotAboveKennelRole(RoleId),
+    /// The victim's top role isn't below the bot's, so the bot can't time them out or strip their roles.
+    BotNotAboveVictim,
+    /// The kennel length is below the server's minimum.
+    TooShort(Duration),
+    /// The kennel length is above the author's maximum.
+    TooLong(Duration),
+}
+
+impl KennelRefusal {
+    /// Checks whether `author` may kennel `victim` in `guild`, and whether the `bot` is able to carry it out.
+    pub fn check(
+        server: &Server,
+        guild: &PartialGuild,
+        bot: &Member,
+        author: &Member,
+        victim: &Member,
+    ) -> Result<(), KennelRefusal> {
+        Self::check_roles(server, guild.owner_id, &guild.roles, bot, author, victim)
+    }
+
+    /// [`KennelRefusal::check`], going by just the guild's owner and roles, for when there's no [`PartialGuild`] at
+    /// hand.
+    pub fn check_roles(
+        server: &Server,
+        owner_id: UserId,
+        roles: &HashMap<RoleId, Role>,
+        bot: &Member,
+        author: &Member,
+        victim: &Member,
+    ) -> Result<(), KennelRefusal> {
+        if author.user.id == victim.user.id {
+            return Err(KennelRefusal::SelfKennel);
+        }
+
+        if victim.user.bot {
+            return Err(KennelRefusal::Bot);
+        }
+
+        if owner_id == victim.user.id {
+            return Err(KennelRefusal::Owner);
+        }
+
+        if victim
+            .roles
+            .iter()
+            .filter_map(|role_id| roles.get(role_id))
+            .any(|role| role.permissions.administrator())
+        {
+            return Err(KennelRefusal::Administrator);
+        }
+
+        if server.immune_users.contains(&victim.user.id) {
+            return Err(KennelRefusal::ImmuneUser);
+        }
+
+        if let Some(role_id) = victim
+            .roles
+            .iter()
+            .find(|role_id| server.immune_roles.contains(role_id))
+        {
+            return Err(KennelRefusal::ImmuneRole(*role_id));
+        }
+
+        let victim_position = top_role_position(roles, victim);
+
+        if owner_id != author.user.id && top_role_position(roles, author) <= victim_position {
+            return Err(KennelRefusal::AuthorNotAbove);
+        }
+
+        let bot_position = top_role_position(roles, bot);
+
+        if server.punishment_mode == PunishmentMode::Role
+            && let Some(kennel_role) = server.role_id
+            && roles
+                .get(&kennel_role)
+                .is_some_and(|role| role.position >= bot_position)
+        {
+            return Err(KennelRefusal::BotNotAboveKennelRole(kennel_role));
+        }
+
+        if (server.punishment_mode == PunishmentMode::Timeout || server.strip_roles)
+            && bot_position <= victim_position
+        {
+            return Err(KennelRefusal::BotNotAboveVictim);
+        }
+
+        Ok(())
+    }
+}
+
+/// The position of a member's highest role, where 0 is `@everyone`.
+fn top_role_position(roles: &HashMap<RoleId, Role>, member: &Member) -> u16 {
+    member
+        .roles
+        .iter()
+        .filter_map(|role_id| roles.get(role_id))
+        .map(|role| role.position)
+        .max()
+        .unwrap_or(0)
+}
+
+impl std::fmt::Display for KennelRefusal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KennelRefusal::SelfKennel => write!(f, "You can't kennel yourself!"),
+            KennelRefusal::Bot => write!(f, "Bots can't be kenneled!"),
+            KennelRefusal::Owner => write!(f, "The server owner can't be kenneled!"),
+            KennelRefusal::Administrator => write!(f, "Administrators can't be kenneled!"),
+            KennelRefusal::ImmuneUser => write!(f, "That user is immune to the kennel!"),
+            KennelRefusal::ImmuneRole(role_id) => {
+                write!(f, "Members with <@&{role_id}> are immune to the kennel!")
+            }
+            KennelRefusal::AuthorNotAbove => {
+                write!(
+                    f,
+                    "You can only kennel members whose top role is below yours!"
+                )
+            }
+            KennelRefusal::BotNotAboveKennelRole(role_id) => write!(
+                f,
+                "I can't hand out <@&{role_id}>! Move my role above it in the server settings."
+            ),
+            KennelRefusal::BotNotAboveVictim => write!(
+                f,
+                "I can't punish that member! Move my role above theirs in the server settings."
+            ),
+            KennelRefusal::TooShort(min) => write!(
+                f,
+                "Kennelings here have to last at least {}!",
+                humantime::format_duration(*min)
+            ),
+            KennelRefusal::TooLong(max) => write!(
+                f,
+                "You can only kennel people for up to {}!",
+                humantime::format_duration(*max)
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serenity::all::{GuildId, Permissions};
+
+    const OWNER: UserId = UserId::new(1);
+    const BOT: UserId = UserId::new(2);
+    const AUTHOR: UserId = UserId::new(3);
+    const VICTIM: UserId = UserId::new(4);
+
+    const KENNEL_ROLE: RoleId = RoleId::new(10);
+    const MEMBER_ROLE: RoleId = RoleId::new(11);
+    const MOD_ROLE: RoleId = RoleId::new(12);
+    const BOT_ROLE: RoleId = RoleId::new(13);
+    const ADMIN_ROLE: RoleId = RoleId::new(14);
+
+    /// The roles of a guild where the bot's role is above the mods', which is above everyone else's.
+    fn roles() -> HashMap<RoleId, Role> {
+        [
+            (KENNEL_ROLE, 1, Permissions::empty()),
+            (MEMBER_ROLE, 2, Permissions::empty()),
+            (MOD_ROLE, 3, Permissions::MODERATE_MEMBERS),
+            (BOT_ROLE, 4, Permissions::MANAGE_ROLES),
+            (ADMIN_ROLE, 5, Permissions::ADMINISTRATOR),
+        ]
+        .into_iter()
+        .map(|(id, position, permissions)| {
+            let mut role = Role::default();
+            role.id = id;
+            role.position = position;
+            role.permissions = permissions;
+
+            (id, role)
+        })
+        .collect()
+    }
+
+    fn member(user_id: UserId, role_ids: &[RoleId]) -> Member {
+        let mut member = Member::default();
+        member.user.id = user_id;
+        member.roles = role_ids.to_vec();
+
+        member
+    }
+
+    fn server() -> Server {
+        Server {
+            role_id: Some(KENNEL_ROLE),
+            ..Server::new(GuildId::new(1))
+        }
+    }
+
+    /// Checks whether a mod can kennel `victim` in the guild from [`roles`].
+    fn check(server: &Server, victim: &Member) -> Result<(), KennelRefusal> {
+        KennelRefusal::check_roles(
+            server,
+            OWNER,
+            &roles(),
+            &member(BOT, &[BOT_ROLE]),
+            &member(AUTHOR, &[MOD_ROLE]),
+            victim,
+        )
+    }
+
+    #[test]
+    fn mods_can_kennel_members_below_them() {
+        assert_eq!(check(&server(), &member(VICTIM, &[MEMBER_ROLE])), Ok(()));
+    }
+
+    #[test]
+    fn nobody_kennels_themselves() {
+        assert_eq!(
+            check(&server(), &member(AUTHOR, &[MOD_ROLE])),
+            Err(KennelRefusal::SelfKennel)
+        );
+    }
+
+    #[test]
+    fn bots_owners_and_admins_are_off_limits() {
+        let mut bot = member(VICTIM, &[]);
+        bot.user.bot = true;
+
+        assert_eq!(check(&server(), &bot), Err(KennelRefusal::Bot));
+        assert_eq!(
+            check(&server(), &member(OWNER, &[])),
+            Err(KennelRefusal::Owner)
+        );
+        assert_eq!(
+            check(&server(), &member(VICTIM, &[ADMIN_ROLE])),
+            Err(KennelRefusal::Administrator)
+        );
+    }
+
+    #[test]
+    fn immune_users_and_roles_are_off_limits() {
+        let immune_user = Server {
+            immune_users: vec![VICTIM],
+            ..server()
+        };
+        assert_eq!(
+            check(&immune_user, &member(VICTIM, &[])),
+            Err(KennelRefusal::ImmuneUser)
+        );
+
+        let immune_role = Server {
+            immune_roles: vec![MEMBER_ROLE],
+            ..server()
+        };
+        assert_eq!(
+            check(&immune_role, &member(VICTIM, &[MEMBER_ROLE])),
+            Err(KennelRefusal::ImmuneRole(MEMBER_ROLE))
+        );
+    }
+
+    #[test]
+    fn mods_can_only_kennel_members_below_them() {
+        assert_eq!(
+            check(&server(), &member(VICTIM, &[MOD_ROLE])),
+            Err(KennelRefusal::AuthorNotAbove)
+        );
+    }
+
+    #[test]
+    fn the_owner_can_kennel_anyone_the_bot_can() {
+        assert_eq!(
+            KennelRefusal::check_roles(
+                &server(),
+                OWNER,
+                &roles(),
+                &member(BOT, &[BOT_ROLE]),
+                &member(OWNER, &[]),
+                &member(VICTIM, &[MOD_ROLE]),
+            ),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn the_bot_has_to_be_above_the_kennel_role() {
+        let admin_kennel_role = Server {
+            role_id: Some(ADMIN_ROLE),
+            ..server()
+        };
+
+        assert_eq!(
+            check(&admin_kennel_role, &member(VICTIM, &[MEMBER_ROLE])),
+            Err(KennelRefusal::BotNotAboveKennelRole(ADMIN_ROLE))
+        );
+    }
+
+    #[test]
+    fn the_bot_has_to_be_above_victims_it_times_out_or_strips() {
+        let bot_level = member(VICTIM, &[BOT_ROLE]);
+        let owner_kennels = |server: &Server| {
+            KennelRefusal::check_roles(
+                server,
+                OWNER,
+                &roles(),
+                &member(BOT, &[BOT_ROLE]),
+                &member(OWNER, &[]),
+                &bot_level,
+            )
+        };
+
+        // Handing out a role doesn't need the bot to be above the victim.
+        assert_eq!(owner_kennels(&server()), Ok(()));
+
+        let timeouts = Server {
+            punishment_mode: PunishmentMode::Timeout,
+            ..server()
+        };
+        assert_eq!(
+            owner_kennels(&timeouts),
+            Err(KennelRefusal::BotNotAboveVictim)
+        );
+
+        let stripping = Server {
+            strip_roles: true,
+            ..server()
+        };
+        assert_eq!(
+            owner_kennels(&stripping),
+            Err(KennelRefusal::BotNotAboveVictim)
+        );
+    }
+}
@@ -0,0 +1,429 @@
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Datelike, NaiveDate, NaiveTime, TimeDelta, TimeZone, Utc, Weekday};
+use chrono_tz::Tz;
+use serenity::all::{GuildId, UserId};
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::NO_REASON;
+use crate::storage::Storage;
+use crate::types::kenneling::Kenneling;
+use crate::types::status::KennelingStatus;
+use crate::util::pgint_dur::{PgIntervalToDuration as _, to_interval};
+use crate::util::snowflake::from_snowflake;
+
+/// How late an occurrence can be turned into a kenneling and still last its whole length. Anything later, like
+/// occurrences that came up while the bot was offline, only kennels for what's left of it.
+const LATE_TOLERANCE: TimeDelta = TimeDelta::minutes(1);
+
+/// The days of the week a schedule runs on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Weekdays(u8);
+
+impl Weekdays {
+    pub const EVERY_DAY: Weekdays = Weekdays(0b111_1111);
+    pub const WEEKDAYS: Weekdays = Weekdays(0b001_1111);
+    pub const WEEKENDS: Weekdays = Weekdays(0b110_0000);
+
+    /// The representation stored in the `kennel_schedules` table: one bit per day, starting with Monday.
+    pub fn bits(&self) -> i16 {
+        self.0.into()
+    }
+
+    pub fn from_bits(bits: i16) -> Result<Self> {
+        u8::try_from(bits)
+            .ok()
+            .filter(|bits| (1..=Self::EVERY_DAY.0).contains(bits))
+            .map(Weekdays)
+            .ok_or_else(|| anyhow!("Invalid weekdays: {bits}"))
+    }
+
+    pub fn contains(&self, weekday: Weekday) -> bool {
+        self.0 & Self::bit(weekday) != 0
+    }
+
+    fn bit(weekday: Weekday) -> u8 {
+        1 << weekday.num_days_from_monday()
+    }
+}
+
+impl FromStr for Weekdays {
+    type Err = anyhow::Error;
+
+    /// Takes days (`mon`, `Tuesday`) and ranges of days (`mon-fri`, `sun-thu`), separated by commas, or one of
+    /// `every day`, `daily`, `weekdays` and `weekends`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().to_lowercase();
+
+        match s.as_str() {
+            "every day" | "daily" => return Ok(Self::EVERY_DAY),
+            "weekdays" => return Ok(Self::WEEKDAYS),
+            "weekends" => return Ok(Self::WEEKENDS),
+            _ => {}
+        }
+
+        let day = |day: &str| {
+            Weekday::from_str(day.trim())
+                .map_err(|_| anyhow!("'{}' isn't a day of the week", day.trim()))
+        };
+
+        let mut bits = 0;
+
+        for part in s.split(',') {
+            match part.split_once('-') {
+                Some((first, last)) => {
+                    let last = day(last)?;
+                    let mut weekday = day(first)?;
+
+                    // Ranges can wrap around the end of the week, like `fri-mon`.
+                    loop {
+                        bits |= Self::bit(weekday);
+
+                        if weekday == last {
+                            break;
+                        }
+
+                        weekday = weekday.succ();
+                    }
+                }
+                None => bits |= Self::bit(day(part)?),
+            }
+        }
+
+        Ok(Weekdays(bits))
+    }
+}
+
+impl std::fmt::Display for Weekdays {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            Self::EVERY_DAY => f.write_str("every day"),
+            Self::WEEKDAYS => f.write_str("weekdays"),
+            Self::WEEKENDS => f.write_str("weekends"),
+            _ => {
+                let days = [
+                    Weekday::Mon,
+                    Weekday::Tue,
+                    Weekday::Wed,
+                    Weekday::Thu,
+                    Weekday::Fri,
+                    Weekday::Sat,
+                    Weekday::Sun,
+                ]
+                .into_iter()
+                .filter(|weekday| self.contains(*weekday))
+                .map(|weekday| weekday.to_string())
+                .collect::<Vec<_>>();
+
+                f.write_str(&days.join(", "))
+            }
+        }
+    }
+}
+
+/// Represents the fields available from a query to the `kennel_schedules` table.
+#[derive(Debug)]
+pub struct KennelScheduleRow {
+    pub id: i32,
+    pub guild_id: i64,
+    pub victim_id: i64,
+    pub author_id: i64,
+    pub weekdays: i16,
+    pub start_time: NaiveTime,
+    pub timezone: String,
+    pub kennel_length: sqlx::postgres::types::PgInterval,
+    pub reason: Option<String>,
+    pub paused: bool,
+    pub next_run: sqlx::types::chrono::NaiveDateTime,
+}
+
+/// A kenneling that happens over and over, like a bedtime lockout every weeknight. Each time it comes up, it's turned
+/// into a kenneling of its own.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KennelSchedule {
+    pub id: Option<i32>,
+    pub guild_id: GuildId,
+    pub victim_id: UserId,
+    pub author_id: UserId,
+    pub weekdays: Weekdays,
+    /// The time of day each kenneling starts at, on the clock in `timezone`.
+    pub start_time: NaiveTime,
+    pub timezone: Tz,
+    pub kennel_length: Duration,
+    /// Why the victim is being kenneled, which is given to every kenneling.
+    pub reason: Option<String>,
+    /// Whether the schedule is on hold, so that nothing comes up until it's resumed.
+    pub paused: bool,
+    /// When the next kenneling starts.
+    pub next_run: DateTime<Utc>,
+}
+
+impl TryFrom<&KennelScheduleRow> for KennelSchedule {
+    type Error = anyhow::Error;
+
+    fn try_from(row: &KennelScheduleRow) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: Some(row.id),
+            guild_id: from_snowflake(row.guild_id)?,
+            victim_id: from_snowflake(row.victim_id)?,
+            author_id: from_snowflake(row.author_id)?,
+            weekdays: Weekdays::from_bits(row.weekdays)?,
+            start_time: row.start_time,
+            timezone: row
+                .timezone
+                .parse()
+                .map_err(|_| anyhow!("Unknown timezone: {}", row.timezone))?,
+            kennel_length: row.kennel_length.to_duration()?,
+            reason: row.reason.clone(),
+            paused: row.paused,
+            next_run: row.next_run.and_utc(),
+        })
+    }
+}
+
+impl TryFrom<&KennelSchedule> for KennelScheduleRow {
+    type Error = anyhow::Error;
+
+    fn try_from(schedule: &KennelSchedule) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: schedule.id.unwrap_or_default(),
+            guild_id: schedule.guild_id.into(),
+            victim_id: schedule.victim_id.into(),
+            author_id: schedule.author_id.into(),
+            weekdays: schedule.weekdays.bits(),
+            start_time: schedule.start_time,
+            timezone: schedule.timezone.name().to_string(),
+            kennel_length: to_interval(schedule.kennel_length)?,
+            reason: schedule.reason.clone(),
+            paused: schedule.paused,
+            next_run: schedule.next_run.naive_utc(),
+        })
+    }
+}
+
+impl KennelSchedule {
+    /// A new schedule, which first comes up the next time its start time does after `now`. It isn't stored until it's
+    /// inserted.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        guild_id: GuildId,
+        victim_id: UserId,
+        author_id: UserId,
+        weekdays: Weekdays,
+        start_time: NaiveTime,
+        timezone: Tz,
+        kennel_length: Duration,
+        now: DateTime<Utc>,
+    ) -> Result<Self> {
+        let mut schedule = Self {
+            id: None,
+            guild_id,
+            victim_id,
+            author_id,
+            weekdays,
+            start_time,
+            timezone,
+            kennel_length,
+            reason: None,
+            paused: false,
+            next_run: now,
+        };
+
+        schedule.next_run = schedule.occurrence_after(now)?;
+
+        Ok(schedule)
+    }
+
+    /// When this schedule next comes up after `after`.
+    pub fn occurrence_after(&self, after: DateTime<Utc>) -> Result<DateTime<Utc>> {
+        // The same weekday a week later comes up at the latest, even if today's start time already passed.
+        after
+            .with_timezone(&self.timezone)
+            .date_naive()
+            .iter_days()
+            .take(8)
+            .filter(|date| self.weekdays.contains(date.weekday()))
+            .filter_map(|date| self.start_on(date))
+            .find(|occurrence| *occurrence > after)
+            .ok_or_else(|| anyhow!("Schedule {:?} never comes up after {after}", self.id))
+    }
+
+    /// When the kenneling on `date` starts, on that day's clock.
+    fn start_on(&self, date: NaiveDate) -> Option<DateTime<Utc>> {
+        let start = date.and_time(self.start_time);
+
+        // When the clocks go back, the start time happens twice, and the first one counts. When they go forward, it
+        // might not happen at all, so the kenneling starts an hour later instead.
+        self.timezone
+            .from_local_datetime(&start)
+            .earliest()
+            .or_else(|| {
+                self.timezone
+                    .from_local_datetime(&(start + TimeDelta::hours(1)))
+                    .earliest()
+            })
+            .map(|start| start.to_utc())
+    }
+
+    /// Turns the occurrence that came up at `next_run` into a pending kenneling, which starts like any other scheduled
+    /// one, and moves the schedule on to its next occurrence. Occurrences that are already over by `now` are skipped.
+    /// Whether the author can still kennel the victim for that long is checked when it starts, in [`Kenneling::start`].
+    ///
+    /// Returns whether the schedule moved on, which it doesn't if it was paused, deleted or run by someone else first.
+    pub async fn run(&self, storage: &dyn Storage, now: DateTime<Utc>) -> Result<bool> {
+        let id = self
+            .id
+            .ok_or_else(|| anyhow!("Can't run a schedule that was never inserted"))?;
+
+        let (kenneled_at, kennel_length) = if now - self.next_run <= LATE_TOLERANCE {
+            (self.next_run, self.kennel_length)
+        } else {
+            let ends_at = self.next_run + self.kennel_length;
+
+            (now, (ends_at - now).to_std().unwrap_or_default())
+        };
+
+        let kenneling = (kennel_length >= Duration::from_secs(1)).then(|| Kenneling {
+            kenneled_at,
+            released_at: kenneled_at + kennel_length,
+            status: KennelingStatus::Pending,
+            reason: self.reason.clone(),
+            ..Kenneling::new(self.guild_id, self.victim_id, self.author_id, kennel_length)
+        });
+
+        storage
+            .advance_schedule(
+                id,
+                self.next_run,
+                self.occurrence_after(now)?,
+                kenneling.as_ref(),
+            )
+            .await
+    }
+
+    /// The reason for this schedule, or a placeholder if none was given.
+    pub fn display_reason(&self) -> &str {
+        self.reason.as_deref().unwrap_or(NO_REASON)
+    }
+
+    /// Describes when this schedule comes up, like "weekdays at 23:00 (Europe/Berlin) for 8h".
+    pub fn describe(&self) -> String {
+        format!(
+            "{} at {} ({}) for {}",
+            self.weekdays,
+            self.start_time.format("%H:%M"),
+            self.timezone,
+            humantime::format_duration(self.kennel_length)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schedule(weekdays: &str, start_time: &str, timezone: Tz) -> KennelSchedule {
+        KennelSchedule {
+            id: Some(1),
+            guild_id: GuildId::new(1),
+            victim_id: UserId::new(2),
+            author_id: UserId::new(3),
+            weekdays: weekdays.parse().unwrap(),
+            start_time: NaiveTime::parse_from_str(start_time, "%H:%M").unwrap(),
+            timezone,
+            kennel_length: Duration::from_secs(8 * 60 * 60),
+            reason: None,
+            paused: false,
+            next_run: DateTime::UNIX_EPOCH,
+        }
+    }
+
+    fn at(time: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(time).unwrap().to_utc()
+    }
+
+    #[test]
+    fn weekdays_parse_days_ranges_and_names() {
+        assert_eq!("mon-fri".parse::<Weekdays>().unwrap(), Weekdays::WEEKDAYS);
+        assert_eq!(
+            "Saturday, sun".parse::<Weekdays>().unwrap(),
+            Weekdays::WEEKENDS
+        );
+        assert_eq!("daily".parse::<Weekdays>().unwrap(), Weekdays::EVERY_DAY);
+        assert_eq!(
+            "sun-thu".parse::<Weekdays>().unwrap().to_string(),
+            "Mon, Tue, Wed, Thu, Sun"
+        );
+        assert!("someday".parse::<Weekdays>().is_err());
+    }
+
+    #[test]
+    fn weekdays_round_trip_through_their_bits() {
+        for weekdays in ["mon", "sun-thu", "weekends", "every day"] {
+            let weekdays = weekdays.parse::<Weekdays>().unwrap();
+
+            assert_eq!(Weekdays::from_bits(weekdays.bits()).unwrap(), weekdays);
+        }
+
+        assert!(Weekdays::from_bits(0).is_err());
+        assert!(Weekdays::from_bits(128).is_err());
+    }
+
+    #[test]
+    fn occurrences_skip_days_off() {
+        let schedule = schedule("weekdays", "23:00", Tz::UTC);
+
+        // Friday evening, then Monday, since the weekend is off.
+        assert_eq!(
+            schedule
+                .occurrence_after(at("2025-01-03T12:00:00Z"))
+                .unwrap(),
+            at("2025-01-03T23:00:00Z")
+        );
+        assert_eq!(
+            schedule
+                .occurrence_after(at("2025-01-03T23:00:00Z"))
+                .unwrap(),
+            at("2025-01-06T23:00:00Z")
+        );
+    }
+
+    #[test]
+    fn occurrences_follow_the_local_clock() {
+        let schedule = schedule("daily", "23:00", Tz::Europe__Berlin);
+
+        // Winter time is UTC+1, and summer time is UTC+2.
+        assert_eq!(
+            schedule
+                .occurrence_after(at("2025-01-01T12:00:00Z"))
+                .unwrap(),
+            at("2025-01-01T22:00:00Z")
+        );
+        assert_eq!(
+            schedule
+                .occurrence_after(at("2025-07-01T12:00:00Z"))
+                .unwrap(),
+            at("2025-07-01T21:00:00Z")
+        );
+    }
+
+    #[test]
+    fn occurrences_survive_daylight_saving_time() {
+        // The clocks in Berlin skipped from 02:00 to 03:00 on 2025-03-30, and went back from 03:00 to 02:00 on
+        // 2025-10-26.
+        let schedule = schedule("daily", "02:30", Tz::Europe__Berlin);
+
+        assert_eq!(
+            schedule
+                .occurrence_after(at("2025-03-29T12:00:00Z"))
+                .unwrap(),
+            at("2025-03-30T01:30:00Z")
+        );
+        assert_eq!(
+            schedule
+                .occurrence_after(at("2025-10-25T12:00:00Z"))
+                .unwrap(),
+            at("2025-10-26T00:30:00Z")
+        );
+    }
+}
@@ -1,45 +1,358 @@
-use serenity::all::{ChannelId, GuildId, RoleId};
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Utc};
+use serenity::all::{ChannelId, GuildId, Member, RoleId, UserId};
 
-use crate::string_to_id;
+use std::time::Duration;
+
+use crate::discord::Discord;
+use crate::storage::Storage;
+use crate::types::manual_release::ManualReleasePolicy;
+use crate::types::punishment::PunishmentMode;
+use crate::types::stacking::StackingPolicy;
+use crate::util::pgint_dur::PgIntervalToDuration as _;
+use crate::util::snowflake::{from_snowflake, from_snowflakes};
 
 /// Represents the fields available from a query to the `servers` table.
 #[derive(Debug)]
 pub struct ServerRow {
-    pub guild_id: String,
+    pub guild_id: i64,
     pub command_name: String,
     pub announcement_message: String,
     pub release_message: String,
-    pub role_id: String,
-    pub kennel_channel: Option<String>,
+    pub role_id: Option<i64>,
+    pub kennel_channel: Option<i64>,
     pub kennel_message: String,
+    pub stacking_policy: String,
+    pub punishment_mode: String,
+    pub strip_roles: bool,
+    pub immune_roles: Vec<i64>,
+    pub immune_users: Vec<i64>,
+    pub min_kennel_length: Option<sqlx::postgres::types::PgInterval>,
+    pub max_kennel_length: Option<sqlx::postgres::types::PgInterval>,
+    pub count_publicly: bool,
+    pub manual_release: String,
+    pub vote_threshold: Option<i32>,
+    pub vote_window: sqlx::postgres::types::PgInterval,
 }
 
 /// Information about a given Server from the database.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Server {
     pub guild_id: GuildId,
     pub command_name: String,
     pub announcement_message: String,
     pub release_message: String,
-    pub role_id: RoleId,
+    /// The kennel role. Only required when the punishment mode is [`PunishmentMode::Role`].
+    pub role_id: Option<RoleId>,
     pub kennel_channel: Option<ChannelId>,
     pub kennel_message: String,
+    pub stacking_policy: StackingPolicy,
+    pub punishment_mode: PunishmentMode,
+    /// Whether kenneled users have their other roles taken away for the duration of the kenneling.
+    pub strip_roles: bool,
+    /// Members with any of these roles can't be kenneled.
+    pub immune_roles: Vec<RoleId>,
+    /// These users can't be kenneled.
+    pub immune_users: Vec<UserId>,
+    /// The shortest anyone can be kenneled for.
+    pub min_kennel_length: Option<Duration>,
+    /// The longest anyone can be kenneled for, unless one of their roles says otherwise.
+    pub max_kennel_length: Option<Duration>,
+    /// Whether this server's kennelings count towards the bot's public stats.
+    pub count_publicly: bool,
+    /// What happens when someone takes the punishment off of a kenneled member by hand.
+    pub manual_release: ManualReleasePolicy,
+    /// How many members have to approve a `/vote_kennel` for it to pass, or deny it for it to fail. Voting is off
+    /// without one. At least 2, since the proposer's approval counts.
+    pub vote_threshold: Option<u32>,
+    /// How long a `/vote_kennel` stays open.
+    pub vote_window: Duration,
 }
 
 impl TryFrom<ServerRow> for Server {
-    type Error = std::num::ParseIntError;
+    type Error = anyhow::Error;
 
     fn try_from(row: ServerRow) -> Result<Self, Self::Error> {
         Ok(Self {
-            guild_id: string_to_id(&row.guild_id)?,
-            role_id: string_to_id(&row.role_id)?,
-            kennel_channel: row
-                .kennel_channel
-                .map(|kc| string_to_id::<ChannelId>(&kc).expect("Malformed channel id")),
+            guild_id: from_snowflake(row.guild_id)?,
+            role_id: row.role_id.map(from_snowflake).transpose()?,
+            kennel_channel: row.kennel_channel.map(from_snowflake).transpose()?,
             command_name: row.command_name,
             announcement_message: row.announcement_message,
             release_message: row.release_message,
             kennel_message: row.kennel_message,
+            stacking_policy: row.stacking_policy.parse()?,
+            punishment_mode: row.punishment_mode.parse()?,
+            strip_roles: row.strip_roles,
+            immune_roles: from_snowflakes(&row.immune_roles)?,
+            immune_users: from_snowflakes(&row.immune_users)?,
+            min_kennel_length: row
+                .min_kennel_length
+                .map(|min| min.to_duration())
+                .transpose()?,
+            max_kennel_length: row
+                .max_kennel_length
+                .map(|max| max.to_duration())
+                .transpose()?,
+            count_publicly: row.count_publicly,
+            manual_release: row.manual_release.parse()?,
+            vote_threshold: row.vote_threshold.map(u32::try_from).transpose()?,
+            vote_window: row.vote_window.to_duration()?,
         })
     }
 }
+
+impl Server {
+    /// A server with the default settings, which is what a server gets when it's first set up.
+    pub fn new(guild_id: GuildId) -> Self {
+        Self {
+            guild_id,
+            command_name: "kennel".to_string(),
+            announcement_message: "$victim has been locked away in the kennel.".to_string(),
+            release_message: "$victim has been released from the kennel.".to_string(),
+            role_id: None,
+            kennel_channel: None,
+            kennel_message: "You will return $return.'".to_string(),
+            stacking_policy: StackingPolicy::Reject,
+            punishment_mode: PunishmentMode::Role,
+            strip_roles: false,
+            immune_roles: Vec::new(),
+            immune_users: Vec::new(),
+            min_kennel_length: None,
+            max_kennel_length: None,
+            count_publicly: true,
+            manual_release: ManualReleasePolicy::Honor,
+            vote_threshold: None,
+            vote_window: Duration::from_secs(10 * 60),
+        }
+    }
+
+    /// The kennel role, or an error if the server is in role mode without one.
+    pub fn require_role(&self) -> Result<RoleId> {
+        self.role_id
+            .ok_or_else(|| anyhow!("Server {} has no kennel role set", self.guild_id))
+    }
+
+    /// Whether this server's punishment mode can keep someone kenneled until `released_at`.
+    pub fn can_enforce_until(&self, released_at: DateTime<Utc>) -> bool {
+        match self.punishment_mode.max_remaining() {
+            Some(max_remaining) => released_at <= Utc::now() + max_remaining,
+            None => true,
+        }
+    }
+
+    /// The roles to take off of a member while they're kenneled, if this server strips roles. Leaves out the kennel
+    /// role itself, and managed roles (bots, integrations, boosters), which can't be taken away.
+    pub async fn strippable_roles(
+        &self,
+        discord: &dyn Discord,
+        victim: &Member,
+    ) -> Result<Vec<RoleId>> {
+        if !self.strip_roles {
+            return Ok(Vec::new());
+        }
+
+        let guild_roles = discord.guild_roles(self.guild_id).await?;
+
+        Ok(victim
+            .roles
+            .iter()
+            .filter(|role_id| Some(**role_id) != self.role_id)
+            .filter(|role_id| {
+                guild_roles.iter().any(|role| {
+                    role.id == **role_id && !role.managed && !role.tags.premium_subscriber
+                })
+            })
+            .copied()
+            .collect())
+    }
+
+    /// Puts this server's punishment on a member. Timeouts last until `until`, roles until they're removed.
+    pub async fn add_punishment(
+        &self,
+        discord: &dyn Discord,
+        victim: &Member,
+        until: DateTime<Utc>,
+        audit_log_reason: &str,
+    ) -> Result<()> {
+        match self.punishment_mode {
+            PunishmentMode::Role => {
+                discord
+                    .add_role(
+                        self.guild_id,
+                        victim.user.id,
+                        self.require_role()?,
+                        audit_log_reason,
+                    )
+                    .await
+            }
+            PunishmentMode::Timeout => {
+                discord
+                    .set_timeout(self.guild_id, victim.user.id, Some(until), audit_log_reason)
+                    .await
+            }
+        }
+    }
+
+    /// Takes this server's punishment off of a member.
+    pub async fn remove_punishment(
+        &self,
+        discord: &dyn Discord,
+        victim: &Member,
+        audit_log_reason: &str,
+    ) -> Result<()> {
+        match self.punishment_mode {
+            PunishmentMode::Role => {
+                discord
+                    .remove_role(
+                        self.guild_id,
+                        victim.user.id,
+                        self.require_role()?,
+                        audit_log_reason,
+                    )
+                    .await
+            }
+            PunishmentMode::Timeout => {
+                discord
+                    .set_timeout(self.guild_id, victim.user.id, None, audit_log_reason)
+                    .await
+            }
+        }
+    }
+
+    /// Looks up who took this server's punishment off of a member, from the audit log. Only removals at `since` or
+    /// later count.
+    pub async fn punishment_removed_by(
+        &self,
+        discord: &dyn Discord,
+        user_id: UserId,
+        since: DateTime<Utc>,
+    ) -> Result<Option<UserId>> {
+        match self.punishment_mode {
+            PunishmentMode::Role => {
+                discord
+                    .role_removed_by(self.guild_id, user_id, self.require_role()?, since)
+                    .await
+            }
+            PunishmentMode::Timeout => {
+                discord
+                    .timeout_lifted_by(self.guild_id, user_id, since)
+                    .await
+            }
+        }
+    }
+
+    /// Switches this server over to a new punishment mode and saves it. Kennelings keep the punishment they were
+    /// given, which the new mode wouldn't take off again, and ones that haven't started were only checked against the
+    /// old mode (e.g. timeouts can't be as long as a role), so this is refused while there are any that haven't ended.
+    ///
+    /// Returns `false` if it was refused.
+    pub async fn change_punishment_mode(
+        &mut self,
+        storage: &dyn Storage,
+        mode: PunishmentMode,
+    ) -> Result<bool> {
+        if mode != self.punishment_mode && self.has_open_kennelings(storage).await? {
+            return Ok(false);
+        }
+
+        self.punishment_mode = mode;
+        storage.upsert_server(self).await?;
+
+        Ok(true)
+    }
+
+    /// Whether any kenneling in this server is running, paused, overdue or waiting to start.
+    async fn has_open_kennelings(&self, storage: &dyn Storage) -> Result<bool> {
+        let guild_id = Some(self.guild_id);
+
+        if !storage.running_kennelings(guild_id).await?.is_empty()
+            || !storage.pending_kennelings(guild_id).await?.is_empty()
+        {
+            return Ok(true);
+        }
+
+        let paused = storage.paused_kennelings().await?;
+        let due = storage.due_kennelings().await?;
+
+        Ok(paused
+            .iter()
+            .chain(&due)
+            .any(|kenneling| kenneling.guild_id == self.guild_id))
+    }
+
+    /// Switches this server over to a new kennel role and saves it. If the server punishes with a role, everyone
+    /// who is currently kenneled is moved from the old role to the new one.
+    pub async fn change_kennel_role(
+        &mut self,
+        discord: &dyn Discord,
+        storage: &dyn Storage,
+        role_id: RoleId,
+    ) -> Result<()> {
+        let existing_role_id = self.role_id.replace(role_id);
+
+        // Saved first, and the new role handed out before the old one is taken away, so that nobody looks like they
+        // were let out of the kennel by hand halfway through.
+        storage.upsert_server(self).await?;
+
+        let Some(existing_role_id) = existing_role_id.filter(|existing_role_id| {
+            self.punishment_mode == PunishmentMode::Role && *existing_role_id != role_id
+        }) else {
+            return Ok(());
+        };
+
+        let active_kennelings = storage.running_kennelings(Some(self.guild_id)).await?;
+
+        tracing::trace!(
+            "Moving {} active kennelings in guild {} to the new kennel role",
+            active_kennelings.len(),
+            self.guild_id
+        );
+
+        for kenneling in active_kennelings {
+            tracing::trace!("Updating kenneling: {kenneling:?}");
+
+            // Their kenneling is paused until they come back, at which point they get the new role.
+            if discord
+                .member(self.guild_id, kenneling.victim_id)
+                .await?
+                .is_none()
+            {
+                continue;
+            }
+
+            let audit_log_reason = "Kennel role changed";
+
+            discord
+                .add_role(
+                    self.guild_id,
+                    kenneling.victim_id,
+                    role_id,
+                    audit_log_reason,
+                )
+                .await?;
+            discord
+                .remove_role(
+                    self.guild_id,
+                    kenneling.victim_id,
+                    existing_role_id,
+                    audit_log_reason,
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Whether a member currently has this server's punishment on them.
+    pub fn has_punishment(&self, member: &Member) -> bool {
+        match self.punishment_mode {
+            PunishmentMode::Role => self
+                .role_id
+                .is_some_and(|role_id| member.roles.contains(&role_id)),
+            PunishmentMode::Timeout => member
+                .communication_disabled_until
+                .is_some_and(|until| until.unix_timestamp() > Utc::now().timestamp()),
+        }
+    }
+}
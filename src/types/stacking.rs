@@ -0,0 +1,190 @@
+use anyhow::anyhow;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::types::kenneling::Kenneling;
+
+/// What a server does when someone who is already kenneled gets kenneled again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, poise::ChoiceParameter)]
+pub enum StackingPolicy {
+    /// Refuse the new kenneling.
+    #[name = "Reject the new kenneling"]
+    Reject,
+    /// End the active kenneling and start the new one in its place.
+    #[name = "Replace the active kenneling"]
+    Replace,
+    /// Add the new length onto the active kenneling.
+    #[name = "Extend the active kenneling"]
+    Extend,
+    /// Keep whichever kenneling ends later.
+    #[name = "Keep whichever ends later"]
+    Max,
+}
+
+/// The result of applying a [`StackingPolicy`] to a new kenneling.
+#[derive(Debug)]
+pub enum StackingOutcome {
+    /// Nothing to stack with, so the new kenneling goes ahead as-is.
+    Insert,
+    /// The new kenneling is refused in favour of the active one.
+    Reject(Kenneling),
+    /// The active kenneling ends, and the new one is inserted.
+    Replace(Kenneling),
+    /// The active kenneling stays, with its length changed to the given one.
+    Adjust(Kenneling, Duration),
+}
+
+impl StackingPolicy {
+    /// The representation stored in the `servers` table.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StackingPolicy::Reject => "reject",
+            StackingPolicy::Replace => "replace",
+            StackingPolicy::Extend => "extend",
+            StackingPolicy::Max => "max",
+        }
+    }
+
+    /// Decides what happens to `new`, given the victim's `active` kenneling. This is the only place stacking rules
+    /// live, so that every path that kennels someone behaves the same.
+    pub fn resolve(self, active: Option<Kenneling>, new: &Kenneling) -> StackingOutcome {
+        let Some(active) = active else {
+            return StackingOutcome::Insert;
+        };
+
+        match self {
+            StackingPolicy::Reject => StackingOutcome::Reject(active),
+            StackingPolicy::Replace => StackingOutcome::Replace(active),
+            StackingPolicy::Extend => {
+                let kennel_length = active.kennel_length + new.kennel_length;
+
+                StackingOutcome::Adjust(active, kennel_length)
+            }
+            StackingPolicy::Max => {
+                let kennel_length = active.kennel_length
+                    + (new.released_at - active.released_at)
+                        .to_std()
+                        .unwrap_or_default();
+
+                StackingOutcome::Adjust(active, kennel_length)
+            }
+        }
+    }
+}
+
+impl FromStr for StackingPolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "reject" => Ok(StackingPolicy::Reject),
+            "replace" => Ok(StackingPolicy::Replace),
+            "extend" => Ok(StackingPolicy::Extend),
+            "max" => Ok(StackingPolicy::Max),
+            _ => Err(anyhow!("Unknown stacking policy: {s}")),
+        }
+    }
+}
+
+impl std::fmt::Display for StackingPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serenity::all::{GuildId, UserId};
+
+    fn minutes(minutes: u64) -> Duration {
+        Duration::from_secs(minutes * 60)
+    }
+
+    fn kenneling(kennel_length: Duration) -> Kenneling {
+        Kenneling::new(
+            GuildId::new(1),
+            UserId::new(2),
+            UserId::new(3),
+            kennel_length,
+        )
+    }
+
+    /// A kenneling of `kennel_length` that started `ago` minutes before `new`.
+    fn active(kennel_length: Duration, ago: u64, new: &Kenneling) -> Kenneling {
+        let kenneled_at = new.kenneled_at - minutes(ago);
+
+        Kenneling {
+            id: Some(1),
+            kenneled_at,
+            released_at: kenneled_at + kennel_length,
+            ..kenneling(kennel_length)
+        }
+    }
+
+    #[test]
+    fn nothing_to_stack_with_goes_ahead() {
+        let new = kenneling(minutes(30));
+
+        for policy in [
+            StackingPolicy::Reject,
+            StackingPolicy::Replace,
+            StackingPolicy::Extend,
+            StackingPolicy::Max,
+        ] {
+            assert!(matches!(
+                policy.resolve(None, &new),
+                StackingOutcome::Insert
+            ));
+        }
+    }
+
+    #[test]
+    fn reject_and_replace_leave_the_active_kenneling_alone() {
+        let new = kenneling(minutes(30));
+        let active = active(minutes(60), 10, &new);
+
+        assert!(matches!(
+            StackingPolicy::Reject.resolve(Some(active.clone()), &new),
+            StackingOutcome::Reject(rejected) if rejected == active
+        ));
+        assert!(matches!(
+            StackingPolicy::Replace.resolve(Some(active.clone()), &new),
+            StackingOutcome::Replace(replaced) if replaced == active
+        ));
+    }
+
+    #[test]
+    fn extend_adds_the_new_length_on() {
+        let new = kenneling(minutes(30));
+        let active = active(minutes(60), 10, &new);
+
+        assert!(matches!(
+            StackingPolicy::Extend.resolve(Some(active), &new),
+            StackingOutcome::Adjust(_, length) if length == minutes(90)
+        ));
+    }
+
+    #[test]
+    fn max_runs_until_the_new_kenneling_would_end() {
+        // The active one has 50 minutes left, and the new one would last 80.
+        let new = kenneling(minutes(80));
+        let active = active(minutes(60), 10, &new);
+
+        assert!(matches!(
+            StackingPolicy::Max.resolve(Some(active), &new),
+            StackingOutcome::Adjust(_, length) if length == minutes(90)
+        ));
+    }
+
+    #[test]
+    fn max_keeps_an_active_kenneling_that_ends_later() {
+        let new = kenneling(minutes(30));
+        let active = active(minutes(60), 10, &new);
+
+        assert!(matches!(
+            StackingPolicy::Max.resolve(Some(active), &new),
+            StackingOutcome::Adjust(_, length) if length == minutes(60)
+        ));
+    }
+}
@@ -0,0 +1,45 @@
+use serenity::all::UserId;
+use std::time::Duration;
+
+/// Totals for the kennelings in a single guild.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct GuildStats {
+    pub kennelings: i64,
+    /// How many different users have been kenneled.
+    pub victims: i64,
+    /// How long everyone has actually spent in the kennel, counting early releases as the time served.
+    pub total_length: Option<Duration>,
+    /// The average sentence handed out, however much of it was served.
+    pub average_length: Option<Duration>,
+    pub last_day: i64,
+    pub last_week: i64,
+    pub last_month: i64,
+}
+
+/// Which side of a kenneling a leaderboard ranks people by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Leaderboard {
+    /// The users who have been kenneled the most.
+    Victims,
+    /// The users who have kenneled people the most.
+    Kennelers,
+}
+
+/// One user's place on a [`Leaderboard`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LeaderboardEntry {
+    pub user_id: UserId,
+    pub kennelings: i64,
+    /// How long those kennelings lasted, counting early releases as the time served.
+    pub total_length: Duration,
+}
+
+/// Totals across every guild that counts towards the bot's public stats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PublicStats {
+    /// How long every counted kenneling lasted, counting early releases as the time served.
+    pub total_length: Option<Duration>,
+    /// Kennelings that are running right now.
+    pub active: i64,
+    pub guilds: i64,
+}
@@ -0,0 +1,73 @@
+use anyhow::anyhow;
+use std::str::FromStr;
+
+/// Where a kenneling is in its lifecycle, and if it's over, how it ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KennelingStatus {
+    /// The kenneling is scheduled to start later.
+    Pending,
+    /// The kenneling was called off before it started.
+    Cancelled,
+    /// The sentence is running.
+    Active,
+    /// The victim left the guild, so the sentence is on hold until they come back.
+    Paused,
+    /// The sentence ran out and the victim was let out.
+    ReleasedOnTime,
+    /// Somebody let the victim out before the sentence was up.
+    ReleasedEarly,
+    /// A new kenneling took this one's place.
+    Revoked,
+    /// The victim couldn't be let out, and the bot gave up trying.
+    Failed,
+}
+
+impl KennelingStatus {
+    /// Whether the kenneling is still going, even if it's on hold.
+    pub fn is_open(&self) -> bool {
+        matches!(self, KennelingStatus::Active | KennelingStatus::Paused)
+    }
+
+    /// Whether the kenneling ever actually started, which is what decides whether it counts towards the stats.
+    pub fn has_started(&self) -> bool {
+        !matches!(self, KennelingStatus::Pending | KennelingStatus::Cancelled)
+    }
+
+    /// The representation stored in the `kennelings` table.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            KennelingStatus::Pending => "pending",
+            KennelingStatus::Cancelled => "cancelled",
+            KennelingStatus::Active => "active",
+            KennelingStatus::Paused => "paused",
+            KennelingStatus::ReleasedOnTime => "released_on_time",
+            KennelingStatus::ReleasedEarly => "released_early",
+            KennelingStatus::Revoked => "revoked",
+            KennelingStatus::Failed => "failed",
+        }
+    }
+}
+
+impl FromStr for KennelingStatus {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pending" => Ok(KennelingStatus::Pending),
+            "cancelled" => Ok(KennelingStatus::Cancelled),
+            "active" => Ok(KennelingStatus::Active),
+            "paused" => Ok(KennelingStatus::Paused),
+            "released_on_time" => Ok(KennelingStatus::ReleasedOnTime),
+            "released_early" => Ok(KennelingStatus::ReleasedEarly),
+            "revoked" => Ok(KennelingStatus::Revoked),
+            "failed" => Ok(KennelingStatus::Failed),
+            _ => Err(anyhow!("Unknown kenneling status: {s}")),
+        }
+    }
+}
+
+impl std::fmt::Display for KennelingStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
@@ -0,0 +1,145 @@
+use serenity::all::UserId;
+use std::time::Duration;
+
+/// How long a vote can stay open, exclusive. A vote is updated through the interaction that started it, and Discord
+/// stops accepting those updates after 15 minutes.
+pub const MAX_VOTE_WINDOW: Duration = Duration::from_secs(15 * 60);
+
+/// A `/vote_kennel` that's still open: who voted which way, and how many votes it takes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KennelVote {
+    pub victim_id: UserId,
+    /// How many approvals it takes to pass, or denials to fail.
+    pub threshold: u32,
+    pub approvals: Vec<UserId>,
+    pub denials: Vec<UserId>,
+}
+
+/// Where a [`KennelVote`] stands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VoteOutcome {
+    /// Neither side has enough votes yet.
+    Open,
+    /// Enough members approved, so the victim gets kenneled.
+    Passed,
+    /// Enough members denied, so nothing happens.
+    Failed,
+}
+
+/// A reason a vote isn't counted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BallotRefusal {
+    /// The victim tried to vote on their own kenneling.
+    OwnKenneling,
+    /// The voter already voted.
+    AlreadyVoted,
+}
+
+impl std::fmt::Display for BallotRefusal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BallotRefusal::OwnKenneling => f.write_str("You can't vote on your own kenneling!"),
+            BallotRefusal::AlreadyVoted => f.write_str("You already voted!"),
+        }
+    }
+}
+
+impl KennelVote {
+    /// A new vote, which whoever proposed it approves of.
+    pub fn new(victim_id: UserId, proposer_id: UserId, threshold: u32) -> Self {
+        Self {
+            victim_id,
+            threshold,
+            approvals: vec![proposer_id],
+            denials: Vec::new(),
+        }
+    }
+
+    /// Counts a vote, and returns where the vote stands after it.
+    pub fn cast(&mut self, voter_id: UserId, approve: bool) -> Result<VoteOutcome, BallotRefusal> {
+        if voter_id == self.victim_id {
+            return Err(BallotRefusal::OwnKenneling);
+        }
+
+        if self.approvals.contains(&voter_id) || self.denials.contains(&voter_id) {
+            return Err(BallotRefusal::AlreadyVoted);
+        }
+
+        if approve {
+            self.approvals.push(voter_id);
+        } else {
+            self.denials.push(voter_id);
+        }
+
+        Ok(self.outcome())
+    }
+
+    pub fn outcome(&self) -> VoteOutcome {
+        let reached = |votes: &[UserId]| votes.len() >= self.threshold as usize;
+
+        if reached(&self.approvals) {
+            VoteOutcome::Passed
+        } else if reached(&self.denials) {
+            VoteOutcome::Failed
+        } else {
+            VoteOutcome::Open
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VICTIM: UserId = UserId::new(1);
+    const PROPOSER: UserId = UserId::new(2);
+
+    fn voter(id: u64) -> UserId {
+        UserId::new(10 + id)
+    }
+
+    #[test]
+    fn enough_approvals_pass() {
+        let mut vote = KennelVote::new(VICTIM, PROPOSER, 3);
+
+        assert_eq!(vote.outcome(), VoteOutcome::Open);
+        assert_eq!(vote.cast(voter(1), true), Ok(VoteOutcome::Open));
+        assert_eq!(vote.cast(voter(2), false), Ok(VoteOutcome::Open));
+        assert_eq!(vote.cast(voter(3), true), Ok(VoteOutcome::Passed));
+    }
+
+    #[test]
+    fn enough_denials_fail() {
+        let mut vote = KennelVote::new(VICTIM, PROPOSER, 2);
+
+        assert_eq!(vote.cast(voter(1), false), Ok(VoteOutcome::Open));
+        assert_eq!(vote.cast(voter(2), false), Ok(VoteOutcome::Failed));
+    }
+
+    #[test]
+    fn the_proposer_approves() {
+        let mut vote = KennelVote::new(VICTIM, PROPOSER, 2);
+
+        assert_eq!(vote.outcome(), VoteOutcome::Open);
+        assert_eq!(vote.cast(voter(1), true), Ok(VoteOutcome::Passed));
+    }
+
+    #[test]
+    fn victims_cant_vote() {
+        let mut vote = KennelVote::new(VICTIM, PROPOSER, 2);
+
+        assert_eq!(vote.cast(VICTIM, false), Err(BallotRefusal::OwnKenneling));
+        assert!(vote.denials.is_empty());
+    }
+
+    #[test]
+    fn nobody_votes_twice() {
+        let mut vote = KennelVote::new(VICTIM, PROPOSER, 5);
+
+        assert_eq!(vote.cast(PROPOSER, true), Err(BallotRefusal::AlreadyVoted));
+        assert_eq!(vote.cast(voter(1), false), Ok(VoteOutcome::Open));
+        assert_eq!(vote.cast(voter(1), true), Err(BallotRefusal::AlreadyVoted));
+        assert_eq!(vote.approvals, vec![PROPOSER]);
+        assert_eq!(vote.denials, vec![voter(1)]);
+    }
+}
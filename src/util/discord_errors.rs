@@ -0,0 +1,15 @@
+use serenity::all::HttpError;
+
+/// Discord's JSON error code for "Unknown Member".
+const UNKNOWN_MEMBER: isize = 10007;
+/// Discord's JSON error code for "Unknown User".
+const UNKNOWN_USER: isize = 10013;
+
+/// Whether a request failed because the user isn't (or is no longer) in the guild.
+pub fn is_missing_member(error: &serenity::Error) -> bool {
+    matches!(
+        error,
+        serenity::Error::Http(HttpError::UnsuccessfulRequest(response))
+            if matches!(response.error.code, UNKNOWN_MEMBER | UNKNOWN_USER)
+    )
+}
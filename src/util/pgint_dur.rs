@@ -2,14 +2,146 @@ use std::time::Duration;
 
 use sqlx::postgres::types::PgInterval;
 
+const MICROS_PER_DAY: i128 = 24 * 60 * 60 * 1_000_000;
+
+/// A length that can't be moved between the database and Rust without changing what it means.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InvalidInterval {
+    /// Months don't have a fixed length, so an interval with months in it isn't any one [`Duration`].
+    Months(PgInterval),
+    /// [`Duration`]s can't be negative.
+    Negative(PgInterval),
+    /// The [`Duration`] is longer than an interval can hold.
+    TooLong(Duration),
+}
+
+impl std::fmt::Display for InvalidInterval {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InvalidInterval::Months(interval) => {
+                write!(
+                    f,
+                    "{interval:?} has months in it, which have no fixed length"
+                )
+            }
+            InvalidInterval::Negative(interval) => write!(f, "{interval:?} is negative"),
+            InvalidInterval::TooLong(duration) => {
+                write!(f, "{duration:?} is too long to store as an interval")
+            }
+        }
+    }
+}
+
+impl std::error::Error for InvalidInterval {}
+
 pub trait PgIntervalToDuration {
-    fn as_duration(&self) -> Duration;
+    fn to_duration(&self) -> Result<Duration, InvalidInterval>;
 }
 
 impl PgIntervalToDuration for PgInterval {
-    fn as_duration(&self) -> Duration {
-        Duration::from_micros(self.microseconds as u64)
-            + Duration::from_secs(self.days as u64 * 24 * 60 * 60)
-            + Duration::from_secs(self.months as u64 * 30 * 24 * 60 * 60)
+    /// Days are always 24 hours long, since kennelings are stored as `timestamp`s, which don't have daylight saving
+    /// time. The days and microseconds can have different signs (like `1 day -1 hour`), so only the total has to be
+    /// positive.
+    fn to_duration(&self) -> Result<Duration, InvalidInterval> {
+        if self.months != 0 {
+            return Err(InvalidInterval::Months(*self));
+        }
+
+        let micros = i128::from(self.days) * MICROS_PER_DAY + i128::from(self.microseconds);
+
+        if micros < 0 {
+            return Err(InvalidInterval::Negative(*self));
+        }
+
+        // Up to `i32::MAX` days doesn't fit in a `u64` of microseconds, but the seconds always do.
+        Ok(Duration::new(
+            (micros / 1_000_000) as u64,
+            (micros % 1_000_000 * 1_000) as u32,
+        ))
+    }
+}
+
+/// Turns a length into something that can be bound to an `interval`. Intervals only go down to microseconds, so
+/// anything shorter is dropped.
+pub fn to_interval(duration: Duration) -> Result<PgInterval, InvalidInterval> {
+    Ok(PgInterval {
+        months: 0,
+        days: 0,
+        microseconds: duration
+            .as_micros()
+            .try_into()
+            .map_err(|_| InvalidInterval::TooLong(duration))?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    /// Durations that fit in an interval, down to the microsecond.
+    fn storable_duration() -> impl Strategy<Value = Duration> {
+        (0..=i64::MAX as u64).prop_map(Duration::from_micros)
+    }
+
+    proptest! {
+        #[test]
+        fn durations_round_trip(duration in storable_duration()) {
+            prop_assert_eq!(to_interval(duration).unwrap().to_duration(), Ok(duration));
+        }
+
+        #[test]
+        fn nanoseconds_are_dropped(duration in storable_duration(), nanos in 0..1_000u32) {
+            let with_nanos = duration + Duration::from_nanos(nanos.into());
+
+            prop_assert_eq!(to_interval(with_nanos), to_interval(duration));
+        }
+
+        #[test]
+        fn days_are_a_day_long(days in any::<i32>(), microseconds in any::<i64>()) {
+            let interval = PgInterval { months: 0, days, microseconds };
+            let total = i128::from(days) * MICROS_PER_DAY + i128::from(microseconds);
+
+            match interval.to_duration() {
+                Ok(duration) => {
+                    prop_assert!(total >= 0);
+                    prop_assert_eq!(duration.as_micros() as i128, total);
+                }
+                Err(e) => {
+                    prop_assert!(total < 0);
+                    prop_assert_eq!(e, InvalidInterval::Negative(interval));
+                }
+            }
+        }
+
+        #[test]
+        fn intervals_round_trip_to_the_same_length(
+            days in 0..106_751_990i32,
+            microseconds in 0..MICROS_PER_DAY as i64,
+        ) {
+            let interval = PgInterval { months: 0, days, microseconds };
+            let round_tripped = to_interval(interval.to_duration().unwrap()).unwrap();
+
+            prop_assert_eq!(round_tripped.to_duration(), interval.to_duration());
+        }
+
+        #[test]
+        fn months_are_rejected(
+            months in any::<i32>().prop_filter("months", |months| *months != 0),
+            days in any::<i32>(),
+            microseconds in any::<i64>(),
+        ) {
+            let interval = PgInterval { months, days, microseconds };
+
+            prop_assert_eq!(interval.to_duration(), Err(InvalidInterval::Months(interval)));
+        }
+    }
+
+    #[test]
+    fn too_long_durations_are_rejected() {
+        assert_eq!(
+            to_interval(Duration::MAX),
+            Err(InvalidInterval::TooLong(Duration::MAX))
+        );
     }
 }
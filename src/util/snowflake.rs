@@ -0,0 +1,29 @@
+use std::num::NonZeroU64;
+
+/// A Discord ID from the database that Discord could never have handed out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidSnowflake(pub i64);
+
+impl std::fmt::Display for InvalidSnowflake {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} is not a valid Discord ID", self.0)
+    }
+}
+
+impl std::error::Error for InvalidSnowflake {}
+
+/// Turns a `bigint` from the database into a Discord ID.
+///
+/// IDs go into the database with `i64::from`, which every serenity ID type implements.
+pub fn from_snowflake<Id: From<NonZeroU64>>(id: i64) -> Result<Id, InvalidSnowflake> {
+    u64::try_from(id)
+        .ok()
+        .and_then(NonZeroU64::new)
+        .map(Id::from)
+        .ok_or(InvalidSnowflake(id))
+}
+
+/// Turns a `bigint[]` from the database into Discord IDs.
+pub fn from_snowflakes<Id: From<NonZeroU64>>(ids: &[i64]) -> Result<Vec<Id>, InvalidSnowflake> {
+    ids.iter().map(|id| from_snowflake(*id)).collect()
+}
@@ -0,0 +1,141 @@
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Days, NaiveDateTime, NaiveTime, Utc};
+
+/// Formats accepted for a date and time, which are taken to be in UTC. They're matched against lowercased input, so the
+/// ISO `T` separator is written as a `t`.
+const DATE_TIME_FORMATS: [&str; 4] = [
+    "%Y-%m-%d %H:%M",
+    "%Y-%m-%d %H:%M:%S",
+    "%Y-%m-%dt%H:%M",
+    "%Y-%m-%dt%H:%M:%S",
+];
+
+/// Formats accepted for a time of day, which is taken to be in UTC.
+const TIME_FORMATS: [&str; 3] = ["%H:%M", "%I:%M%p", "%I:%M %p"];
+
+/// Works out when a scheduled kenneling should start, from what a moderator wrote.
+///
+/// Relative times look like `in 2h`, or just `2h`. Absolute times can be a Discord timestamp (`<t:1700000000:f>`), an
+/// RFC 3339 time with an offset, or a date and time (`2025-01-01 21:00`, `2025-01-01T21:00`) or time of day (`21:00`,
+/// `9pm`) in UTC. A time of day on its own means the next time the clock shows it.
+pub fn parse_start_time(input: &str, now: DateTime<Utc>) -> Result<DateTime<Utc>> {
+    let input = input.trim().to_lowercase();
+    let input = input.as_str();
+
+    let relative = input.strip_prefix("in ").unwrap_or(input);
+    if let Ok(duration) = humantime::parse_duration(relative) {
+        return chrono::Duration::from_std(duration)
+            .ok()
+            .and_then(|duration| now.checked_add_signed(duration))
+            .ok_or_else(|| anyhow!("That's too far away!"));
+    }
+
+    if let Some(timestamp) = input
+        .strip_prefix("<t:")
+        .and_then(|timestamp| timestamp.strip_suffix('>'))
+    {
+        let seconds = timestamp.split(':').next().unwrap_or_default();
+
+        return seconds
+            .parse()
+            .ok()
+            .and_then(|seconds| DateTime::from_timestamp(seconds, 0))
+            .ok_or_else(|| anyhow!("That isn't a valid Discord timestamp!"));
+    }
+
+    if let Ok(date_time) = DateTime::parse_from_rfc3339(input) {
+        return Ok(date_time.to_utc());
+    }
+
+    if let Some(date_time) = DATE_TIME_FORMATS
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(input, format).ok())
+    {
+        return Ok(date_time.and_utc());
+    }
+
+    if let Some(time) = parse_time_of_day(input) {
+        let today = now.date_naive().and_time(time).and_utc();
+
+        return if today > now {
+            Ok(today)
+        } else {
+            today
+                .checked_add_days(Days::new(1))
+                .ok_or_else(|| anyhow!("That's too far away!"))
+        };
+    }
+
+    Err(anyhow!(
+        "Couldn't tell when that is! Say something like 'in 2h', '21:00' (UTC), or paste a Discord timestamp"
+    ))
+}
+
+/// Reads a time of day, like `21:00`, `9pm` or `9:30 PM`.
+pub fn parse_time_of_day(input: &str) -> Option<NaiveTime> {
+    let input = input.trim().to_lowercase();
+
+    // Times like `9pm` leave out the minutes, which the parser can't do without.
+    let input = match input.find(['a', 'p']) {
+        Some(suffix) if !input.contains(':') => {
+            format!("{}:00{}", input[..suffix].trim(), &input[suffix..])
+        }
+        _ => input,
+    };
+
+    TIME_FORMATS
+        .iter()
+        .find_map(|format| NaiveTime::parse_from_str(&input, format).ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 2025-01-01 20:00 UTC.
+    fn now() -> DateTime<Utc> {
+        DateTime::from_timestamp(1_735_761_600, 0).unwrap()
+    }
+
+    fn at(input: &str) -> DateTime<Utc> {
+        parse_start_time(input, now()).unwrap()
+    }
+
+    #[test]
+    fn relative_times_count_from_now() {
+        assert_eq!(at("in 2h"), now() + chrono::Duration::hours(2));
+        assert_eq!(at("30m"), now() + chrono::Duration::minutes(30));
+    }
+
+    #[test]
+    fn absolute_times_are_taken_as_is() {
+        assert_eq!(at("<t:1735765200:f>"), now() + chrono::Duration::hours(1));
+        assert_eq!(at("<t:1735765200>"), now() + chrono::Duration::hours(1));
+        assert_eq!(
+            at("2025-01-01T22:00:00+01:00"),
+            now() + chrono::Duration::hours(1)
+        );
+        assert_eq!(at("2025-01-01 21:00"), now() + chrono::Duration::hours(1));
+        assert_eq!(at("2025-01-01T21:00"), now() + chrono::Duration::hours(1));
+        assert_eq!(
+            at("2025-01-01t21:00:00"),
+            now() + chrono::Duration::hours(1)
+        );
+    }
+
+    #[test]
+    fn times_of_day_are_the_next_one_coming_up() {
+        assert_eq!(at("21:00"), now() + chrono::Duration::hours(1));
+        assert_eq!(at("9pm"), now() + chrono::Duration::hours(1));
+        assert_eq!(at("9:30 PM"), now() + chrono::Duration::minutes(90));
+        assert_eq!(at("19:00"), now() + chrono::Duration::hours(23));
+        assert_eq!(at("8pm"), now() + chrono::Duration::days(1));
+    }
+
+    #[test]
+    fn nonsense_is_rejected() {
+        assert!(parse_start_time("whenever", now()).is_err());
+        assert!(parse_start_time("<t:soon>", now()).is_err());
+        assert!(parse_start_time("25:00", now()).is_err());
+    }
+}
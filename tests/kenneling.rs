@@ -0,0 +1,905 @@
+//! Kennels and releases people end to end, against in-memory storage and a fake Discord.
+
+use serenity::all::{ChannelId, GuildId, RoleId, UserId};
+use shame_bot::discord::fake::Call;
+use shame_bot::discord::{Discord as _, FakeDiscord};
+use shame_bot::storage::{MemoryStorage, Storage, no_effect};
+use shame_bot::types::*;
+use std::time::Duration;
+
+const GUILD: GuildId = GuildId::new(1);
+const VICTIM: UserId = UserId::new(2);
+const AUTHOR: UserId = UserId::new(3);
+const KENNEL_ROLE: RoleId = RoleId::new(4);
+const OTHER_ROLE: RoleId = RoleId::new(5);
+const NEW_KENNEL_ROLE: RoleId = RoleId::new(6);
+const KENNEL_CHANNEL: ChannelId = ChannelId::new(7);
+const MOD_ROLE: RoleId = RoleId::new(8);
+const BOT_ROLE: RoleId = RoleId::new(10);
+const OWNER: UserId = UserId::new(11);
+
+/// A guild with a kennel role and a kennel channel, and a victim who has one other role.
+async fn setup() -> (FakeDiscord, MemoryStorage, Server) {
+    let discord = FakeDiscord::new();
+    discord.add_guild_role(GUILD, KENNEL_ROLE);
+    discord.add_guild_role(GUILD, OTHER_ROLE);
+    discord.add_guild_role(GUILD, NEW_KENNEL_ROLE);
+    discord.add_guild_role(GUILD, MOD_ROLE);
+    discord.add_guild_role(GUILD, BOT_ROLE);
+    discord.add_member(GUILD, VICTIM, &[OTHER_ROLE]);
+    discord.add_member(GUILD, AUTHOR, &[MOD_ROLE]);
+    discord.add_member(GUILD, FakeDiscord::BOT_ID, &[BOT_ROLE]);
+    discord.set_guild_owner(GUILD, OWNER);
+
+    let storage = MemoryStorage::new();
+    let server = Server {
+        role_id: Some(KENNEL_ROLE),
+        kennel_channel: Some(KENNEL_CHANNEL),
+        ..Server::new(GUILD)
+    };
+    storage.upsert_server(&server).await.unwrap();
+
+    (discord, storage, server)
+}
+
+/// Kennels the victim the way `/kennel` does, minus the replies.
+async fn kennel(
+    discord: &FakeDiscord,
+    storage: &MemoryStorage,
+    server: &Server,
+    kennel_length: Duration,
+) -> Kenneling {
+    let victim = find_victim(discord).await.unwrap();
+    let stripped_roles = server.strippable_roles(discord, &victim).await.unwrap();
+    let kenneling = Kenneling {
+        status: KennelingStatus::Pending,
+        ..Kenneling::new(GUILD, VICTIM, AUTHOR, kennel_length)
+    };
+
+    let id = storage.insert_pending_kenneling(&kenneling).await.unwrap();
+
+    storage
+        .start(
+            id,
+            &stripped_roles,
+            None,
+            Box::new(|kenneling| {
+                Box::pin(async move {
+                    kenneling
+                        .apply_kennel(discord, server, None)
+                        .await
+                        .map(|_| ())
+                })
+            }),
+        )
+        .await
+        .unwrap()
+        .unwrap()
+}
+
+/// The victim, if they're in the guild.
+async fn find_victim(discord: &FakeDiscord) -> Option<serenity::all::Member> {
+    discord.member(GUILD, VICTIM).await.unwrap()
+}
+
+#[tokio::test]
+async fn kenneling_gives_the_kennel_role() {
+    let (discord, storage, server) = setup().await;
+
+    kennel(&discord, &storage, &server, Duration::from_secs(60)).await;
+
+    assert_eq!(
+        discord.roles_of(GUILD, VICTIM),
+        Some(vec![OTHER_ROLE, KENNEL_ROLE])
+    );
+    assert_eq!(
+        discord.calls(),
+        vec![Call::AddRole {
+            guild_id: GUILD,
+            user_id: VICTIM,
+            role_id: KENNEL_ROLE,
+        }]
+    );
+}
+
+#[tokio::test]
+async fn release_takes_the_kennel_role_away_once() {
+    let (discord, storage, server) = setup().await;
+    let kenneling = kennel(&discord, &storage, &server, Duration::from_secs(60)).await;
+
+    assert!(kenneling.release(&discord, &storage).await.unwrap());
+    assert!(!kenneling.release(&discord, &storage).await.unwrap());
+
+    assert_eq!(discord.roles_of(GUILD, VICTIM), Some(vec![OTHER_ROLE]));
+
+    let release_messages = discord
+        .calls()
+        .into_iter()
+        .filter(|call| matches!(call, Call::SendMessage { channel_id, .. } if *channel_id == KENNEL_CHANNEL))
+        .count();
+    assert_eq!(release_messages, 1);
+    assert!(
+        storage
+            .active_kenneling(GUILD, VICTIM)
+            .await
+            .unwrap()
+            .is_none()
+    );
+}
+
+#[tokio::test]
+async fn release_edits_the_announcement() {
+    let (discord, storage, server) = setup().await;
+    let mut kenneling = kennel(&discord, &storage, &server, Duration::from_secs(60)).await;
+
+    let announcement = discord
+        .send_message(KENNEL_CHANNEL, "Kenneled!")
+        .await
+        .unwrap();
+    kenneling
+        .set_announcement(&storage, (KENNEL_CHANNEL, announcement))
+        .await
+        .unwrap();
+
+    kenneling.release(&discord, &storage).await.unwrap();
+
+    assert_eq!(
+        discord.message(announcement),
+        Some(format!("<@{VICTIM}> has been released from the kennel."))
+    );
+}
+
+#[tokio::test]
+async fn stripped_roles_come_back_on_release() {
+    let (discord, storage, mut server) = setup().await;
+    server.strip_roles = true;
+    storage.upsert_server(&server).await.unwrap();
+
+    let kenneling = kennel(&discord, &storage, &server, Duration::from_secs(60)).await;
+
+    assert_eq!(kenneling.stripped_roles, vec![OTHER_ROLE]);
+    assert_eq!(discord.roles_of(GUILD, VICTIM), Some(vec![KENNEL_ROLE]));
+
+    kenneling.release(&discord, &storage).await.unwrap();
+
+    assert_eq!(discord.roles_of(GUILD, VICTIM), Some(vec![OTHER_ROLE]));
+}
+
+#[tokio::test]
+async fn timeouts_are_set_and_lifted() {
+    let (discord, storage, mut server) = setup().await;
+    server.punishment_mode = PunishmentMode::Timeout;
+    storage.upsert_server(&server).await.unwrap();
+
+    let kenneling = kennel(&discord, &storage, &server, Duration::from_secs(60)).await;
+
+    assert!(server.has_punishment(&find_victim(&discord).await.unwrap()));
+
+    kenneling.release(&discord, &storage).await.unwrap();
+
+    assert!(!server.has_punishment(&find_victim(&discord).await.unwrap()));
+    assert!(
+        discord
+            .calls()
+            .iter()
+            .all(|call| !matches!(call, Call::AddRole { .. } | Call::RemoveRole { .. }))
+    );
+}
+
+#[tokio::test]
+async fn removing_the_role_by_hand_ends_the_kenneling_early() {
+    let (discord, storage, server) = setup().await;
+    let kenneling = kennel(&discord, &storage, &server, Duration::from_secs(60 * 60)).await;
+
+    assert_eq!(
+        kenneling
+            .validate(
+                &discord,
+                &storage,
+                &server,
+                FakeDiscord::BOT_ID,
+                find_victim(&discord).await.as_ref()
+            )
+            .await
+            .unwrap(),
+        Validation::Intact
+    );
+
+    discord.remove_role_manually(GUILD, VICTIM, KENNEL_ROLE, AUTHOR);
+
+    let Validation::EndedEarly(time_served) = kenneling
+        .validate(
+            &discord,
+            &storage,
+            &server,
+            FakeDiscord::BOT_ID,
+            find_victim(&discord).await.as_ref(),
+        )
+        .await
+        .unwrap()
+    else {
+        panic!("The stale kenneling wasn't noticed");
+    };
+
+    assert!(time_served < Duration::from_secs(60));
+
+    let ended = &storage.history(GUILD, VICTIM).await.unwrap()[0];
+    assert_eq!(ended.status, KennelingStatus::ReleasedEarly);
+    assert_eq!(ended.ended_by, Some(AUTHOR));
+    assert_eq!(ended.kennel_length, Duration::from_secs(60 * 60));
+    assert!(storage.due_kennelings().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn member_updates_only_end_kennelings_someone_can_be_blamed_for() {
+    let (discord, storage, server) = setup().await;
+    let kenneling = kennel(&discord, &storage, &server, Duration::from_secs(60 * 60)).await;
+
+    discord
+        .remove_role(GUILD, VICTIM, KENNEL_ROLE, "Removed by hand")
+        .await
+        .unwrap();
+    let victim = find_victim(&discord).await.unwrap();
+
+    // Right after the start, the update may be about something from before the role went on.
+    assert_eq!(
+        kenneling
+            .validate_update(&discord, &storage, &server, FakeDiscord::BOT_ID, &victim)
+            .await
+            .unwrap(),
+        Validation::Intact
+    );
+
+    let kenneling = Kenneling {
+        kenneled_at: kenneling.kenneled_at - chrono::Duration::minutes(1),
+        ..kenneling
+    };
+
+    // Nobody in the audit log yet, so it's left to the healthcheck.
+    assert_eq!(
+        kenneling
+            .validate_update(&discord, &storage, &server, FakeDiscord::BOT_ID, &victim)
+            .await
+            .unwrap(),
+        Validation::Intact
+    );
+    assert!(
+        storage
+            .active_kenneling(GUILD, VICTIM)
+            .await
+            .unwrap()
+            .is_some()
+    );
+
+    discord.remove_role_manually(GUILD, VICTIM, KENNEL_ROLE, AUTHOR);
+
+    assert!(matches!(
+        kenneling
+            .validate_update(&discord, &storage, &server, FakeDiscord::BOT_ID, &victim)
+            .await
+            .unwrap(),
+        Validation::EndedEarly(_)
+    ));
+    assert_eq!(
+        storage.history(GUILD, VICTIM).await.unwrap()[0].ended_by,
+        Some(AUTHOR)
+    );
+}
+
+#[tokio::test]
+async fn removals_from_before_the_kenneling_are_not_blamed() {
+    let (discord, storage, server) = setup().await;
+
+    // From some earlier punishment, which this kenneling has nothing to do with.
+    discord.remove_role_manually(GUILD, VICTIM, KENNEL_ROLE, AUTHOR);
+    tokio::time::sleep(Duration::from_millis(1)).await;
+
+    let kenneling = kennel(&discord, &storage, &server, Duration::from_secs(60 * 60)).await;
+    discord
+        .remove_role(GUILD, VICTIM, KENNEL_ROLE, "Removed by hand")
+        .await
+        .unwrap();
+
+    assert!(matches!(
+        kenneling
+            .validate(
+                &discord,
+                &storage,
+                &server,
+                FakeDiscord::BOT_ID,
+                find_victim(&discord).await.as_ref()
+            )
+            .await
+            .unwrap(),
+        Validation::EndedEarly(_)
+    ));
+    assert_eq!(
+        storage.history(GUILD, VICTIM).await.unwrap()[0].ended_by,
+        None
+    );
+}
+
+#[tokio::test]
+async fn enforcing_servers_put_the_role_back_and_report_it() {
+    let (discord, storage, mut server) = setup().await;
+    server.manual_release = ManualReleasePolicy::Enforce;
+    storage.upsert_server(&server).await.unwrap();
+
+    let kenneling = kennel(&discord, &storage, &server, Duration::from_secs(60 * 60)).await;
+    let moderator = UserId::new(9);
+
+    discord.remove_role_manually(GUILD, VICTIM, KENNEL_ROLE, moderator);
+
+    assert_eq!(
+        kenneling
+            .validate(
+                &discord,
+                &storage,
+                &server,
+                FakeDiscord::BOT_ID,
+                find_victim(&discord).await.as_ref()
+            )
+            .await
+            .unwrap(),
+        Validation::Enforced {
+            removed_by: Some(moderator)
+        }
+    );
+    assert_eq!(
+        discord.roles_of(GUILD, VICTIM),
+        Some(vec![OTHER_ROLE, KENNEL_ROLE])
+    );
+    assert!(discord.calls().iter().any(|call| matches!(
+        call,
+        Call::SendMessage { channel_id, content }
+            if *channel_id == KENNEL_CHANNEL && content.contains(&format!("<@{moderator}>"))
+    )));
+
+    // The sentence wasn't touched.
+    assert_eq!(
+        storage
+            .active_kenneling(GUILD, VICTIM)
+            .await
+            .unwrap()
+            .unwrap()
+            .kennel_length,
+        Duration::from_secs(60 * 60)
+    );
+}
+
+#[tokio::test]
+async fn enforcing_servers_leave_the_bots_own_releases_alone() {
+    let (discord, storage, mut server) = setup().await;
+    server.manual_release = ManualReleasePolicy::Enforce;
+    storage.upsert_server(&server).await.unwrap();
+
+    let kenneling = kennel(&discord, &storage, &server, Duration::from_secs(60 * 60)).await;
+
+    discord.remove_role_manually(GUILD, VICTIM, KENNEL_ROLE, FakeDiscord::BOT_ID);
+
+    assert_eq!(
+        kenneling
+            .validate(
+                &discord,
+                &storage,
+                &server,
+                FakeDiscord::BOT_ID,
+                find_victim(&discord).await.as_ref()
+            )
+            .await
+            .unwrap(),
+        Validation::Intact
+    );
+    assert_eq!(discord.roles_of(GUILD, VICTIM), Some(vec![OTHER_ROLE]));
+}
+
+#[tokio::test]
+async fn leaving_pauses_and_rejoining_resumes() {
+    let (discord, storage, server) = setup().await;
+    let kenneling = kennel(&discord, &storage, &server, Duration::from_secs(60)).await;
+
+    discord.remove_member(GUILD, VICTIM);
+
+    assert_eq!(
+        kenneling
+            .validate(
+                &discord,
+                &storage,
+                &server,
+                FakeDiscord::BOT_ID,
+                find_victim(&discord).await.as_ref()
+            )
+            .await
+            .unwrap(),
+        Validation::Paused
+    );
+    assert_eq!(storage.paused_kennelings().await.unwrap().len(), 1);
+
+    discord.add_member(GUILD, VICTIM, &[OTHER_ROLE]);
+
+    assert!(
+        kenneling
+            .resume(&discord, &storage)
+            .await
+            .unwrap()
+            .is_some()
+    );
+    assert_eq!(
+        discord.roles_of(GUILD, VICTIM),
+        Some(vec![OTHER_ROLE, KENNEL_ROLE])
+    );
+}
+
+#[tokio::test]
+async fn changing_the_kennel_role_moves_active_kennelings() {
+    let (discord, storage, mut server) = setup().await;
+    kennel(&discord, &storage, &server, Duration::from_secs(60)).await;
+
+    server
+        .change_kennel_role(&discord, &storage, NEW_KENNEL_ROLE)
+        .await
+        .unwrap();
+
+    assert_eq!(
+        discord.roles_of(GUILD, VICTIM),
+        Some(vec![OTHER_ROLE, NEW_KENNEL_ROLE])
+    );
+    assert_eq!(
+        storage.server(GUILD).await.unwrap().unwrap().role_id,
+        Some(NEW_KENNEL_ROLE)
+    );
+}
+
+#[tokio::test]
+async fn changing_the_kennel_role_skips_members_who_left() {
+    let (discord, storage, mut server) = setup().await;
+    kennel(&discord, &storage, &server, Duration::from_secs(60)).await;
+
+    discord.remove_member(GUILD, VICTIM);
+
+    server
+        .change_kennel_role(&discord, &storage, NEW_KENNEL_ROLE)
+        .await
+        .unwrap();
+
+    assert_eq!(
+        storage.server(GUILD).await.unwrap().unwrap().role_id,
+        Some(NEW_KENNEL_ROLE)
+    );
+}
+
+#[tokio::test]
+async fn punishment_modes_only_change_while_nobody_is_kenneled() {
+    let (discord, storage, mut server) = setup().await;
+    let kenneling = kennel(&discord, &storage, &server, Duration::from_secs(60)).await;
+
+    assert!(
+        !server
+            .change_punishment_mode(&storage, PunishmentMode::Timeout)
+            .await
+            .unwrap()
+    );
+    assert_eq!(
+        storage
+            .server(GUILD)
+            .await
+            .unwrap()
+            .unwrap()
+            .punishment_mode,
+        PunishmentMode::Role
+    );
+
+    // Whatever the kenneling was given still comes off the way it went on.
+    kenneling.release(&discord, &storage).await.unwrap();
+    assert_eq!(discord.roles_of(GUILD, VICTIM), Some(vec![OTHER_ROLE]));
+
+    assert!(
+        server
+            .change_punishment_mode(&storage, PunishmentMode::Timeout)
+            .await
+            .unwrap()
+    );
+    assert_eq!(
+        storage
+            .server(GUILD)
+            .await
+            .unwrap()
+            .unwrap()
+            .punishment_mode,
+        PunishmentMode::Timeout
+    );
+}
+
+#[tokio::test]
+async fn punishment_modes_dont_change_under_paused_or_pending_kennelings() {
+    let (discord, storage, mut server) = setup().await;
+
+    let pending = schedule(&storage, Duration::from_secs(60)).await;
+    assert!(
+        !server
+            .change_punishment_mode(&storage, PunishmentMode::Timeout)
+            .await
+            .unwrap()
+    );
+
+    // Starting it while the victim is away leaves it paused.
+    discord.remove_member(GUILD, VICTIM);
+    assert_eq!(
+        pending
+            .start(&discord, &storage, FakeDiscord::BOT_ID)
+            .await
+            .unwrap(),
+        StartOutcome::Paused
+    );
+    assert!(
+        !server
+            .change_punishment_mode(&storage, PunishmentMode::Timeout)
+            .await
+            .unwrap()
+    );
+    assert_eq!(server.punishment_mode, PunishmentMode::Role);
+}
+
+#[tokio::test]
+async fn running_kennelings_are_grouped_by_server() {
+    let (discord, storage, server) = setup().await;
+    kennel(&discord, &storage, &server, Duration::from_secs(60)).await;
+
+    let other_guild = GuildId::new(8);
+    storage
+        .upsert_server(&Server::new(other_guild))
+        .await
+        .unwrap();
+    for (victim_id, author_id) in [(VICTIM, AUTHOR), (AUTHOR, VICTIM)] {
+        let id = storage
+            .insert_pending_kenneling(&Kenneling::new(
+                other_guild,
+                victim_id,
+                author_id,
+                Duration::from_secs(60),
+            ))
+            .await
+            .unwrap();
+        storage
+            .start(id, &[], None, no_effect())
+            .await
+            .unwrap()
+            .unwrap();
+    }
+
+    let mut running: Vec<_> = storage
+        .running_kennelings_by_server()
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|(server, kennelings)| (server.guild_id, kennelings.len()))
+        .collect();
+    running.sort();
+
+    assert_eq!(running, vec![(GUILD, 1), (other_guild, 2)]);
+}
+
+/// Schedules a kenneling of the victim that's due to start right away, the way `/kennel` does with a start time.
+async fn schedule(storage: &MemoryStorage, kennel_length: Duration) -> Kenneling {
+    let start_at = chrono::Utc::now() - chrono::Duration::seconds(1);
+    let kenneling = Kenneling {
+        kenneled_at: start_at,
+        released_at: start_at + kennel_length,
+        status: KennelingStatus::Pending,
+        ..Kenneling::new(GUILD, VICTIM, AUTHOR, kennel_length)
+    };
+
+    let id = storage.insert_pending_kenneling(&kenneling).await.unwrap();
+
+    Kenneling {
+        id: Some(id),
+        ..kenneling
+    }
+}
+
+#[tokio::test]
+async fn scheduled_kennelings_start_when_due() {
+    let (discord, storage, _) = setup().await;
+    let pending = schedule(&storage, Duration::from_secs(60)).await;
+
+    assert_eq!(
+        storage.starting_kennelings().await.unwrap(),
+        vec![pending.clone()]
+    );
+    assert!(
+        storage
+            .active_kenneling(GUILD, VICTIM)
+            .await
+            .unwrap()
+            .is_none()
+    );
+
+    assert_eq!(
+        pending
+            .start(&discord, &storage, FakeDiscord::BOT_ID)
+            .await
+            .unwrap(),
+        StartOutcome::Started
+    );
+    assert_eq!(
+        pending
+            .start(&discord, &storage, FakeDiscord::BOT_ID)
+            .await
+            .unwrap(),
+        StartOutcome::Skipped
+    );
+
+    assert_eq!(
+        discord.roles_of(GUILD, VICTIM),
+        Some(vec![OTHER_ROLE, KENNEL_ROLE])
+    );
+    assert!(storage.starting_kennelings().await.unwrap().is_empty());
+    assert_eq!(
+        storage
+            .active_kenneling(GUILD, VICTIM)
+            .await
+            .unwrap()
+            .unwrap()
+            .status,
+        KennelingStatus::Active
+    );
+}
+
+#[tokio::test]
+async fn scheduled_kennelings_are_called_off_if_they_are_not_allowed_anymore() {
+    let (discord, storage, server) = setup().await;
+    storage
+        .upsert_server(&Server {
+            immune_roles: vec![NEW_KENNEL_ROLE],
+            ..server
+        })
+        .await
+        .unwrap();
+
+    let pending = schedule(&storage, Duration::from_secs(60)).await;
+    discord.add_member(GUILD, VICTIM, &[OTHER_ROLE, NEW_KENNEL_ROLE]);
+
+    assert_eq!(
+        pending
+            .start(&discord, &storage, FakeDiscord::BOT_ID)
+            .await
+            .unwrap(),
+        StartOutcome::Cancelled(KennelRefusal::ImmuneRole(NEW_KENNEL_ROLE).to_string())
+    );
+    assert_eq!(
+        discord.roles_of(GUILD, VICTIM),
+        Some(vec![OTHER_ROLE, NEW_KENNEL_ROLE])
+    );
+
+    let pending = schedule(&storage, Duration::from_secs(60)).await;
+    discord.add_member(GUILD, VICTIM, &[OTHER_ROLE]);
+    discord.remove_member(GUILD, AUTHOR);
+
+    assert_eq!(
+        pending
+            .start(&discord, &storage, FakeDiscord::BOT_ID)
+            .await
+            .unwrap(),
+        StartOutcome::Cancelled(format!("<@{AUTHOR}> isn't in the server anymore"))
+    );
+    assert_eq!(discord.roles_of(GUILD, VICTIM), Some(vec![OTHER_ROLE]));
+    assert!(storage.starting_kennelings().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn cancelled_kennelings_never_start() {
+    let (discord, storage, _) = setup().await;
+    let pending = schedule(&storage, Duration::from_secs(60)).await;
+
+    let cancelled = pending
+        .cancel(&discord, &storage, Some(AUTHOR), Some("Changed my mind"))
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(cancelled.status, KennelingStatus::Cancelled);
+    assert_eq!(cancelled.ended_by, Some(AUTHOR));
+
+    assert_eq!(
+        pending
+            .start(&discord, &storage, FakeDiscord::BOT_ID)
+            .await
+            .unwrap(),
+        StartOutcome::Skipped
+    );
+    assert!(storage.starting_kennelings().await.unwrap().is_empty());
+    assert_eq!(discord.roles_of(GUILD, VICTIM), Some(vec![OTHER_ROLE]));
+}
+
+#[tokio::test]
+async fn scheduled_kennelings_of_absent_members_start_paused() {
+    let (discord, storage, _) = setup().await;
+    let pending = schedule(&storage, Duration::from_secs(60)).await;
+
+    discord.remove_member(GUILD, VICTIM);
+
+    assert_eq!(
+        pending
+            .start(&discord, &storage, FakeDiscord::BOT_ID)
+            .await
+            .unwrap(),
+        StartOutcome::Paused
+    );
+    assert_eq!(storage.paused_kennelings().await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn scheduled_kennelings_follow_the_stacking_policy() {
+    let (discord, storage, server) = setup().await;
+    storage
+        .upsert_server(&Server {
+            stacking_policy: StackingPolicy::Reject,
+            ..server.clone()
+        })
+        .await
+        .unwrap();
+    kennel(&discord, &storage, &server, Duration::from_secs(60)).await;
+    let pending = schedule(&storage, Duration::from_secs(60)).await;
+
+    assert!(matches!(
+        pending
+            .start(&discord, &storage, FakeDiscord::BOT_ID)
+            .await
+            .unwrap(),
+        StartOutcome::Cancelled(_)
+    ));
+
+    let history = storage.history(GUILD, VICTIM).await.unwrap();
+    assert_eq!(history.len(), 2);
+    assert_eq!(
+        history
+            .iter()
+            .filter(|kenneling| kenneling.status == KennelingStatus::Cancelled)
+            .count(),
+        1
+    );
+}
+
+/// A daily schedule for the victim whose occurrence came up `late` ago.
+async fn due_schedule(storage: &MemoryStorage, late: chrono::TimeDelta) -> KennelSchedule {
+    let now = chrono::Utc::now();
+    let schedule = KennelSchedule {
+        next_run: now - late,
+        ..KennelSchedule::new(
+            GUILD,
+            VICTIM,
+            AUTHOR,
+            Weekdays::EVERY_DAY,
+            (now - late).time(),
+            chrono_tz::Tz::UTC,
+            Duration::from_secs(60 * 60),
+            now,
+        )
+        .unwrap()
+    };
+
+    let id = storage.insert_schedule(&schedule).await.unwrap();
+
+    KennelSchedule {
+        id: Some(id),
+        ..schedule
+    }
+}
+
+#[tokio::test]
+async fn schedules_kennel_through_the_usual_start() {
+    let (discord, storage, _) = setup().await;
+    let schedule = due_schedule(&storage, chrono::TimeDelta::zero()).await;
+
+    assert_eq!(
+        storage.due_schedules().await.unwrap(),
+        vec![schedule.clone()]
+    );
+    assert!(schedule.run(&storage, chrono::Utc::now()).await.unwrap());
+    assert!(!schedule.run(&storage, chrono::Utc::now()).await.unwrap());
+    assert!(storage.due_schedules().await.unwrap().is_empty());
+
+    let starting = storage.starting_kennelings().await.unwrap();
+    assert_eq!(starting.len(), 1);
+    assert_eq!(starting[0].kennel_length, Duration::from_secs(60 * 60));
+    assert_eq!(
+        starting[0]
+            .start(&discord, &storage, FakeDiscord::BOT_ID)
+            .await
+            .unwrap(),
+        StartOutcome::Started
+    );
+    assert_eq!(
+        discord.roles_of(GUILD, VICTIM),
+        Some(vec![OTHER_ROLE, KENNEL_ROLE])
+    );
+
+    // The next one is a day later, since the schedule runs every day.
+    let next_run = storage.schedules(GUILD).await.unwrap()[0].next_run;
+    assert_eq!(next_run, schedule.next_run + chrono::TimeDelta::days(1));
+}
+
+#[tokio::test]
+async fn late_schedules_only_kennel_for_whats_left() {
+    let (_, storage, _) = setup().await;
+    let schedule = due_schedule(&storage, chrono::TimeDelta::minutes(45)).await;
+
+    assert!(schedule.run(&storage, chrono::Utc::now()).await.unwrap());
+
+    let starting = storage.starting_kennelings().await.unwrap();
+    assert_eq!(starting.len(), 1);
+    assert!(starting[0].kennel_length <= Duration::from_secs(15 * 60));
+    assert!(starting[0].kennel_length > Duration::from_secs(14 * 60));
+}
+
+#[tokio::test]
+async fn paused_schedules_dont_run() {
+    let (_, storage, _) = setup().await;
+    let schedule = due_schedule(&storage, chrono::TimeDelta::zero()).await;
+
+    assert!(
+        storage
+            .set_schedule_paused(GUILD, schedule.id.unwrap(), true, schedule.next_run)
+            .await
+            .unwrap()
+    );
+
+    assert!(storage.due_schedules().await.unwrap().is_empty());
+    assert!(!schedule.run(&storage, chrono::Utc::now()).await.unwrap());
+    assert!(storage.starting_kennelings().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn community_votes_are_nobodys_kenneling() {
+    let (discord, storage, server) = setup().await;
+    storage
+        .upsert_server(&Server {
+            announcement_message: "$victim was kenneled by $kenneler.".to_string(),
+            ..server.clone()
+        })
+        .await
+        .unwrap();
+
+    let vote_message = discord
+        .send_message(KENNEL_CHANNEL, "Vote passed!")
+        .await
+        .unwrap();
+    let mut kenneling = Kenneling {
+        status: KennelingStatus::Pending,
+        announcement: Some((KENNEL_CHANNEL, vote_message)),
+        community_vote: true,
+        ..Kenneling::new(GUILD, VICTIM, AUTHOR, Duration::from_secs(60))
+    };
+    kenneling.id = Some(storage.insert_pending_kenneling(&kenneling).await.unwrap());
+
+    assert_eq!(
+        kenneling
+            .start(&discord, &storage, FakeDiscord::BOT_ID)
+            .await
+            .unwrap(),
+        StartOutcome::Started
+    );
+
+    assert_eq!(
+        discord.roles_of(GUILD, VICTIM),
+        Some(vec![OTHER_ROLE, KENNEL_ROLE])
+    );
+    assert_eq!(
+        discord.message(vote_message),
+        Some(format!("<@{VICTIM}> was kenneled by community vote."))
+    );
+    assert!(
+        storage
+            .leaderboard(GUILD, Leaderboard::Kennelers, 10)
+            .await
+            .unwrap()
+            .is_empty()
+    );
+    assert_eq!(
+        storage
+            .leaderboard(GUILD, Leaderboard::Victims, 10)
+            .await
+            .unwrap()
+            .len(),
+        1
+    );
+}